@@ -0,0 +1,50 @@
+// Compares transforming a batch of small records one by one with `Template::transform` against
+// `Template::transform_each`, which is parallel with the rayon feature:
+// cargo bench --features rayon
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Value};
+use transformer_rs::Template;
+
+const RECORDS: usize = 100_000;
+
+fn transform_each_benchmark(c: &mut Criterion) {
+    let template = Template::new(&json!([{
+        "order": {
+            "id": "/order/id",
+            "account_id": "/retailer/id",
+            "source": "'edi'",
+            "[items]": {"...sku": "/order/items/sku", "...quantity": "/order/items/quantity"}
+        }
+    }]))
+    .unwrap();
+    let inputs: Vec<Value> = (0..RECORDS)
+        .map(|i| {
+            json!({
+                "retailer": {"id": i % 100},
+                "order": {
+                    "id": i,
+                    "items": [{"sku": "SKU-1", "quantity": 1}, {"sku": "SKU-2", "quantity": 2}]
+                }
+            })
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("transform_each");
+    group.sample_size(10);
+    group.bench_function("serial", |b| {
+        b.iter(|| {
+            inputs
+                .iter()
+                .map(|input| template.transform(black_box(input)))
+                .collect::<anyhow::Result<Vec<Value>>>()
+                .unwrap()
+        })
+    });
+    group.bench_function("transform_each", |b| {
+        b.iter(|| template.transform_each(black_box(&inputs)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, transform_each_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+// Transforms a large input with many scalar mappings, most of the time goes into looking up and
+// copying the mapped values, and printing or cloning the output template:
+// cargo bench --bench transform_large
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Map, Value};
+use transformer_rs::Template;
+
+const FIELDS: usize = 500;
+const ITEMS: usize = 10_000;
+
+fn transform_large_benchmark(c: &mut Criterion) {
+    let mut fields = Map::new();
+    let mut mappings = Map::new();
+    for i in 0..FIELDS {
+        fields.insert(format!("field_{}", i), json!(format!("value {}", i)));
+        mappings.insert(
+            format!("field_{}", i),
+            Value::from(format!("/order/fields/field_{}", i)),
+        );
+    }
+    mappings.insert("[items]".to_string(), json!({"...sku": "/order/items/sku"}));
+    let template = Template::new(&json!([{ "order": mappings }])).unwrap();
+    let items: Vec<Value> = (0..ITEMS)
+        .map(|i| json!({"sku": format!("SKU-{}", i), "quantity": i}))
+        .collect();
+    let input = json!({"order": {"fields": fields, "items": items}});
+
+    c.bench_function("transform_large", |b| {
+        b.iter(|| template.transform(black_box(&input)).unwrap())
+    });
+}
+
+criterion_group!(benches, transform_large_benchmark);
+criterion_main!(benches);
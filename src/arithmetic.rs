@@ -0,0 +1,157 @@
+use anyhow::{anyhow, bail, Result};
+use core::convert::TryFrom;
+use serde_json::Number;
+
+// Arithmetic operator of the `add`, `sub`, `mul` and `div` functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+// Applies the operator to two JSON numbers. Integers stay integers when the result is exact: a
+// division of integers is an integer if the divisor divides the dividend, `10 / 2` -> `5`, and a
+// float otherwise, `10 / 4` -> `2.5`. An integer result that overflows `i64` and `u64` becomes a
+// float. A float operand gives a float, even if its value is integral, `2.5 * 2` -> `5.0`. A
+// division by zero and a result that is not a finite number are errors.
+pub fn apply(operator: Operator, a: &Number, b: &Number) -> Result<Number> {
+    if operator == Operator::Div && b.as_f64() == Some(0.0) {
+        bail!("division by zero: {} / {}", a, b);
+    }
+    if let (Some(a), Some(b)) = (integer(a), integer(b)) {
+        let result = match operator {
+            Operator::Add => a.checked_add(b),
+            Operator::Sub => a.checked_sub(b),
+            Operator::Mul => a.checked_mul(b),
+            Operator::Div if a % b == 0 => Some(a / b),
+            Operator::Div => None,
+        };
+        let number = result.and_then(|result| {
+            i64::try_from(result)
+                .map(Number::from)
+                .or_else(|_| u64::try_from(result).map(Number::from))
+                .ok()
+        });
+        if let Some(number) = number {
+            return Ok(number);
+        }
+    }
+    let (a, b) = (
+        a.as_f64().unwrap_or_default(),
+        b.as_f64().unwrap_or_default(),
+    );
+    let result = match operator {
+        Operator::Add => a + b,
+        Operator::Sub => a - b,
+        Operator::Mul => a * b,
+        Operator::Div => a / b,
+    };
+    Number::from_f64(result).ok_or_else(|| anyhow!("the result is not a finite number: {}", result))
+}
+
+// the value of an integer JSON number, `None` for floats
+fn integer(n: &Number) -> Option<i128> {
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn apply_values(operator: Operator, a: Value, b: Value) -> Result<Value> {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => apply(operator, &a, &b).map(Value::Number),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_apply_integers() {
+        assert_eq!(
+            apply_values(Operator::Add, json!(1), json!(2)).unwrap(),
+            json!(3)
+        );
+        assert_eq!(
+            apply_values(Operator::Sub, json!(1), json!(2)).unwrap(),
+            json!(-1)
+        );
+        assert_eq!(
+            apply_values(Operator::Mul, json!(-4), json!(3)).unwrap(),
+            json!(-12)
+        );
+
+        // an exact division stays an integer, a non-exact one is a float
+        let quotient = apply_values(Operator::Div, json!(10), json!(2)).unwrap();
+        assert_eq!(quotient, json!(5));
+        assert!(quotient.is_i64());
+        assert_eq!(quotient.to_string(), "5");
+        let quotient = apply_values(Operator::Div, json!(10), json!(3)).unwrap();
+        assert!(quotient.is_f64());
+        assert_eq!(quotient, json!(10.0 / 3.0));
+        assert_eq!(
+            apply_values(Operator::Div, json!(-9), json!(3)).unwrap(),
+            json!(-3)
+        );
+
+        // u64 integers above i64::MAX stay integers
+        assert_eq!(
+            apply_values(Operator::Sub, json!(u64::MAX), json!(1)).unwrap(),
+            json!(u64::MAX - 1)
+        );
+        assert_eq!(
+            apply_values(Operator::Add, json!(i64::MIN), json!(u64::MAX)).unwrap(),
+            json!(i64::MAX as u64)
+        );
+        // overflows become floats
+        let sum = apply_values(Operator::Add, json!(u64::MAX), json!(1)).unwrap();
+        assert!(sum.is_f64());
+        assert_eq!(sum, json!(u64::MAX as f64 + 1.0));
+        assert!(
+            apply_values(Operator::Mul, json!(u64::MAX), json!(u64::MAX))
+                .unwrap()
+                .is_f64()
+        );
+    }
+
+    #[test]
+    fn test_apply_floats() {
+        let product = apply_values(Operator::Mul, json!(2.5), json!(2)).unwrap();
+        assert!(product.is_f64());
+        assert_eq!(product.to_string(), "5.0");
+        assert_eq!(
+            apply_values(Operator::Add, json!(0.5), json!(0.25)).unwrap(),
+            json!(0.75)
+        );
+        assert_eq!(
+            apply_values(Operator::Div, json!(1.0), json!(4)).unwrap(),
+            json!(0.25)
+        );
+    }
+
+    #[test]
+    fn test_apply_err() {
+        assert_eq!(
+            apply_values(Operator::Div, json!(1), json!(0))
+                .unwrap_err()
+                .to_string(),
+            "division by zero: 1 / 0"
+        );
+        assert_eq!(
+            apply_values(Operator::Div, json!(1.5), json!(0.0))
+                .unwrap_err()
+                .to_string(),
+            "division by zero: 1.5 / 0.0"
+        );
+        assert_eq!(
+            apply_values(Operator::Mul, json!(f64::MAX), json!(2))
+                .unwrap_err()
+                .to_string(),
+            "the result is not a finite number: inf"
+        );
+    }
+}
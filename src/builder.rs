@@ -0,0 +1,246 @@
+use crate::path::{escape_pointer_key, unescape_pointer_key};
+use crate::template::Template;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+};
+use anyhow::{bail, Result};
+use core::convert::TryFrom;
+use serde_json::{Map, Value};
+
+/// Builds an output object of a template without spelling out the template notation, the built
+/// object is the same JSON the transformer consumes. It converts into a [`Value`], or into a
+/// [`Template`] whose output is the built object.
+/// # Example
+/// ```
+/// use serde_json::{json, Value};
+/// use transformer_rs::TemplateBuilder;
+///
+/// let output: Value = TemplateBuilder::new()
+///     .object("order", |order| {
+///         order
+///             .map("id", "/order_id")
+///             .literal("source", "edi")
+///             .array_object("items", |items| items.spread("item_id", "/ids"))
+///     })
+///     .into();
+///
+/// assert_eq!(
+///     output,
+///     json!({
+///         "order": {
+///             "id": "/order_id",
+///             "source": "'edi'",
+///             "[items]": {"...item_id": "/ids"}
+///         }
+///     })
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TemplateBuilder {
+    fields: Map<String, Value>,
+}
+
+impl TemplateBuilder {
+    /// Creates a builder of an empty object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the output field to the input mapping path.
+    pub fn map(mut self, key: &str, path: &str) -> Self {
+        self.fields.insert(key.to_string(), Value::from(path));
+        self
+    }
+
+    /// Hard codes the value of the output field, single quotes and backslashes in the value are
+    /// escaped.
+    pub fn literal(mut self, key: &str, value: &str) -> Self {
+        self.fields.insert(
+            key.to_string(),
+            Value::from(format!(
+                "'{}'",
+                value.replace('\\', r"\\").replace('\'', "''")
+            )),
+        );
+        self
+    }
+
+    /// Maps the output field to the input array whose elements are spread over the objects of the
+    /// enclosing array object.
+    pub fn spread(mut self, key: &str, path: &str) -> Self {
+        self.fields.insert(format!("...{}", key), Value::from(path));
+        self
+    }
+
+    /// Adds a nested object built by the passed function.
+    pub fn object<F>(mut self, key: &str, build: F) -> Self
+    where
+        F: FnOnce(TemplateBuilder) -> TemplateBuilder,
+    {
+        self.fields
+            .insert(key.to_string(), build(TemplateBuilder::new()).into());
+        self
+    }
+
+    /// Adds a nested object built by the passed function that is converted into an array, it
+    /// should have at least one spread field.
+    pub fn array_object<F>(mut self, key: &str, build: F) -> Self
+    where
+        F: FnOnce(TemplateBuilder) -> TemplateBuilder,
+    {
+        self.fields
+            .insert(format!("[{}]", key), build(TemplateBuilder::new()).into());
+        self
+    }
+
+    /// Returns the built object.
+    pub fn build(self) -> Value {
+        Value::Object(self.fields)
+    }
+}
+
+impl From<TemplateBuilder> for Value {
+    fn from(builder: TemplateBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl TryFrom<TemplateBuilder> for Template {
+    type Error = anyhow::Error;
+
+    fn try_from(builder: TemplateBuilder) -> Result<Self, Self::Error> {
+        Template::new(&Value::Array(vec![builder.build()]))
+    }
+}
+
+// Builds the output object of a flat map of JSON pointers to mappings, example
+// `{"/order/id": "/order_id"}` -> `{"order": {"id": "/order_id"}}`. Every pointer token is an object
+// key, numeric ones included. A pointer can't be under another mapped pointer.
+pub fn pointers_to_output(pointers: &Map<String, Value>) -> Result<Value> {
+    let mut output = Map::new();
+    for (pointer, mapping) in pointers {
+        let tokens = match pointer.strip_prefix('/') {
+            Some(tokens) => tokens.split('/').map(unescape_pointer_key),
+            None => bail!("Bad pointer format; {} should start with /", pointer),
+        };
+        let mut obj = &mut output;
+        let mut prefix = String::new();
+        let mut tokens = tokens.peekable();
+        while let Some(token) = tokens.next() {
+            if token.is_empty() {
+                bail!("Bad pointer format; {} has an empty key", pointer);
+            }
+            prefix.push('/');
+            prefix.push_str(&escape_pointer_key(&token));
+            if tokens.peek().is_none() {
+                if obj.contains_key(&token) {
+                    bail!(
+                        "Bad pointer format; {} is already mapped by the pointers under it",
+                        pointer
+                    );
+                }
+                obj.insert(token, mapping.clone());
+                break;
+            }
+            obj = match obj
+                .entry(token)
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(nested) => nested,
+                value => bail!(
+                    "Bad pointer format; {} is under {}, which is already mapped to {}",
+                    pointer,
+                    prefix,
+                    value
+                ),
+            };
+        }
+    }
+    Ok(Value::Object(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build() {
+        let output = TemplateBuilder::new()
+            .object("order", |order| {
+                order
+                    .map("id", "/order_id")
+                    .literal("name", "O'Brien")
+                    .literal("path", r"C:\temp\")
+                    .object("product", |product| product.map("sku", "/product/sku"))
+                    .array_object("items", |items| {
+                        items.map("order_id", "/order_id").spread("id", "/ids")
+                    })
+            })
+            .build();
+
+        assert_eq!(
+            output,
+            json!({
+                "order": {
+                    "id": "/order_id",
+                    "name": "'O''Brien'",
+                    "path": r"'C:\\temp\\'",
+                    "product": {"sku": "/product/sku"},
+                    "[items]": {"order_id": "/order_id", "...id": "/ids"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_pointers_to_output() {
+        let to_output = |pointers: Value| {
+            pointers_to_output(pointers.as_object().unwrap()).map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            to_output(json!({
+                "/order/sub_order/account_id": "/retailer/id",
+                "/order/id": "/order_id",
+                "/order/a~1b": "'x'",
+                "/[items]/...sku": "/skus"
+            })),
+            Ok(json!({
+                "order": {"sub_order": {"account_id": "/retailer/id"}, "id": "/order_id", "a/b": "'x'"},
+                "[items]": {"...sku": "/skus"}
+            }))
+        );
+        assert_eq!(
+            to_output(json!({"/order": "/order", "/order/id": "/order_id"})),
+            Err(
+                "Bad pointer format; /order/id is under /order, which is already mapped to \"/order\""
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            to_output(json!({"order/id": "/order_id"})),
+            Err("Bad pointer format; order/id should start with /".to_string())
+        );
+        assert_eq!(
+            to_output(json!({"/order//id": "/order_id"})),
+            Err("Bad pointer format; /order//id has an empty key".to_string())
+        );
+    }
+
+    #[test]
+    fn test_try_from_template() {
+        let template = Template::try_from(
+            TemplateBuilder::new().array_object("", |items| items.spread("id", "/ids")),
+        )
+        .unwrap();
+
+        assert_eq!(template.output(), &json!([{"[]": {"...id": "/ids"}}]));
+        assert_eq!(
+            template.transform(&json!({"ids": [1, 2]})).unwrap(),
+            json!([[{"id": 1}, {"id": 2}]])
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use crate::options::TransformOptions;
+use crate::transformer::{resolve_path, unquote_literal, AsContexts, Contexts};
+use alloc::{vec, vec::Vec};
+use anyhow::{anyhow, bail, Result};
+use core::cmp::Ordering;
+use serde_json::{from_str, Value};
+
+/// Key of the condition of an output object, the object is only included in the output if the
+/// condition holds.
+pub const WHEN_KEY: &str = "@when";
+// comparison operators supported by conditions
+const OPERATORS: [&str; 6] = ["==", "!=", ">=", "<=", ">", "<"];
+
+// Evaluates the `@when` condition of an output object, objects without a condition are included
+pub fn is_included(
+    contexts: &impl AsContexts,
+    output: &Value,
+    options: &TransformOptions,
+) -> Result<bool> {
+    let contexts = &*contexts.as_contexts();
+    match output.get(WHEN_KEY) {
+        None => Ok(true),
+        Some(Value::String(condition)) => evaluate_condition(contexts, condition, options),
+        Some(condition) => bail!(
+            "Bad condition format; {} should be a string: {}",
+            WHEN_KEY,
+            condition
+        ),
+    }
+}
+
+// Evaluates a condition expression against the input. The expression is either a single operand,
+// evaluated for truthiness, or two operands compared with an operator, separated by spaces,
+// example `/order/total > #100`. An operand is a mapping path, a `'string'` literal or a `#` prefixed
+// JSON literal, example `#100`, `#true` or `#null`. Paths are resolved against the contexts the same
+// way as mappings are, paths that can't be resolved are null.
+pub fn evaluate_condition(
+    contexts: &impl AsContexts,
+    condition: &str,
+    options: &TransformOptions,
+) -> Result<bool> {
+    let contexts = &*contexts.as_contexts();
+    let tokens = split_condition(condition)?;
+    match tokens.as_slice() {
+        [operand] => Ok(is_truthy(&resolve_operand(contexts, operand, options)?)),
+        [left, operator, right] => compare(
+            &resolve_operand(contexts, left, options)?,
+            operator,
+            &resolve_operand(contexts, right, options)?,
+        ),
+        _ => bail!(
+            "Bad condition format; condition should be an operand or two operands compared with an operator: {}",
+            condition
+        ),
+    }
+}
+
+// Returns true if the condition compares two operands for equality, example `sku == 'SKU-343'`
+pub fn is_equality(condition: &str) -> bool {
+    matches!(split_condition(condition).as_deref(), Ok([_, "==", _]))
+}
+
+// Returns true if the condition has a comparison operator, example `quantity > #1`
+pub fn is_comparison(condition: &str) -> bool {
+    split_condition(condition)
+        .map(|tokens| tokens.iter().any(|token| OPERATORS.contains(token)))
+        .unwrap_or_default()
+}
+
+// Returns false for null, false, 0, empty strings, empty arrays and empty objects, true otherwise
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+// Compares two values with the operator. Any values can be checked for equality, numbers are
+// compared by value so `1` equals `1.0`. Ordering operators only compare numbers with numbers and
+// strings with strings.
+pub fn compare(left: &Value, operator: &str, right: &Value) -> Result<bool> {
+    match operator {
+        "==" => Ok(values_eq(left, right)),
+        "!=" => Ok(!values_eq(left, right)),
+        ">" | ">=" | "<" | "<=" => {
+            let ordering = match (left, right) {
+                (Value::Number(l), Value::Number(r)) => l
+                    .as_f64()
+                    .zip(r.as_f64())
+                    .and_then(|(l, r)| l.partial_cmp(&r)),
+                (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                anyhow!(
+                    "Failed to evaluate condition; can't compare {} {} {}",
+                    left,
+                    operator,
+                    right
+                )
+            })?;
+            Ok(match operator {
+                ">" => ordering == Ordering::Greater,
+                ">=" => ordering != Ordering::Less,
+                "<" => ordering == Ordering::Less,
+                _ => ordering != Ordering::Greater,
+            })
+        }
+        _ => bail!(
+            "Bad condition format; unsupported operator {}, supported operators are {}",
+            operator,
+            OPERATORS.join(" ")
+        ),
+    }
+}
+
+fn values_eq(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64() == r.as_f64(),
+        (l, r) => l == r,
+    }
+}
+
+// resolves a condition operand, a `'string'` literal, a `#` prefixed JSON literal or a mapping path
+fn resolve_operand(
+    contexts: &Contexts,
+    operand: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    if let Some(literal) = unquote_literal(operand) {
+        return Ok(Value::from(literal));
+    }
+    if let Some(literal) = operand.strip_prefix('#') {
+        return from_str(literal)
+            .map_err(|_| anyhow!("Bad condition format; invalid literal {}", operand));
+    }
+    Ok(resolve_path(contexts, operand, options)?.unwrap_or(Value::Null))
+}
+
+// splits a condition on spaces, except the ones in `'string'` literals, `["quoted"]` keys and path
+// predicates, example `/items[quantity > #1]/sku`
+fn split_condition(condition: &str) -> Result<Vec<&str>> {
+    let mut tokens = vec![];
+    let mut token_start = None;
+    let mut in_literal = false;
+    let mut escaped = false;
+    let mut in_quoted_key = false;
+    let mut depth = 0;
+    for (i, c) in condition.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_literal => escaped = true,
+            '\'' if !in_quoted_key => in_literal = !in_literal,
+            '"' if !in_literal => in_quoted_key = !in_quoted_key,
+            '[' if !in_literal && !in_quoted_key => depth += 1,
+            ']' if !in_literal && !in_quoted_key && depth > 0 => depth -= 1,
+            ' ' if !in_literal && !in_quoted_key && depth == 0 => {
+                if let Some(start) = token_start.take() {
+                    tokens.push(&condition[start..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        token_start.get_or_insert(i);
+    }
+    if in_literal || in_quoted_key {
+        bail!(
+            "Bad condition format; unclosed quote in condition: {}",
+            condition
+        );
+    }
+    if let Some(start) = token_start {
+        tokens.push(&condition[start..]);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_condition() {
+        assert_eq!(split_condition("/a").unwrap(), vec!["/a"]);
+        assert_eq!(
+            split_condition(" /a  >=  #1 ").unwrap(),
+            vec!["/a", ">=", "#1"]
+        );
+        assert_eq!(
+            split_condition("/[\"a b\"] == 'x y'").unwrap(),
+            vec!["/[\"a b\"]", "==", "'x y'"]
+        );
+        assert_eq!(
+            split_condition("/items[sku == 'a]b'] != #[]").unwrap(),
+            vec!["/items[sku == 'a]b']", "!=", "#[]"]
+        );
+        assert_eq!(
+            split_condition(r"/name == 'O\' Brien\\'").unwrap(),
+            vec!["/name", "==", r"'O\' Brien\\'"]
+        );
+        assert_eq!(
+            split_condition("/a == 'x").err().unwrap().to_string(),
+            "Bad condition format; unclosed quote in condition: /a == 'x"
+        );
+    }
+
+    #[test]
+    fn test_is_equality() {
+        assert!(is_equality("sku == 'SKU 343'"));
+        assert!(is_equality("/items[a == #1]/b == #2"));
+        assert!(!is_equality("quantity != #1"));
+        assert!(!is_equality("/items[a == #1]/b"));
+        assert!(!is_equality("sku == 'a"));
+    }
+
+    #[test]
+    fn test_is_truthy() {
+        assert!(!is_truthy(&json!(null)));
+        assert!(!is_truthy(&json!(false)));
+        assert!(!is_truthy(&json!(0)));
+        assert!(!is_truthy(&json!(0.0)));
+        assert!(!is_truthy(&json!("")));
+        assert!(!is_truthy(&json!([])));
+        assert!(!is_truthy(&json!({})));
+        assert!(is_truthy(&json!(true)));
+        assert!(is_truthy(&json!(-1)));
+        assert!(is_truthy(&json!("a")));
+        assert!(is_truthy(&json!([0])));
+        assert!(is_truthy(&json!({"a": null})));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert!(compare(&json!(1), "==", &json!(1.0)).unwrap());
+        assert!(compare(&json!("a"), "!=", &json!(1)).unwrap());
+        assert!(compare(&json!(null), "==", &json!(null)).unwrap());
+        assert!(compare(&json!(2), ">", &json!(1)).unwrap());
+        assert!(compare(&json!(2), ">=", &json!(2)).unwrap());
+        assert!(!compare(&json!(2), "<", &json!(2)).unwrap());
+        assert!(compare(&json!(2), "<=", &json!(2.5)).unwrap());
+        assert!(compare(&json!("abc"), "<", &json!("abd")).unwrap());
+        assert_eq!(
+            compare(&json!("1"), ">", &json!(1))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to evaluate condition; can't compare \"1\" > 1"
+        );
+        assert_eq!(
+            compare(&json!(1), "=~", &json!(1))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad condition format; unsupported operator =~, supported operators are == != >= <= > <"
+        );
+    }
+
+    #[test]
+    fn test_is_included() {
+        let input = json!({"order": {"total": 150}});
+        let options = TransformOptions::default();
+
+        assert!(is_included(&[&input], &json!({"id": "/order/id"}), &options).unwrap());
+        assert!(is_included(&[&input], &json!("/order/total"), &options).unwrap());
+        assert!(is_included(
+            &[&input],
+            &json!({"@when": "/order/total > #100"}),
+            &options
+        )
+        .unwrap());
+        assert!(!is_included(
+            &[&input],
+            &json!({"@when": "/order/total < #100"}),
+            &options
+        )
+        .unwrap());
+        assert_eq!(
+            is_included(&[&input], &json!({"@when": true}), &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad condition format; @when should be a string: true"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_condition() {
+        let input = json!({"order": {"total": 150, "status": "shipped", "discount": null}});
+        let options = TransformOptions::default();
+
+        assert!(evaluate_condition(&[&input], "/order/total", &options).unwrap());
+        assert!(!evaluate_condition(&[&input], "/order/discount", &options).unwrap());
+        assert!(!evaluate_condition(&[&input], "/order/missing", &options).unwrap());
+        assert!(evaluate_condition(&[&input], "/order/total > #100", &options).unwrap());
+        assert!(!evaluate_condition(&[&input], "/order/total <= #100", &options).unwrap());
+        assert!(evaluate_condition(&[&input], "/order/status == 'shipped'", &options).unwrap());
+        assert!(evaluate_condition(&[&input], "/order/discount == #null", &options).unwrap());
+        assert!(evaluate_condition(&[&input], "'O''Brien' != 'O Brien'", &options).unwrap());
+        assert!(evaluate_condition(&[&input], "#2 > #1", &options).unwrap());
+        assert_eq!(
+            evaluate_condition(&[&input], "/order/total >", &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad condition format; condition should be an operand or two operands compared with an operator: /order/total >"
+        );
+        assert_eq!(
+            evaluate_condition(&[&input], "/order/total > #abc", &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad condition format; invalid literal #abc"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value};
+
+// Serializes the transformed output to CSV, a header row followed by one row per object. Every
+// output object, or the root array, should have been converted into an array of flat objects with
+// the same fields, the rows of all of them are written in order.
+pub fn to_csv(value: &Value) -> Result<String> {
+    let rows = collect_rows(value)?;
+    let mut writer = ::csv::Writer::from_writer(vec![]);
+    if let Some(first_row) = rows.first() {
+        let header: Vec<&String> = first_row.keys().collect();
+        writer.write_record(&header)?;
+        for (i, row) in rows.into_iter().enumerate() {
+            if !row.keys().eq(header.iter().copied()) {
+                bail!(
+                    "Failed to convert to CSV; the row {} has the fields {}, different from the header {}",
+                    i,
+                    join_keys(row.keys()),
+                    join_keys(header.iter().copied())
+                );
+            }
+            let mut record = Vec::with_capacity(row.len());
+            for (key, v) in row {
+                record.push(match v {
+                    Value::Null => String::new(),
+                    Value::String(s) => s.clone(),
+                    Value::Array(_) | Value::Object(_) => bail!(
+                        "Failed to convert to CSV; the field {} of the row {} should be a scalar: {}",
+                        key,
+                        i,
+                        v
+                    ),
+                    v => v.to_string(),
+                });
+            }
+            writer.write_record(&record)?;
+        }
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| anyhow!("Failed to convert to CSV; {}", err))?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+// collects the objects of the converted arrays of every output array element
+fn collect_rows(value: &Value) -> Result<Vec<&Map<String, Value>>> {
+    let mut rows = vec![];
+    for element in value
+        .as_array()
+        .ok_or_else(|| anyhow!("Failed to convert to CSV; the output should be an array"))?
+    {
+        let arrays: Vec<(&str, &Value)> = match element {
+            Value::Object(obj) => obj.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+            array => vec![("[]", array)],
+        };
+        for (name, array) in arrays {
+            let array = array.as_array().ok_or_else(|| {
+                anyhow!(
+                    "Failed to convert to CSV; the output {} should be an array of objects: {}",
+                    name,
+                    array
+                )
+            })?;
+            for row in array {
+                rows.push(row.as_object().ok_or_else(|| {
+                    anyhow!(
+                        "Failed to convert to CSV; the output {} should be an array of objects: {}",
+                        name,
+                        row
+                    )
+                })?);
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn join_keys<'a>(keys: impl Iterator<Item = &'a String>) -> String {
+    keys.map(String::as_str).collect::<Vec<&str>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_csv() {
+        let value = json!([
+            {"order": [
+                {"id": "34554543", "quantity": 4, "note": null},
+                {"id": "7643534", "quantity": 3, "note": "gift, wrapped"}
+            ]},
+            [{"id": "512342", "quantity": 1, "note": "\"fragile\""}]
+        ]);
+
+        assert_eq!(
+            to_csv(&value).unwrap(),
+            "id,note,quantity\n34554543,,4\n7643534,\"gift, wrapped\",3\n512342,\"\"\"fragile\"\"\",1\n"
+        );
+        assert_eq!(to_csv(&json!([{"order": []}])).unwrap(), "");
+    }
+
+    #[test]
+    fn test_to_csv_err() {
+        assert_eq!(
+            to_csv(&json!([{"order": [{"id": 1, "sku": "a"}, {"id": 2}]}]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to convert to CSV; the row 1 has the fields id, different from the header id, sku"
+        );
+        assert_eq!(
+            to_csv(&json!([{"order": [{"id": 1, "items": {"sku": "a"}}]}]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to convert to CSV; the field items of the row 0 should be a scalar: {\"sku\":\"a\"}"
+        );
+        assert_eq!(
+            to_csv(&json!([{"order": {"id": 1}}]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to convert to CSV; the output order should be an array of objects: {\"id\":1}"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::error::Error;
+use core::fmt;
+use serde_json::{to_string, to_string_pretty, Value};
+
+// number of template lines rendered before and after the failing field
+const SNIPPET_CONTEXT_LINES: usize = 2;
+// placeholder swapped in for the failing field value to find its line in the rendered template
+const FIELD_MARKER: &str = "\u{0}failing field\u{0}";
+
+// Error raised while resolving an output field, it carries the JSON pointer of the field in the
+// output template. It displays as the wrapped error, so it is transparent for the callers that
+// don't need the location.
+#[derive(Debug)]
+pub struct FieldError {
+    pub pointer: String,
+    source: anyhow::Error,
+}
+
+impl FieldError {
+    pub fn new(pointer: String, source: anyhow::Error) -> Self {
+        FieldError { pointer, source }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Error for FieldError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let source: &(dyn Error + 'static) = self.source.as_ref();
+        source.source()
+    }
+}
+
+// prefixes the pointer of a field error with the passed prefix, any other error is returned as is
+pub fn prefix_field_error(mut err: anyhow::Error, prefix: &str) -> anyhow::Error {
+    if let Some(field_error) = err.downcast_mut::<FieldError>() {
+        field_error.pointer = format!("{}{}", prefix, field_error.pointer);
+    }
+    err
+}
+
+/// Structured transform error that knows where in the output template it happened, see
+/// [`transform_diagnostic`](crate::transform_diagnostic).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticError {
+    message: String,
+    pointer: Option<String>,
+    template: Value,
+}
+
+impl DiagnosticError {
+    pub(crate) fn new(err: &anyhow::Error, template: Value) -> Self {
+        DiagnosticError {
+            message: err.to_string(),
+            pointer: err
+                .downcast_ref::<FieldError>()
+                .map(|field_error| field_error.pointer.clone()),
+            template,
+        }
+    }
+
+    /// The error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// JSON pointer of the failing field in the output template, if the error is related to a
+    /// specific field.
+    pub fn pointer(&self) -> Option<&str> {
+        self.pointer.as_deref()
+    }
+
+    /// Renders the error message along with a snippet of the output template where the line of
+    /// the failing field is marked with `>`. Example:
+    /// ```text
+    /// error: Failed to resolve mapping value; couldn't find field name idd in the obj {...}
+    ///   --> /0/order/id
+    ///     2 |   {
+    ///     3 |     "order": {
+    /// >   4 |       "id": "/product/idd"
+    ///     5 |     }
+    ///     6 |   }
+    /// ```
+    pub fn render(&self) -> String {
+        let mut rendered = format!("error: {}", self.message);
+        if let Some(pointer) = &self.pointer {
+            rendered.push_str(&format!("\n  --> {}", pointer));
+            if let Some(snippet) = self.snippet(pointer) {
+                rendered.push('\n');
+                rendered.push_str(&snippet);
+            }
+        }
+        rendered
+    }
+
+    fn snippet(&self, pointer: &str) -> Option<String> {
+        let mut template = self.template.clone();
+        let field = template.pointer_mut(pointer)?;
+        let field_value = core::mem::replace(field, Value::from(FIELD_MARKER));
+        let pretty_template = to_string_pretty(&template).ok()?;
+        let marker = to_string(FIELD_MARKER).ok()?;
+
+        let lines: Vec<&str> = pretty_template.lines().collect();
+        let field_line = lines.iter().position(|line| line.contains(&marker))?;
+        let first_line = field_line.saturating_sub(SNIPPET_CONTEXT_LINES);
+        let last_line = (field_line + SNIPPET_CONTEXT_LINES).min(lines.len() - 1);
+
+        let snippet = (first_line..=last_line)
+            .map(|i| {
+                if i == field_line {
+                    format!(
+                        "> {:>3} | {}",
+                        i + 1,
+                        lines[i].replace(&marker, &to_string(&field_value).unwrap_or_default())
+                    )
+                } else {
+                    format!("  {:>3} | {}", i + 1, lines[i])
+                }
+            })
+            .collect::<Vec<String>>();
+        Some(snippet.join("\n"))
+    }
+}
+
+impl fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pointer {
+            Some(pointer) => write!(f, "{} (at {})", self.message, pointer),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Error for DiagnosticError {}
+
+/// Error of a batch transform, it carries the index of the first input that failed to transform,
+/// see [`Template::transform_each`](crate::Template::transform_each).
+#[derive(Debug)]
+pub struct BatchError {
+    index: usize,
+    error: anyhow::Error,
+}
+
+impl BatchError {
+    pub(crate) fn new(index: usize, error: anyhow::Error) -> Self {
+        BatchError { index, error }
+    }
+
+    /// Index of the failing input in the batch.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The error the input failed to transform with.
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+
+    /// Returns the error the input failed to transform with.
+    pub fn into_error(self) -> anyhow::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to transform the input {} of the batch; {}",
+            self.index, self.error
+        )
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let error: &(dyn Error + 'static) = self.error.as_ref();
+        Some(error)
+    }
+}
+
+/// Error of an output object of the template that was skipped with the
+/// [`TransformOptions`](crate::TransformOptions) `continue_on_element_error` option, see
+/// [`transform_with_report`](crate::transform_with_report).
+#[derive(Debug)]
+pub struct ElementError {
+    index: usize,
+    error: anyhow::Error,
+}
+
+impl ElementError {
+    pub(crate) fn new(index: usize, error: anyhow::Error) -> Self {
+        ElementError { index, error }
+    }
+
+    /// Index of the failing output object in the output template array.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The error the output object failed to transform with.
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+
+    /// Returns the error the output object failed to transform with.
+    pub fn into_error(self) -> anyhow::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to transform the output object {}; {}",
+            self.index, self.error
+        )
+    }
+}
+
+impl Error for ElementError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let error: &(dyn Error + 'static) = self.error.as_ref();
+        Some(error)
+    }
+}
+
+/// Error of an input JSON document with duplicated keys, parsed with the
+/// [`TransformOptions`](crate::TransformOptions) `deny_duplicate_input_keys` option, see
+/// [`transform_str_with_options`](crate::transform_str_with_options). It carries the JSON pointers
+/// of the duplicated keys, in document order.
+#[derive(Debug)]
+pub struct DuplicateKeysError {
+    pointers: Vec<String>,
+}
+
+impl DuplicateKeysError {
+    pub(crate) fn new(pointers: Vec<String>) -> Self {
+        DuplicateKeysError { pointers }
+    }
+
+    /// JSON pointers of the duplicated keys, example `/order/id`.
+    pub fn pointers(&self) -> &[String] {
+        &self.pointers
+    }
+}
+
+impl fmt::Display for DuplicateKeysError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to parse input JSON; duplicated keys: {}",
+            self.pointers.join(", ")
+        )
+    }
+}
+
+impl Error for DuplicateKeysError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use serde_json::json;
+
+    #[test]
+    fn test_prefix_field_error() {
+        let err = anyhow::Error::new(FieldError::new("/order/id".to_string(), anyhow!("failed")));
+        let err = prefix_field_error(err, "/0");
+        assert_eq!(err.to_string(), "failed");
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/order/id"
+        );
+
+        let err = prefix_field_error(anyhow!("failed"), "/0");
+        assert_eq!(err.to_string(), "failed");
+    }
+
+    #[test]
+    fn test_render() {
+        let template = json!([{"order": {"id": "/product/idd", "sku": "/product/sku"}}]);
+        let err = anyhow::Error::new(FieldError::new(
+            "/0/order/id".to_string(),
+            anyhow!("couldn't find field name idd"),
+        ));
+        let diagnostic = DiagnosticError::new(&err, template);
+
+        assert_eq!(diagnostic.message(), "couldn't find field name idd");
+        assert_eq!(diagnostic.pointer(), Some("/0/order/id"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "couldn't find field name idd (at /0/order/id)"
+        );
+        assert_eq!(
+            diagnostic.render(),
+            [
+                "error: couldn't find field name idd",
+                "  --> /0/order/id",
+                "    2 |   {",
+                "    3 |     \"order\": {",
+                ">   4 |       \"id\": \"/product/idd\",",
+                "    5 |       \"sku\": \"/product/sku\"",
+                "    6 |     }",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_without_pointer() {
+        let diagnostic = DiagnosticError::new(&anyhow!("failed"), json!([]));
+        assert_eq!(diagnostic.pointer(), None);
+        assert_eq!(diagnostic.render(), "error: failed");
+    }
+}
@@ -0,0 +1,237 @@
+use crate::path::{
+    clean_key, escape_pointer_key, format_key, is_comment, is_obj_to_be_converted_to_array,
+    split_path, unquote_key,
+};
+use crate::META_KEY;
+use alloc::collections::BTreeMap;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use anyhow::Result;
+use core::fmt;
+use serde_json::Value;
+
+/// A change of a mapping between two versions of an output template, see
+/// [`diff_templates`](crate::diff_templates). The paths are the output paths cleaned from the spread
+/// markers, the array convertible objects keep their brackets, example `/[order]/...ids` ->
+/// `/[order]/ids`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateChange {
+    /// The output path is only mapped in the new template.
+    Added { path: String, mapping: String },
+    /// The output path is only mapped in the old template.
+    Removed { path: String, mapping: String },
+    /// The output path is mapped in both templates, to different values.
+    Retargeted {
+        path: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl TemplateChange {
+    /// Returns the output path of the changed mapping.
+    pub fn path(&self) -> &str {
+        match self {
+            TemplateChange::Added { path, .. }
+            | TemplateChange::Removed { path, .. }
+            | TemplateChange::Retargeted { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for TemplateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateChange::Added { path, mapping } => write!(f, "{} added as {}", path, mapping),
+            TemplateChange::Removed { path, mapping } => {
+                write!(f, "{} removed, it was {}", path, mapping)
+            }
+            TemplateChange::Retargeted { path, from, to } => {
+                write!(f, "{} retargeted from {} to {}", path, from, to)
+            }
+        }
+    }
+}
+
+// Returns the changes from the old to the new mappings, ordered by output path
+pub fn diff_mappings(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<TemplateChange> {
+    let mut changes = Vec::new();
+    for (path, from) in old {
+        match new.get(path) {
+            Some(to) if to != from => changes.push(TemplateChange::Retargeted {
+                path: path.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            Some(_) => {}
+            None => changes.push(TemplateChange::Removed {
+                path: path.clone(),
+                mapping: from.clone(),
+            }),
+        }
+    }
+    for (path, mapping) in new {
+        if !old.contains_key(path) {
+            changes.push(TemplateChange::Added {
+                path: path.clone(),
+                mapping: mapping.clone(),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path().cmp(b.path()));
+    changes
+}
+
+// Collects the mapping of every field of a template element by cleaned output path, comments and
+// the metadata of the element are skipped. A path that can't be cleaned, e.g. with an unclosed quoted key, is kept as written, the
+// template is not validated here. The brackets of the array convertible objects are kept, since
+// `[order]` and `order` are different outputs.
+pub fn collect_mappings(
+    output: &Value,
+    xpath: &str,
+    key: &str,
+    mappings: &mut BTreeMap<String, String>,
+) {
+    match output {
+        Value::Object(tree) => {
+            let obj_xpath = format_key(xpath, key);
+            let is_meta = |sub_key: &str| obj_xpath.is_empty() && sub_key == META_KEY;
+            for (sub_key, v) in tree
+                .iter()
+                .filter(|(sub_key, _)| !is_comment(sub_key) && !is_meta(sub_key))
+            {
+                collect_mappings(v, &obj_xpath, sub_key, mappings);
+            }
+        }
+        Value::Array(elements) => {
+            for (i, v) in elements.iter().enumerate() {
+                collect_mappings(v, &format_key(xpath, key), &i.to_string(), mappings);
+            }
+        }
+        value => {
+            let path = format_key(xpath, key);
+            let mapping = match value {
+                Value::String(mapping) => mapping.trim().to_string(),
+                value => value.to_string(),
+            };
+            mappings.insert(output_path(&path).unwrap_or(path), mapping);
+        }
+    }
+}
+
+// cleans the spread markers of the keys of an output path into a JSON pointer, example
+// `/[order]/...ids` -> `/[order]/ids`
+fn output_path(path: &str) -> Result<String> {
+    split_path(path)?
+        .into_iter()
+        .filter(|key| !key.is_empty())
+        .try_fold(String::new(), |xpath, key| {
+            let key = unquote_key(key).unwrap_or(key);
+            let key = if is_obj_to_be_converted_to_array(key) {
+                key
+            } else {
+                clean_key(key)?
+            };
+            Ok(format!("{}/{}", xpath, escape_pointer_key(key)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_collect_mappings() {
+        let mut mappings = BTreeMap::new();
+        collect_mappings(
+            &json!({
+                "[order]": {"//note": "ids", "...ids": " /ids ", "total": "/total"},
+                "coordinates": ["/geo/lat", "/geo/lng"],
+                "count": 1
+            }),
+            "",
+            "",
+            &mut mappings,
+        );
+
+        assert_eq!(
+            mappings.into_iter().collect::<Vec<(String, String)>>(),
+            vec![
+                ("/[order]/ids".to_string(), "/ids".to_string()),
+                ("/[order]/total".to_string(), "/total".to_string()),
+                ("/coordinates/0".to_string(), "/geo/lat".to_string()),
+                ("/coordinates/1".to_string(), "/geo/lng".to_string()),
+                ("/count".to_string(), "1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_mappings_array_convertible_keys() {
+        let mut mappings = BTreeMap::new();
+        collect_mappings(
+            &json!({"[a]": {"...id": "/ids"}, "a": {"id": "/id"}}),
+            "",
+            "",
+            &mut mappings,
+        );
+
+        assert_eq!(
+            mappings.into_iter().collect::<Vec<(String, String)>>(),
+            vec![
+                ("/[a]/id".to_string(), "/ids".to_string()),
+                ("/a/id".to_string(), "/id".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_mappings_meta() {
+        let mut mappings = BTreeMap::new();
+        collect_mappings(
+            &json!({"@meta": {"version": "3"}, "order": {"@meta": "/meta"}}),
+            "",
+            "",
+            &mut mappings,
+        );
+
+        assert_eq!(
+            mappings.into_iter().collect::<Vec<(String, String)>>(),
+            vec![("/order/@meta".to_string(), "/meta".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_diff_mappings() {
+        let old = BTreeMap::from([
+            ("/order/id".to_string(), "/product/id".to_string()),
+            ("/order/name".to_string(), "/product/name".to_string()),
+            ("/order/total".to_string(), "/total".to_string()),
+        ]);
+        let new = BTreeMap::from([
+            ("/order/id".to_string(), "/product/sku".to_string()),
+            ("/order/currency".to_string(), "'EUR'".to_string()),
+            ("/order/total".to_string(), "/total".to_string()),
+        ]);
+
+        assert_eq!(
+            diff_mappings(&old, &new)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            vec![
+                "/order/currency added as 'EUR'",
+                "/order/id retargeted from /product/id to /product/sku",
+                "/order/name removed, it was /product/name",
+            ]
+        );
+        assert!(diff_mappings(&old, &old).is_empty());
+    }
+}
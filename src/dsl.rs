@@ -0,0 +1,194 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value};
+
+// prefix of a comment line of the DSL
+const COMMENT_PREFIX: &str = "#";
+// line separating the output objects of the DSL
+const OBJECT_SEPARATOR: &str = "---";
+// marker of a spread array, it also separates the key from the previous one
+const SPREAD_MARKER: &str = "...";
+
+// Parses the line based template DSL into the equivalent output template, see `parse_dsl`. Every
+// `key = mapping` line sets the mapping of an output field, the dotted key being the path of the
+// field, and the `---` lines start a new output object. The mappings are kept as written, so hard
+// coded values, fallbacks and functions work the same as in JSON templates.
+pub fn parse(text: &str) -> Result<Value> {
+    let mut objs = vec![];
+    let mut obj = Map::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+            continue;
+        }
+        if line == OBJECT_SEPARATOR {
+            if !obj.is_empty() {
+                objs.push(Value::Object(core::mem::take(&mut obj)));
+            }
+            continue;
+        }
+        let (key, mapping) = line.split_once('=').ok_or_else(|| {
+            anyhow!(
+                "Bad DSL format; line {} should be `key = mapping`: {}",
+                i + 1,
+                line
+            )
+        })?;
+        let keys = split_key(key.trim()).map_err(|err| {
+            anyhow!(
+                "Bad DSL format; line {} has a malformed key, {}: {}",
+                i + 1,
+                err,
+                line
+            )
+        })?;
+        insert(&mut obj, &keys, mapping.trim())
+            .map_err(|err| anyhow!("Bad DSL format; line {} {}: {}", i + 1, err, line))?;
+    }
+    if !obj.is_empty() {
+        objs.push(Value::Object(obj));
+    }
+    if objs.is_empty() {
+        bail!("Bad DSL format; the template has no mapping");
+    }
+    Ok(Value::Array(objs))
+}
+
+// Splits a dotted output key into its keys, a spread marker starts a new key that keeps the marker,
+// example `[order].details...ids` -> [`[order]`, `details`, `...ids`]
+fn split_key(key: &str) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut current = String::new();
+    let mut rest = key;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix(SPREAD_MARKER) {
+            if !current.is_empty() {
+                keys.push(core::mem::take(&mut current));
+            }
+            current.push_str(SPREAD_MARKER);
+            rest = after;
+            continue;
+        }
+        if c == '.' {
+            keys.push(core::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    keys.push(current);
+    match keys
+        .iter()
+        .find(|key| key.trim().is_empty() || key.as_str() == SPREAD_MARKER)
+    {
+        Some(_) => bail!("its keys should not be empty"),
+        None => Ok(keys.into_iter().map(|key| key.trim().to_string()).collect()),
+    }
+}
+
+// Sets the mapping at the path of keys, creating the intermediate objects
+fn insert(obj: &mut Map<String, Value>, keys: &[String], mapping: &str) -> Result<()> {
+    let (last, parents) = keys.split_last().ok_or_else(|| anyhow!("has no key"))?;
+    let mut current = obj;
+    for key in parents {
+        current = match current
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            Value::Object(child) => child,
+            _ => bail!("maps a field under {}, which already has a mapping", key),
+        };
+    }
+    if current.contains_key(last) {
+        bail!("maps {} again", last);
+    }
+    current.insert(last.clone(), Value::from(mapping));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let template = parse(
+            "
+            # order fields
+            order.account_id = /retailer/id
+            order.source = 'edi'
+            order.carrier = /order/carrier || 'unknown'
+            order.po_number = regex(/notes, 'PO-(\\d+)')
+            [items]...sku = /order/shipments/items/sku
+            [items].details...quantity = /order/shipments/items/quantity
+            ---
+            []...id = /ids
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            template,
+            json!([
+                {
+                    "order": {
+                        "account_id": "/retailer/id",
+                        "source": "'edi'",
+                        "carrier": "/order/carrier || 'unknown'",
+                        "po_number": "regex(/notes, 'PO-(\\d+)')"
+                    },
+                    "[items]": {
+                        "...sku": "/order/shipments/items/sku",
+                        "details": {"...quantity": "/order/shipments/items/quantity"}
+                    }
+                },
+                {"[]": {"...id": "/ids"}}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(split_key("order.id").unwrap(), vec!["order", "id"]);
+        assert_eq!(split_key("...ids").unwrap(), vec!["...ids"]);
+        assert_eq!(
+            split_key("[order].details...ids").unwrap(),
+            vec!["[order]", "details", "...ids"]
+        );
+        assert_eq!(split_key("[]...id").unwrap(), vec!["[]", "...id"]);
+        assert!(split_key("order..id").is_err());
+        assert!(split_key("order.").is_err());
+        assert!(split_key("order...").is_err());
+    }
+
+    #[test]
+    fn test_parse_err() {
+        let err = |text: &str| parse(text).unwrap_err().to_string();
+
+        assert_eq!(
+            err("order.id /order_id"),
+            "Bad DSL format; line 1 should be `key = mapping`: order.id /order_id"
+        );
+        assert_eq!(
+            err("order.id = /order_id\norder..name = /name"),
+            "Bad DSL format; line 2 has a malformed key, its keys should not be empty: order..name = /name"
+        );
+        assert_eq!(
+            err("order.id = /order_id\norder.id = /id"),
+            "Bad DSL format; line 2 maps id again: order.id = /id"
+        );
+        assert_eq!(
+            err("order = /order\norder.id = /id"),
+            "Bad DSL format; line 2 maps a field under order, which already has a mapping: order.id = /id"
+        );
+        assert_eq!(
+            err("# only comments"),
+            "Bad DSL format; the template has no mapping"
+        );
+    }
+}
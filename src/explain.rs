@@ -0,0 +1,286 @@
+use crate::path::{format_key, is_comment, is_obj_to_be_converted_to_array, is_to_be_spread_array};
+use crate::transformer::{
+    EACH_KEY, FILTER_KEY, GROUP_BY_KEY, INDEXED_KEY, MERGE_BY_KEY, RANGE_KEY, ZIP_KEY,
+};
+use crate::META_KEY;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use serde_json::{Map, Value};
+
+/// How an array convertible object (`[]`) of a template is converted into an array, see
+/// [`explain_array_conversion`](crate::explain_array_conversion). The paths are the output paths as
+/// written in the template, example `/0/[order]/...ids`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrayConversionReport {
+    /// Path of the array convertible object.
+    pub path: String,
+    /// Paths of the spread fields (`...`) the object is split on.
+    pub spread_fields: Vec<String>,
+    /// Paths of the spread markers of the subtree that don't split the object, along with the
+    /// reason, example the spread fields of a nested array convertible object.
+    pub ignored_spread_fields: Vec<(String, String)>,
+}
+
+impl ArrayConversionReport {
+    /// Returns true if the object is converted into an array, that is if it has at least one spread
+    /// field, otherwise the transform fails.
+    pub fn is_converted(&self) -> bool {
+        !self.spread_fields.is_empty()
+    }
+}
+
+impl fmt::Display for ArrayConversionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_converted() {
+            write!(
+                f,
+                "{} is split on {}",
+                self.path,
+                self.spread_fields.join(", ")
+            )?;
+        } else {
+            write!(f, "{} fails, no spread field was found", self.path)?;
+        }
+        for (path, reason) in &self.ignored_spread_fields {
+            write!(f, "; {} is ignored, {}", path, reason)?;
+        }
+        Ok(())
+    }
+}
+
+// Collects the report of every array convertible object of a template element, nested ones after
+// their parent. The spread fields are paired with the array convertible objects the same way as
+// they are when the transformed output is converted: a spread field splits its closest enclosing
+// array convertible object, through plain nested objects. Fixed arrays are not converted, so they
+// are not walked.
+pub fn explain_array_conversions(
+    output: &Value,
+    xpath: &str,
+    key: &str,
+    reports: &mut Vec<ArrayConversionReport>,
+) {
+    if let Value::Object(tree) = output {
+        let path = format_key(xpath, key);
+        let index = if is_obj_to_be_converted_to_array(key) {
+            reports.push(ArrayConversionReport {
+                path: path.clone(),
+                spread_fields: Vec::new(),
+                ignored_spread_fields: Vec::new(),
+            });
+            Some(reports.len() - 1)
+        } else {
+            None
+        };
+        let mut nested = Vec::new();
+        // the metadata of a template element is not transformed
+        let fields = tree
+            .iter()
+            .filter(|(sub_key, _)| !(xpath.is_empty() && sub_key.as_str() == META_KEY));
+        collect_spread_fields(fields, &path, index, reports, &mut nested);
+        for (path, key, value) in nested {
+            explain_array_conversions(value, &path, &key, reports);
+        }
+    }
+}
+
+// Records the spread fields of an object in the report of its enclosing array convertible object,
+// if any, walking down the plain nested objects. The nested array convertible objects are left to
+// the caller, they have their own report.
+fn collect_spread_fields<'a>(
+    fields: impl Iterator<Item = (&'a String, &'a Value)>,
+    xpath: &str,
+    index: Option<usize>,
+    reports: &mut [ArrayConversionReport],
+    nested: &mut Vec<(String, String, &'a Value)>,
+) {
+    for (sub_key, v) in fields.filter(|(sub_key, _)| !is_comment(sub_key)) {
+        let path = format_key(xpath, sub_key);
+        let is_spread = is_to_be_spread_array(sub_key);
+        let ignored = match v {
+            _ if is_obj_to_be_converted_to_array(sub_key) => {
+                nested.push((xpath.to_string(), sub_key.clone(), v));
+                None
+            }
+            // the objects of these directives resolve to arrays, the array convertible objects of a
+            // `@range` body are converted once per iteration, the ones of the other bodies are not
+            Value::Object(obj)
+                if has_directive(
+                    obj,
+                    &[EACH_KEY, RANGE_KEY, MERGE_BY_KEY, FILTER_KEY, ZIP_KEY],
+                ) =>
+            {
+                if obj.contains_key(RANGE_KEY) {
+                    nested.push((xpath.to_string(), sub_key.clone(), v));
+                }
+                if let (Some(index), true) = (index, is_spread) {
+                    reports[index].spread_fields.push(path);
+                }
+                None
+            }
+            Value::Object(obj) if has_directive(obj, &[GROUP_BY_KEY, INDEXED_KEY]) => {
+                if is_spread {
+                    Some("it resolves to an object, only arrays are spread")
+                } else {
+                    None
+                }
+            }
+            Value::Object(obj) => {
+                collect_spread_fields(obj.iter(), &path, index, reports, nested);
+                if is_spread {
+                    Some("its value is an object, only mappings are spread")
+                } else {
+                    None
+                }
+            }
+            Value::String(_) | Value::Array(_) if is_spread => {
+                if let Some(index) = index {
+                    reports[index].spread_fields.push(path);
+                }
+                None
+            }
+            _ if is_spread => Some("its value should be a mapping"),
+            _ => None,
+        };
+        // the spread markers outside of array convertible objects have no report to be listed in
+        if let (Some(reason), Some(index)) = (ignored, index) {
+            reports[index]
+                .ignored_spread_fields
+                .push((format_key(xpath, sub_key), reason.to_string()));
+        }
+    }
+}
+
+fn has_directive(obj: &Map<String, Value>, directives: &[&str]) -> bool {
+    directives
+        .iter()
+        .any(|directive| obj.contains_key(*directive))
+}
+
+// Lists the spread fields of the nested array convertible objects among the ignored spread fields
+// of their closest enclosing array convertible object, the nested reports being after their parent
+pub fn mark_nested_spread_fields(reports: &mut [ArrayConversionReport]) {
+    for i in 1..reports.len() {
+        let (parents, rest) = reports.split_at_mut(i);
+        let report = &rest[0];
+        let parent = parents
+            .iter_mut()
+            .rev()
+            .find(|parent| report.path.starts_with(&format!("{}/", parent.path)));
+        if let Some(parent) = parent {
+            for path in &report.spread_fields {
+                parent.ignored_spread_fields.push((
+                    path.clone(),
+                    format!(
+                        "it splits the nested array convertible object {}",
+                        report.path
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_explain_array_conversions() {
+        let mut reports = vec![];
+        explain_array_conversions(
+            &json!({
+                "[order]": {
+                    "//...note": "comments are skipped",
+                    "details": {"...id": "/ids"},
+                    "...codes": {"code": "/code"},
+                    "...count": 1,
+                    "...lines": {"@each": "/lines", "sku": "sku"},
+                    "...groups": {"@groupBy": "/lines", "key": "sku"},
+                    "slots": {"@range": 2, "[items]": {"...sku": "/skus"}},
+                    "[empty]": {"id": "/id"}
+                },
+                "...orphan": "/ids"
+            }),
+            "",
+            "",
+            &mut reports,
+        );
+        mark_nested_spread_fields(&mut reports);
+
+        assert_eq!(
+            reports,
+            vec![
+                ArrayConversionReport {
+                    path: "/[order]".to_string(),
+                    spread_fields: vec![
+                        "/[order]/...lines".to_string(),
+                        "/[order]/details/...id".to_string()
+                    ],
+                    ignored_spread_fields: vec![
+                        (
+                            "/[order]/...codes".to_string(),
+                            "its value is an object, only mappings are spread".to_string()
+                        ),
+                        (
+                            "/[order]/...count".to_string(),
+                            "its value should be a mapping".to_string()
+                        ),
+                        (
+                            "/[order]/...groups".to_string(),
+                            "it resolves to an object, only arrays are spread".to_string()
+                        ),
+                        (
+                            "/[order]/slots/[items]/...sku".to_string(),
+                            "it splits the nested array convertible object /[order]/slots/[items]"
+                                .to_string()
+                        ),
+                    ],
+                },
+                ArrayConversionReport {
+                    path: "/[order]/[empty]".to_string(),
+                    spread_fields: vec![],
+                    ignored_spread_fields: vec![],
+                },
+                ArrayConversionReport {
+                    path: "/[order]/slots/[items]".to_string(),
+                    spread_fields: vec!["/[order]/slots/[items]/...sku".to_string()],
+                    ignored_spread_fields: vec![],
+                },
+            ]
+        );
+        assert!(reports[0].is_converted());
+        assert!(!reports[1].is_converted());
+        assert_eq!(
+            reports[1].to_string(),
+            "/[order]/[empty] fails, no spread field was found"
+        );
+    }
+
+    #[test]
+    fn test_explain_array_conversions_meta() {
+        let mut reports = vec![];
+        explain_array_conversions(
+            &json!({
+                "@meta": {"[x]": {"id": "/id"}},
+                "order": {"@meta": {"[y]": {"...id": "/ids"}}}
+            }),
+            "",
+            "0",
+            &mut reports,
+        );
+
+        assert_eq!(
+            reports,
+            vec![ArrayConversionReport {
+                path: "/0/order/@meta/[y]".to_string(),
+                spread_fields: vec!["/0/order/@meta/[y]/...id".to_string()],
+                ignored_spread_fields: vec![],
+            }]
+        );
+    }
+}
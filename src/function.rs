@@ -0,0 +1,1764 @@
+use crate::arithmetic::{apply, Operator};
+use crate::condition::is_truthy;
+use crate::jsonpath::JSONPATH_PREFIX;
+use crate::options::TransformOptions;
+use crate::transformer::{
+    resolve_alternative, truncate_context, type_name, unquote_literal, AsContexts, Contexts,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use anyhow::{anyhow, bail, Result};
+use core::convert::TryFrom;
+use core::iter::Peekable;
+use core::str::Chars;
+use serde_json::{Map, Number, Value};
+
+// Splits a function call into the function name and its trimmed arguments, example
+// `regex(/notes, 'PO-(\d+)')` -> (`regex`, [`/notes`, `'PO-(\d+)'`]). `None` is returned if the value
+// is not a function call, the name should start with a letter followed by letters, digits or `_`,
+// optionally prefixed with `@` like the directives, e.g. `@keys(/warehouses)`. The commas of an
+// object literal argument don't split it, e.g. `translate(/status, {'S': 'shipped'})`, nor do the
+// ones in parentheses or square brackets, e.g. `size(jsonpath:$.items[0,1])`.
+pub fn parse_function_call(value: &str) -> Result<Option<(&str, Vec<&str>)>> {
+    let open = match value.find('(') {
+        Some(open) if value.ends_with(')') => open,
+        _ => return Ok(None),
+    };
+    let name = &value[..open];
+    let mut name_chars = name.strip_prefix('@').unwrap_or(name).chars();
+    if !name_chars.next().is_some_and(char::is_alphabetic)
+        || !name_chars.all(|c| c.is_alphanumeric() || c == '_')
+    {
+        return Ok(None);
+    }
+
+    let args = &value[open + 1..value.len() - 1];
+    let mut split_args = vec![];
+    let mut arg_start = 0;
+    let mut depth = 0;
+    let mut braces = 0;
+    let mut brackets = 0;
+    let mut in_literal = false;
+    let mut escaped = false;
+    let mut in_quoted_key = false;
+    for (i, c) in args.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_literal => escaped = true,
+            '\'' if !in_quoted_key => in_literal = !in_literal,
+            '"' if !in_literal => in_quoted_key = !in_quoted_key,
+            _ if in_literal || in_quoted_key => {}
+            '(' => depth += 1,
+            ')' if depth == 0 => bail!("Bad function format; unbalanced parentheses in {}", value),
+            ')' => depth -= 1,
+            '{' => braces += 1,
+            '}' if braces == 0 => bail!("Bad function format; unbalanced braces in {}", value),
+            '}' => braces -= 1,
+            '[' => brackets += 1,
+            ']' if brackets == 0 => {
+                bail!("Bad function format; unbalanced brackets in {}", value)
+            }
+            ']' => brackets -= 1,
+            ',' if depth == 0 && braces == 0 && brackets == 0 => {
+                split_args.push(args[arg_start..i].trim());
+                arg_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_literal || in_quoted_key {
+        bail!("Bad function format; unclosed quote in {}", value);
+    }
+    if depth != 0 {
+        bail!("Bad function format; unbalanced parentheses in {}", value);
+    }
+    if braces != 0 {
+        bail!("Bad function format; unbalanced braces in {}", value);
+    }
+    if brackets != 0 {
+        bail!("Bad function format; unbalanced brackets in {}", value);
+    }
+    if !args.trim().is_empty() {
+        split_args.push(args[arg_start..].trim());
+    }
+    Ok(Some((name, split_args)))
+}
+
+// Resolves the arguments of a function call and applies the function. The outer result is an error
+// if the call is malformed, the inner one if an argument can't be resolved from the input or the
+// function can't be applied to the resolved arguments. The table argument of `translate` is an
+// object literal parsed as is, see `parse_object_literal`, other functions take no object literal.
+pub fn resolve_function(
+    contexts: &impl AsContexts,
+    name: &str,
+    args: &[&str],
+    options: &TransformOptions,
+) -> Result<Result<Value>> {
+    let contexts = &*contexts.as_contexts();
+    // the paths of `exists` and `coalesceArray` are tried rather than resolved, a missing value is
+    // not an error
+    match name {
+        "exists" => {
+            expect_args(name, args, 1)?;
+            // a missing optional field doesn't exist either, a path is tried without its markers
+            let is_path = unquote_literal(args[0]).is_none()
+                && !args[0].starts_with(JSONPATH_PREFIX)
+                && parse_function_call(args[0])?.is_none();
+            let arg = if is_path {
+                without_optional_markers(args[0])
+            } else {
+                args[0].to_string()
+            };
+            let exists = resolve_alternative(contexts, &arg, options)?.is_ok();
+            return Ok(Ok(Value::Bool(exists)));
+        }
+        "coalesceArray" => {
+            expect_min_args(name, args, 1)?;
+            return coalesce_array(contexts, args, options).map(Ok);
+        }
+        _ => {}
+    }
+    let mut values = Vec::with_capacity(args.len());
+    for (i, arg) in args.iter().enumerate() {
+        if arg.starts_with('{') {
+            if name != "translate" || i != 1 {
+                bail!(
+                    "Bad function format; an object literal is only allowed as the translate table: {}",
+                    arg
+                );
+            }
+            values.push(parse_object_literal(arg)?);
+            continue;
+        }
+        match resolve_alternative(contexts, arg, options)? {
+            Ok(value) => values.push(value),
+            Err(err) => return Ok(Err(err)),
+        }
+    }
+    match name {
+        "regex" => {
+            expect_args(name, &values, 2)?;
+            regex(&values[0], &values[1])
+        }
+        "sha256" | "md5" => {
+            expect_args(name, &values, 1)?;
+            hash(name, &values[0]).map(Ok)
+        }
+        "count" | "size" => {
+            expect_args(name, &values, 1)?;
+            Ok(size(name, &values[0]))
+        }
+        "asArray" => {
+            expect_args(name, &values, 1)?;
+            Ok(Ok(as_array(values.remove(0))))
+        }
+        "flatten" => {
+            expect_args(name, &values, 1)?;
+            let mut flattened = vec![];
+            flatten(values.remove(0), &mut flattened);
+            Ok(Ok(Value::Array(flattened)))
+        }
+        "@keys" | "@values" => {
+            expect_args(name, &values, 1)?;
+            Ok(keys_or_values(name, &values[0]))
+        }
+        "pick" => {
+            expect_min_args(name, &values, 2)?;
+            pick(&values[0], &values[1..], options)
+        }
+        "omit" => {
+            expect_min_args(name, &values, 2)?;
+            omit(&values[0], &values[1..])
+        }
+        "toSet" => {
+            expect_args(name, &values, 1)?;
+            Ok(to_set(&values[0]))
+        }
+        "not" => {
+            expect_args(name, &values, 1)?;
+            Ok(not(&values[0], options))
+        }
+        "upper" | "lower" | "trim" => {
+            expect_args(name, &values, 1)?;
+            Ok(transform_string(name, &values[0]))
+        }
+        "add" | "sub" | "mul" | "div" => {
+            expect_args(name, &values, 2)?;
+            Ok(arithmetic(name, &values[0], &values[1]))
+        }
+        "ratio" => {
+            expect_args(name, &values, 2)?;
+            Ok(ratio(&values[0], &values[1], options))
+        }
+        "format" => {
+            expect_args(name, &values, 2)?;
+            format_value(&values[0], &values[1])
+        }
+        "translate" => {
+            if values.len() != 3 {
+                expect_args(name, &values, 2)?;
+            }
+            translate(&values[0], &values[1], values.get(2))
+        }
+        _ => bail!("Bad function format; unknown function {}", name),
+    }
+}
+
+fn expect_args<T>(name: &str, values: &[T], count: usize) -> Result<()> {
+    if values.len() != count {
+        bail!(
+            "Bad function format; {} expects {} arguments, got {}",
+            name,
+            count,
+            values.len()
+        );
+    }
+    Ok(())
+}
+
+fn expect_min_args<T>(name: &str, values: &[T], count: usize) -> Result<()> {
+    if values.len() < count {
+        bail!(
+            "Bad function format; {} expects at least {} arguments, got {}",
+            name,
+            count,
+            values.len()
+        );
+    }
+    Ok(())
+}
+
+// Returns the mapping path without its optional markers, the `?` after the field names, example
+// `/order/discount?/code` -> `/order/discount/code`. The quoted keys and the predicates, in square
+// brackets, are kept as is.
+fn without_optional_markers(path: &str) -> String {
+    let mut stripped = String::with_capacity(path.len());
+    let mut brackets = 0;
+    let mut quote = None;
+    for c in path.chars() {
+        match c {
+            _ if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {}
+            '\'' | '"' if brackets > 0 => quote = Some(c),
+            '[' => brackets += 1,
+            ']' if brackets > 0 => brackets -= 1,
+            '?' if brackets == 0 => continue,
+            _ => {}
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+// Collects the values of the mappings that resolve, in order, the missing and null ones are skipped,
+// example `coalesceArray(/phone_home, /phone_work)` -> `["555-0100"]` without a work phone
+fn coalesce_array(contexts: &Contexts, args: &[&str], options: &TransformOptions) -> Result<Value> {
+    let mut values = vec![];
+    for arg in args {
+        match resolve_alternative(contexts, arg, options)? {
+            Ok(Value::Null) | Err(_) => {}
+            Ok(value) => values.push(value),
+        }
+    }
+    Ok(Value::Array(values))
+}
+
+// Parses an object literal argument, either a JSON object or an object whose keys and values are
+// single quoted like the hard coded values, example `{'S': 'shipped', 'P': 'pending'}`. The values
+// that are not single quoted are parsed as JSON, e.g. numbers, booleans or `null`.
+fn parse_object_literal(arg: &str) -> Result<Value> {
+    if let Ok(value @ Value::Object(_)) = serde_json::from_str(arg) {
+        return Ok(value);
+    }
+    let malformed = || {
+        anyhow!(
+            "Bad function format; the object literal should map quoted keys to values: {}",
+            arg
+        )
+    };
+    let inner = arg
+        .strip_prefix('{')
+        .and_then(|inner| inner.strip_suffix('}'))
+        .ok_or_else(malformed)?;
+    let mut obj = Map::new();
+    if inner.trim().is_empty() {
+        return Ok(Value::Object(obj));
+    }
+    for entry in split_unquoted(inner, ',') {
+        let mut parts = split_unquoted(entry, ':').into_iter();
+        let (key, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(key), Some(value), None) => (key.trim(), value.trim()),
+            _ => return Err(malformed()),
+        };
+        let key = match unquote_literal(key) {
+            Some(key) => key,
+            None => match serde_json::from_str(key) {
+                Ok(Value::String(key)) => key,
+                _ => return Err(malformed()),
+            },
+        };
+        let value = match unquote_literal(value) {
+            Some(value) => Value::String(value),
+            None => serde_json::from_str(value).map_err(|_| malformed())?,
+        };
+        obj.insert(key, value);
+    }
+    Ok(Value::Object(obj))
+}
+
+// Splits the text on the separator, except inside single or double quotes
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote.is_some() => escaped = true,
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            _ if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {}
+            _ if c == separator => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+// Looks up the value in the translation table, strings by their text and numbers by their JSON
+// representation, example `2` -> the `'2'` entry. A value with no entry in the table gives the
+// default, or fails to resolve without one.
+fn translate(value: &Value, table: &Value, default: Option<&Value>) -> Result<Result<Value>> {
+    let table = match table {
+        Value::Object(table) => table,
+        table => bail!(
+            "Bad function format; the translate table should be an object literal, not a {}: {}",
+            type_name(table),
+            table
+        ),
+    };
+    let key = match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Null => None,
+        value => {
+            return Ok(Err(anyhow!(
+                "Failed to resolve translate; the value should be a string or a number, not a {}: {}",
+                type_name(value),
+                value
+            )));
+        }
+    };
+    match (key.and_then(|key| table.get(&key)), default) {
+        (Some(translation), _) => Ok(Ok(translation.clone())),
+        (None, Some(default)) => Ok(Ok(default.clone())),
+        (None, None) => Ok(Err(anyhow!(
+            "Failed to resolve translate; the value has no entry in the table: {}",
+            value
+        ))),
+    }
+}
+
+// Returns the field names passed to a function as strings, in the call order
+fn field_names<'a>(name: &str, values: &'a [Value]) -> Result<Vec<&'a str>> {
+    values
+        .iter()
+        .map(|value| {
+            value.as_str().ok_or_else(|| {
+                anyhow!(
+                    "Bad function format; the {} field names should be strings: {}",
+                    name,
+                    value
+                )
+            })
+        })
+        .collect()
+}
+
+// Returns a new object with only the named fields of the object, the missing ones are left out, or
+// fail to resolve with the `strict_pick` option. Null, e.g. a missing optional object, picks to null.
+fn pick(value: &Value, names: &[Value], options: &TransformOptions) -> Result<Result<Value>> {
+    let names = field_names("pick", names)?;
+    let obj = match value {
+        Value::Object(obj) => obj,
+        Value::Null => return Ok(Ok(Value::Null)),
+        value => {
+            return Ok(Err(anyhow!(
+                "Failed to resolve pick; the value should be an object: {}",
+                value
+            )))
+        }
+    };
+    let mut picked = Map::new();
+    for name in names {
+        match obj.get(name) {
+            Some(field) => {
+                picked.insert(name.to_string(), field.clone());
+            }
+            None if options.strict_pick => {
+                return Ok(Err(anyhow!(
+                    "Failed to resolve pick; couldn't find the field {} in the object {}",
+                    name,
+                    truncate_context(value.to_string(), options)
+                )))
+            }
+            None => {}
+        }
+    }
+    Ok(Ok(Value::Object(picked)))
+}
+
+// Returns a copy of the object without the named fields, the missing ones are ignored. Null, e.g. a
+// missing optional object, omits to null.
+fn omit(value: &Value, names: &[Value]) -> Result<Result<Value>> {
+    let names = field_names("omit", names)?;
+    let mut obj = match value {
+        Value::Object(obj) => obj.clone(),
+        Value::Null => return Ok(Ok(Value::Null)),
+        value => {
+            return Ok(Err(anyhow!(
+                "Failed to resolve omit; the value should be an object: {}",
+                value
+            )))
+        }
+    };
+    for name in names {
+        obj.remove(name);
+    }
+    Ok(Ok(Value::Object(obj)))
+}
+
+// Returns an object with the elements of an array as keys mapped to `true`, for membership checks.
+// The elements should be strings or numbers, numbers are keyed by their JSON representation.
+fn to_set(value: &Value) -> Result<Value> {
+    let values = value.as_array().ok_or_else(|| {
+        anyhow!(
+            "Failed to resolve toSet; the value should be an array: {}",
+            value
+        )
+    })?;
+    let mut set = Map::new();
+    for element in values {
+        let key = match element {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            element => bail!(
+                "Failed to resolve toSet; the elements should be strings or numbers, not a {}: {}",
+                type_name(element),
+                element
+            ),
+        };
+        set.insert(key, Value::Bool(true));
+    }
+    Ok(Value::Object(set))
+}
+
+// Returns the negation of a value, coerced to a boolean with the `@when` truthiness rules. With the
+// `strict_not` option only booleans and null, e.g. a missing optional field, can be negated.
+fn not(value: &Value, options: &TransformOptions) -> Result<Value> {
+    match value {
+        Value::Bool(_) | Value::Null => {}
+        value if options.strict_not => bail!(
+            "Failed to resolve not; the value should be a boolean, not a {}: {}",
+            type_name(value),
+            value
+        ),
+        _ => {}
+    }
+    Ok(Value::Bool(!is_truthy(value)))
+}
+
+// Converts a string to upper case for `upper`, to lower case for `lower`, or removes its leading and
+// trailing whitespace for `trim`. The case conversions follow the Unicode case mappings regardless
+// of the locale, so a character can map to several, e.g. `ß` -> `SS`, and the whitespace is the
+// Unicode one, e.g. no-break spaces are trimmed. Null, e.g. a missing optional field, stays null.
+fn transform_string(name: &str, value: &Value) -> Result<Value> {
+    let s = match value {
+        Value::String(s) => s,
+        Value::Null => return Ok(Value::Null),
+        value => bail!(
+            "Failed to resolve {}; the value should be a string, not a {}: {}",
+            name,
+            type_name(value),
+            value
+        ),
+    };
+    Ok(Value::String(match name {
+        "upper" => s.to_uppercase(),
+        "lower" => s.to_lowercase(),
+        _ => s.trim().to_string(),
+    }))
+}
+
+// Returns the number of elements of an array, of keys of an object or of characters of a string,
+// the Unicode scalar values rather than the bytes. Null has a size of 0 so that missing optional
+// fields count as empty. Numbers and bools have no size.
+fn size(name: &str, value: &Value) -> Result<Value> {
+    match value {
+        Value::Array(values) => Ok(Value::from(values.len())),
+        Value::Object(obj) => Ok(Value::from(obj.len())),
+        Value::String(s) => Ok(Value::from(s.chars().count())),
+        Value::Null => Ok(Value::from(0)),
+        value => Err(anyhow!(
+            "Failed to resolve {}; a {} has no size: {}",
+            name,
+            type_name(value),
+            value
+        )),
+    }
+}
+
+// Wraps a value in a one-element array, arrays are left untouched and null, e.g. a missing optional
+// field, gives an empty array
+fn as_array(value: Value) -> Value {
+    match value {
+        Value::Array(_) => value,
+        Value::Null => Value::Array(vec![]),
+        value => Value::Array(vec![value]),
+    }
+}
+
+// Appends the elements of the nested arrays of the value to `flattened`, at any depth, in order,
+// example `[[1, [2]], 3]` -> `[1, 2, 3]`. A value that is not an array is a one-element array like
+// with `asArray`, and null an empty one.
+fn flatten(value: Value, flattened: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                match value {
+                    Value::Array(_) => flatten(value, flattened),
+                    value => flattened.push(value),
+                }
+            }
+        }
+        Value::Null => {}
+        value => flattened.push(value),
+    }
+}
+
+// Returns the keys of an object as strings for `@keys`, its values for `@values`, in the object order
+fn keys_or_values(name: &str, value: &Value) -> Result<Value> {
+    let obj = value.as_object().ok_or_else(|| {
+        anyhow!(
+            "Failed to resolve {}; the value should be an object: {}",
+            name,
+            value
+        )
+    })?;
+    Ok(if name == "@keys" {
+        obj.keys().cloned().map(Value::String).collect()
+    } else {
+        obj.values().cloned().collect()
+    })
+}
+
+// Applies the arithmetic operator of the function to two numbers, see `apply` for the numeric typing
+// rules. Both operands should be numbers.
+fn arithmetic(name: &str, a: &Value, b: &Value) -> Result<Value> {
+    let operator = match name {
+        "add" => Operator::Add,
+        "sub" => Operator::Sub,
+        "mul" => Operator::Mul,
+        _ => Operator::Div,
+    };
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => apply(operator, a, b)
+            .map(Value::Number)
+            .map_err(|err| anyhow!("Failed to resolve {}; {}", name, err)),
+        _ => {
+            let operand = if a.is_number() { b } else { a };
+            Err(anyhow!(
+                "Failed to resolve {}; the operands should be numbers, not a {}: {}",
+                name,
+                type_name(operand),
+                operand
+            ))
+        }
+    }
+}
+
+// Divides two numbers as floats, example `ratio` of `1` and `4` -> `0.25`. A zero denominator fails
+// to resolve, or gives `0.0` with the `zero_ratio_on_zero_denominator` option.
+fn ratio(a: &Value, b: &Value, options: &TransformOptions) -> Result<Value> {
+    let (numerator, denominator) = match (a.as_f64(), b.as_f64()) {
+        (Some(numerator), Some(denominator)) => (numerator, denominator),
+        _ => {
+            let operand = if a.is_number() { b } else { a };
+            bail!(
+                "Failed to resolve ratio; the operands should be numbers, not a {}: {}",
+                type_name(operand),
+                operand
+            );
+        }
+    };
+    if denominator == 0.0 {
+        if options.zero_ratio_on_zero_denominator {
+            return Ok(Value::from(0.0));
+        }
+        bail!("Failed to resolve ratio; division by zero: {} / {}", a, b);
+    }
+    let result = numerator / denominator;
+    Number::from_f64(result).map(Value::Number).ok_or_else(|| {
+        anyhow!(
+            "Failed to resolve ratio; the result is not a finite number: {}",
+            result
+        )
+    })
+}
+
+// the maximum width and precision of a `format` conversion specifier
+const MAX_FORMAT_WIDTH: usize = 1024;
+
+// Conversion specifier of a `format` pattern, `%[flags][width][.precision]conversion`, example `%08d`.
+// The flags are `-` to align left, `0` to pad numbers with zeros and `+` to sign positive numbers.
+struct FormatSpec {
+    left_align: bool,
+    zero_pad: bool,
+    plus_sign: bool,
+    width: usize,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+// Formats a value with a printf style pattern, example `format(/order/id, '%08d')` -> `00000042`.
+// The pattern has a single conversion specifier, `%d` for integers, `%f` for numbers, `%x` and `%X`
+// for non-negative integers in hexadecimal and `%s` for strings, the text around it is kept and
+// `%%` is an escaped `%`. The precision is the number of decimals of `%f`, 6 by default, and the
+// maximum number of characters of `%s`. Null, e.g. a missing optional field, formats to null.
+fn format_value(value: &Value, pattern: &Value) -> Result<Result<Value>> {
+    let pattern = pattern.as_str().ok_or_else(|| {
+        anyhow!(
+            "Bad function format; the format pattern should be a string: {}",
+            pattern
+        )
+    })?;
+    let (prefix, spec, suffix) = parse_format_pattern(pattern)?;
+    if value.is_null() {
+        return Ok(Ok(Value::Null));
+    }
+    Ok(format_spec(value, &spec)
+        .map(|formatted| Value::String(format!("{}{}{}", prefix, formatted, suffix))))
+}
+
+// Splits a `format` pattern into the text before its conversion specifier, the specifier and the
+// text after it
+fn parse_format_pattern(pattern: &str) -> Result<(String, FormatSpec, String)> {
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    let mut spec = None;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let text = if spec.is_none() {
+            &mut prefix
+        } else {
+            &mut suffix
+        };
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+        if spec.is_some() {
+            bail!(
+                "Bad function format; the format pattern {} should have a single conversion specifier",
+                pattern
+            );
+        }
+        let mut parsed = FormatSpec {
+            left_align: false,
+            zero_pad: false,
+            plus_sign: false,
+            width: 0,
+            precision: None,
+            conversion: ' ',
+        };
+        while let Some(flag) = chars.next_if(|c| ['-', '0', '+'].contains(c)) {
+            match flag {
+                '-' => parsed.left_align = true,
+                '0' => parsed.zero_pad = true,
+                _ => parsed.plus_sign = true,
+            }
+        }
+        parsed.width = parse_format_number(&mut chars, "width", pattern)?;
+        if chars.next_if_eq(&'.').is_some() {
+            parsed.precision = Some(parse_format_number(&mut chars, "precision", pattern)?);
+        }
+        parsed.conversion = match chars.next() {
+            Some(conversion @ ('d' | 'x' | 'X')) if parsed.precision.is_none() => conversion,
+            Some(conversion @ ('f' | 's')) => conversion,
+            _ => bail!(
+                "Bad function format; unsupported conversion specifier in the format pattern {}, it should be one of %d, %f, %s, %x or %X",
+                pattern
+            ),
+        };
+        spec = Some(parsed);
+    }
+    let spec = spec.ok_or_else(|| {
+        anyhow!(
+            "Bad function format; the format pattern {} has no conversion specifier",
+            pattern
+        )
+    })?;
+    Ok((prefix, spec, suffix))
+}
+
+// Parses the digits of the width or the precision of a conversion specifier, 0 without digits. It
+// should be at most `MAX_FORMAT_WIDTH`, so that a pattern can't overflow or pad to a huge string.
+fn parse_format_number(chars: &mut Peekable<Chars>, name: &str, pattern: &str) -> Result<usize> {
+    let mut number: usize = 0;
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        number = number
+            .checked_mul(10)
+            .and_then(|number| number.checked_add(digit as usize - '0' as usize))
+            .filter(|number| *number <= MAX_FORMAT_WIDTH)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Bad function format; the {} of the format pattern {} should be at most {}",
+                    name,
+                    pattern,
+                    MAX_FORMAT_WIDTH
+                )
+            })?;
+    }
+    Ok(number)
+}
+
+// Formats a value with a conversion specifier, the value should be of the type of the conversion
+fn format_spec(value: &Value, spec: &FormatSpec) -> Result<String> {
+    let mismatch = |expected: &str| {
+        anyhow!(
+            "Failed to resolve format; %{} expects {}, not a {}: {}",
+            spec.conversion,
+            expected,
+            type_name(value),
+            value
+        )
+    };
+    let integer = || match value {
+        Value::Number(n) => n
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from))
+            .ok_or_else(|| mismatch("an integer")),
+        _ => Err(mismatch("an integer")),
+    };
+    let formatted = match spec.conversion {
+        'd' => integer()?.to_string(),
+        'x' | 'X' => {
+            let n = u128::try_from(integer()?).map_err(|_| mismatch("a non-negative integer"))?;
+            if spec.conversion == 'x' {
+                format!("{:x}", n)
+            } else {
+                format!("{:X}", n)
+            }
+        }
+        'f' => {
+            let n = value.as_f64().ok_or_else(|| mismatch("a number"))?;
+            format!("{:.*}", spec.precision.unwrap_or(6), n)
+        }
+        _ => {
+            let s = value.as_str().ok_or_else(|| mismatch("a string"))?;
+            match spec.precision {
+                Some(precision) => s.chars().take(precision).collect(),
+                None => s.to_string(),
+            }
+        }
+    };
+    let is_number = spec.conversion != 's';
+    let formatted = if is_number && spec.plus_sign && !formatted.starts_with('-') {
+        format!("+{}", formatted)
+    } else {
+        formatted
+    };
+
+    let padding = spec.width.saturating_sub(formatted.chars().count());
+    Ok(if padding == 0 {
+        formatted
+    } else if spec.left_align {
+        format!("{}{}", formatted, " ".repeat(padding))
+    } else if spec.zero_pad && is_number {
+        // the zeros go between the sign and the digits, example `-0042`
+        let digits_start = if formatted.starts_with(['-', '+']) {
+            1
+        } else {
+            0
+        };
+        format!(
+            "{}{}{}",
+            &formatted[..digits_start],
+            "0".repeat(padding),
+            &formatted[digits_start..]
+        )
+    } else {
+        format!("{}{}", " ".repeat(padding), formatted)
+    })
+}
+
+// Returns the first capture group of the pattern in the text, or the whole match if the pattern has
+// no group. Compiled patterns are cached since the same template is usually applied many times.
+#[cfg(feature = "regex")]
+fn regex(text: &Value, pattern: &Value) -> Result<Result<Value>> {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    static PATTERNS: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(Default::default);
+
+    let pattern = pattern.as_str().ok_or_else(|| {
+        anyhow!(
+            "Bad function format; the regex pattern should be a string: {}",
+            pattern
+        )
+    })?;
+    let regex = {
+        let mut patterns = PATTERNS.lock().unwrap_or_else(|err| err.into_inner());
+        match patterns.get(pattern) {
+            Some(regex) => regex.clone(),
+            None => {
+                let regex = Regex::new(pattern).map_err(|err| {
+                    anyhow!(
+                        "Bad function format; invalid regex pattern {}: {}",
+                        pattern,
+                        err
+                    )
+                })?;
+                patterns.insert(pattern.to_string(), regex.clone());
+                regex
+            }
+        }
+    };
+    let text = match text.as_str() {
+        Some(text) => text,
+        None => {
+            return Ok(Err(anyhow!(
+                "Failed to resolve regex; the value should be a string: {}",
+                text
+            )))
+        }
+    };
+    let group = if regex.captures_len() > 1 { 1 } else { 0 };
+    Ok(match regex.captures(text) {
+        Some(captures) => Ok(captures
+            .get(group)
+            .map_or(Value::Null, |m| Value::from(m.as_str()))),
+        None => Err(anyhow!(
+            "Failed to resolve regex; the pattern {} doesn't match {}",
+            pattern,
+            text
+        )),
+    })
+}
+
+#[cfg(not(feature = "regex"))]
+fn regex(_: &Value, _: &Value) -> Result<Result<Value>> {
+    bail!("Bad function format; the regex function requires the regex feature")
+}
+
+// Returns the hex digest of the canonical JSON of the value, its compact serialization with the
+// object keys sorted, so that equal values give the same digest whatever the order of their keys
+#[cfg(feature = "hash")]
+fn hash(name: &str, value: &Value) -> Result<Value> {
+    use core::fmt::Write;
+    use md5::Md5;
+    use sha2::{Digest, Sha256};
+
+    let mut canonical = value.clone();
+    sort_object_keys(&mut canonical);
+    let json = serde_json::to_vec(&canonical)?;
+    let digest = match name {
+        "md5" => Md5::digest(&json).to_vec(),
+        _ => Sha256::digest(&json).to_vec(),
+    };
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        write!(hex, "{:02x}", byte)?;
+    }
+    Ok(Value::String(hex))
+}
+
+// Sorts the keys of the objects of the value, nested objects included. The JSON objects already
+// keep their keys sorted, unless the `preserve_order` feature of serde_json is enabled, e.g. by
+// another crate of the build.
+#[cfg(feature = "hash")]
+fn sort_object_keys(value: &mut Value) {
+    match value {
+        Value::Object(obj) => {
+            let mut fields: Vec<(String, Value)> = core::mem::take(obj).into_iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            obj.extend(fields);
+            obj.values_mut().for_each(sort_object_keys);
+        }
+        Value::Array(values) => values.iter_mut().for_each(sort_object_keys),
+        _ => {}
+    }
+}
+
+#[cfg(not(feature = "hash"))]
+fn hash(name: &str, _: &Value) -> Result<Value> {
+    bail!(
+        "Bad function format; the {} function requires the hash feature",
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_function_call() {
+        assert_eq!(
+            parse_function_call(r"regex(/notes, 'PO-(\d+)')").unwrap(),
+            Some(("regex", vec!["/notes", r"'PO-(\d+)'"]))
+        );
+        assert_eq!(
+            parse_function_call("f(g(/a, /b), 'x, y', \"c,d\")").unwrap(),
+            Some(("f", vec!["g(/a, /b)", "'x, y'", "\"c,d\""]))
+        );
+        assert_eq!(parse_function_call("f()").unwrap(), Some(("f", vec![])));
+        assert_eq!(
+            parse_function_call("@keys(/warehouses)").unwrap(),
+            Some(("@keys", vec!["/warehouses"]))
+        );
+        assert_eq!(parse_function_call("@(x)").unwrap(), None);
+        assert_eq!(parse_function_call("/order/id").unwrap(), None);
+        assert_eq!(parse_function_call("'f(x)'").unwrap(), None);
+        assert_eq!(parse_function_call("/a/f(x)").unwrap(), None);
+        assert_eq!(
+            parse_function_call("f(a)) + g(b)")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad function format; unbalanced parentheses in f(a)) + g(b)"
+        );
+        assert_eq!(
+            parse_function_call("f('a)").err().unwrap().to_string(),
+            "Bad function format; unclosed quote in f('a)"
+        );
+        assert_eq!(
+            parse_function_call("translate(/status, {'S': 'shipped', 'P': 'pending'})").unwrap(),
+            Some((
+                "translate",
+                vec!["/status", "{'S': 'shipped', 'P': 'pending'}"]
+            ))
+        );
+        assert_eq!(
+            parse_function_call("f({'a': 1)").err().unwrap().to_string(),
+            "Bad function format; unbalanced braces in f({'a': 1)"
+        );
+        assert_eq!(
+            parse_function_call("size(jsonpath:$.items[0,1], /items[?sku == 'a,b'])").unwrap(),
+            Some((
+                "size",
+                vec!["jsonpath:$.items[0,1]", "/items[?sku == 'a,b']"]
+            ))
+        );
+        assert_eq!(
+            parse_function_call("size(jsonpath:$.items[?(@.a, @.b)], /id)").unwrap(),
+            Some(("size", vec!["jsonpath:$.items[?(@.a, @.b)]", "/id"]))
+        );
+        assert_eq!(
+            parse_function_call("size(/items[0)")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad function format; unbalanced brackets in size(/items[0)"
+        );
+    }
+
+    #[cfg(feature = "jsonpath")]
+    #[test]
+    fn test_jsonpath_argument() {
+        let input = json!({"items": [{"sku": "a"}, {"sku": "b"}, {"sku": "c"}]});
+        let (name, args) = parse_function_call("size(jsonpath:$.items[0,1])")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resolve_function(&[&input], name, &args, &Default::default())
+                .unwrap()
+                .unwrap(),
+            json!(2)
+        );
+    }
+
+    #[test]
+    fn test_resolve_function_err() {
+        let input = json!({"notes": "ship PO-1234"});
+        assert_eq!(
+            resolve_function(&[&input], "nope", &["/notes"], &Default::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad function format; unknown function nope"
+        );
+        assert_eq!(
+            resolve_function(&[&input], "regex", &["/notes"], &Default::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad function format; regex expects 2 arguments, got 1"
+        );
+        assert!(
+            resolve_function(&[&input], "regex", &["/po", "'x'"], &Default::default())
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_size() {
+        let input = json!({
+            "ids": ["34554543", "7643534"],
+            "details": {"name": "Red Shoes", "manufacture": "company"},
+            "name": "Café",
+            "discount": null,
+            "length": 50,
+            "active": true
+        });
+        let resolve = |name: &str, arg: &str| {
+            resolve_function(&[&input], name, &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve("size", "/ids"), Ok(json!(2)));
+        assert_eq!(resolve("size", "/details"), Ok(json!(2)));
+        assert_eq!(resolve("size", "/name"), Ok(json!(4)));
+        assert_eq!(resolve("size", "/discount"), Ok(json!(0)));
+        assert_eq!(resolve("size", "/missing?"), Ok(json!(0)));
+        assert_eq!(resolve("count", "/ids"), Ok(json!(2)));
+        assert_eq!(
+            resolve("size", "/length"),
+            Err("Failed to resolve size; a number has no size: 50".to_string())
+        );
+        assert_eq!(
+            resolve("count", "/active"),
+            Err("Failed to resolve count; a boolean has no size: true".to_string())
+        );
+    }
+
+    #[test]
+    fn test_size_unicode() {
+        let input = json!({
+            "city": "Zürich",
+            "word": "ıstanbul",
+            "emoji": "👍🏽",
+            "decomposed": "Cafe\u{301}"
+        });
+        let resolve = |arg: &str| {
+            resolve_function(&[&input], "size", &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve("/city"), Ok(json!(6)));
+        assert_eq!(resolve("/word"), Ok(json!(8)));
+        // a thumbs up with a skin tone modifier is two scalar values, and so is an e followed by a
+        // combining accent
+        assert_eq!(resolve("/emoji"), Ok(json!(2)));
+        assert_eq!(resolve("/decomposed"), Ok(json!(5)));
+    }
+
+    #[test]
+    fn test_transform_string() {
+        let input = json!({
+            "city": "Zürich",
+            "street": "Straße",
+            "turkish": "ıi İI",
+            "greek": "ΟΔΟΣ",
+            "padded": "\u{a0} São Paulo\t\n\u{3000}",
+            "count": 1
+        });
+        let resolve = |name: &str, arg: &str| {
+            resolve_function(&[&input], name, &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve("upper", "/city"), Ok(json!("ZÜRICH")));
+        assert_eq!(resolve("lower", "/city"), Ok(json!("zürich")));
+        assert_eq!(resolve("upper", "/street"), Ok(json!("STRASSE")));
+        // the dotless and dotted i follow the Unicode mappings, not the Turkish locale ones
+        assert_eq!(resolve("upper", "/turkish"), Ok(json!("II İI")));
+        assert_eq!(resolve("lower", "/turkish"), Ok(json!("ıi i\u{307}i")));
+        // a final sigma is lower cased to ς
+        assert_eq!(resolve("lower", "/greek"), Ok(json!("οδος")));
+        assert_eq!(resolve("trim", "/padded"), Ok(json!("São Paulo")));
+        assert_eq!(resolve("upper", "/missing?"), Ok(Value::Null));
+        assert_eq!(
+            resolve("trim", "/count"),
+            Err(
+                "Failed to resolve trim; the value should be a string, not a number: 1".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_as_array() {
+        let input = json!({
+            "id": "34554543",
+            "ids": ["34554543", "7643534"],
+            "details": {"name": "Red Shoes"},
+            "discount": null
+        });
+        let resolve = |arg: &str| {
+            resolve_function(&[&input], "asArray", &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve("/id"), Ok(json!(["34554543"])));
+        assert_eq!(resolve("/ids"), Ok(json!(["34554543", "7643534"])));
+        assert_eq!(resolve("/details"), Ok(json!([{"name": "Red Shoes"}])));
+        assert_eq!(resolve("/discount"), Ok(json!([])));
+        assert_eq!(resolve("/missing?"), Ok(json!([])));
+        assert_eq!(resolve("'a'"), Ok(json!(["a"])));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let input = json!({
+            "id": "34554543",
+            "nested": [[["a", "b"], ["c"]], [[], [["d"]]], "e", [null, {"f": [1, [2]]}]],
+            "discount": null
+        });
+        let resolve = |arg: &str| {
+            resolve_function(&[&input], "flatten", &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve("/nested"),
+            Ok(json!(["a", "b", "c", "d", "e", null, {"f": [1, [2]]}]))
+        );
+        assert_eq!(resolve("/id"), Ok(json!(["34554543"])));
+        assert_eq!(resolve("/discount"), Ok(json!([])));
+        assert_eq!(resolve("/missing?"), Ok(json!([])));
+    }
+
+    #[test]
+    fn test_exists() {
+        let input = json!({
+            "order": {"discount_code": "SUMMER", "coupon": null, "items": [{"sku": "SKU-123"}]}
+        });
+        let resolve = |arg: &str| {
+            resolve_function(&[&input], "exists", &[arg], &Default::default())
+                .map(|result| result.unwrap())
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve("/order/discount_code"), Ok(json!(true)));
+        assert_eq!(resolve("/order/coupon"), Ok(json!(true)));
+        assert_eq!(resolve("/order/items/sku"), Ok(json!(true)));
+        assert_eq!(resolve("/order/gift_card"), Ok(json!(false)));
+        assert_eq!(resolve("/order/discount_code/amount"), Ok(json!(false)));
+        assert_eq!(resolve("/order/gift_card?"), Ok(json!(false)));
+        assert_eq!(resolve("/order/gift_card?/amount"), Ok(json!(false)));
+        assert_eq!(resolve("/order/coupon?"), Ok(json!(true)));
+        assert_eq!(resolve("/order/items[?gift_wrap]?/sku"), Ok(json!(true)));
+        assert_eq!(
+            without_optional_markers("/order/[\"a?\"]/items[?gift]?/sku"),
+            "/order/[\"a?\"]/items[?gift]/sku"
+        );
+        assert_eq!(
+            resolve_function(
+                &[&input],
+                "exists",
+                &["/order/discount_code", "/order/coupon"],
+                &Default::default()
+            )
+            .unwrap_err()
+            .to_string(),
+            "Bad function format; exists expects 1 arguments, got 2"
+        );
+    }
+
+    #[test]
+    fn test_coalesce_array() {
+        let input = json!({
+            "phone_home": "555-0100",
+            "phone_work": null,
+            "phone_mobile": "555-0199",
+            "phones": ["555-0123"]
+        });
+        let resolve = |args: &[&str]| {
+            resolve_function(&[&input], "coalesceArray", args, &Default::default())
+                .map(|result| result.unwrap())
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve(&["/phone_home", "/phone_work", "/phone_mobile", "/phone_fax"]),
+            Ok(json!(["555-0100", "555-0199"]))
+        );
+        assert_eq!(
+            resolve(&["/phone_fax", "/phones", "'555-0000'"]),
+            Ok(json!([["555-0123"], "555-0000"]))
+        );
+        assert_eq!(resolve(&["/phone_work", "/phone_fax"]), Ok(json!([])));
+        assert_eq!(
+            resolve(&[]),
+            Err(
+                "Bad function format; coalesceArray expects at least 1 arguments, got 0"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_pick() {
+        let input = json!({
+            "details": {"name": "Red Shoes", "manufacture": "company", "internal_id": 7},
+            "ids": ["34554543"]
+        });
+        let resolve = |args: &[&str], options: &TransformOptions| {
+            resolve_function(&[&input], "pick", args, options)
+                .map(|result| result.map_err(|err| err.to_string()))
+                .map_err(|err| err.to_string())
+        };
+        let options = TransformOptions::default();
+
+        assert_eq!(
+            resolve(&["/details", "'name'", "'manufacture'"], &options),
+            Ok(Ok(json!({"name": "Red Shoes", "manufacture": "company"})))
+        );
+        assert_eq!(
+            resolve(&["/details", "'name'", "'color'"], &options),
+            Ok(Ok(json!({"name": "Red Shoes"})))
+        );
+        assert_eq!(
+            resolve(&["/discount?", "'code'"], &options),
+            Ok(Ok(Value::Null))
+        );
+        assert_eq!(
+            resolve(&["/ids", "'name'"], &options),
+            Ok(Err(
+                "Failed to resolve pick; the value should be an object: [\"34554543\"]".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/details"], &options),
+            Err("Bad function format; pick expects at least 2 arguments, got 1".to_string())
+        );
+        assert_eq!(
+            resolve(&["/details", "/ids"], &options),
+            Err(
+                "Bad function format; the pick field names should be strings: [\"34554543\"]"
+                    .to_string()
+            )
+        );
+
+        let options = TransformOptions {
+            strict_pick: true,
+            ..Default::default()
+        };
+        assert!(resolve(&["/details", "'name'"], &options).unwrap().is_ok());
+        assert!(resolve(&["/details", "'name'", "'color'"], &options)
+            .unwrap()
+            .unwrap_err()
+            .starts_with("Failed to resolve pick; couldn't find the field color in the object"));
+    }
+
+    #[test]
+    fn test_omit() {
+        let input = json!({
+            "product": {"name": "Red Shoes", "internal_id": 7, "details": {"size": 42}},
+            "ids": ["34554543"]
+        });
+        let resolve = |args: &[&str]| {
+            resolve_function(&[&input], "omit", args, &Default::default())
+                .map(|result| result.map_err(|err| err.to_string()))
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve(&["/product/**", "'internal_id'"]),
+            Ok(Ok(json!({"name": "Red Shoes", "details": {"size": 42}})))
+        );
+        assert_eq!(
+            resolve(&["/product", "'internal_id'", "'details'", "'color'"]),
+            Ok(Ok(json!({"name": "Red Shoes"})))
+        );
+        assert_eq!(resolve(&["/discount?", "'code'"]), Ok(Ok(Value::Null)));
+        assert_eq!(
+            resolve(&["/ids", "'name'"]),
+            Ok(Err(
+                "Failed to resolve omit; the value should be an object: [\"34554543\"]".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/product"]),
+            Err("Bad function format; omit expects at least 2 arguments, got 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_set() {
+        let input = json!({
+            "ids": ["34554543", "7643534", "34554543"],
+            "codes": [7, 1.5],
+            "lines": [{"sku": "a"}],
+            "id": "34554543",
+            "none": []
+        });
+        let resolve = |arg: &str| {
+            resolve_function(&[&input], "toSet", &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve("/ids"),
+            Ok(json!({"34554543": true, "7643534": true}))
+        );
+        assert_eq!(resolve("/codes"), Ok(json!({"7": true, "1.5": true})));
+        assert_eq!(resolve("/none"), Ok(json!({})));
+        assert_eq!(
+            resolve("/lines"),
+            Err(
+                "Failed to resolve toSet; the elements should be strings or numbers, not a object: {\"sku\":\"a\"}"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve("/id"),
+            Err("Failed to resolve toSet; the value should be an array: \"34554543\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_not() {
+        let input = json!({
+            "in_stock": true,
+            "discontinued": false,
+            "discount": null,
+            "stock": 0,
+            "price": 12.5,
+            "name": "Red Shoes",
+            "ids": []
+        });
+        let resolve = |arg: &str, options: &TransformOptions| {
+            resolve_function(&[&input], "not", &[arg], options)
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+        let options = TransformOptions::default();
+
+        assert_eq!(resolve("/in_stock", &options), Ok(json!(false)));
+        assert_eq!(resolve("/discontinued", &options), Ok(json!(true)));
+        assert_eq!(resolve("/discount", &options), Ok(json!(true)));
+        assert_eq!(resolve("/missing?", &options), Ok(json!(true)));
+        assert_eq!(resolve("/stock", &options), Ok(json!(true)));
+        assert_eq!(resolve("/price", &options), Ok(json!(false)));
+        assert_eq!(resolve("/name", &options), Ok(json!(false)));
+        assert_eq!(resolve("/ids", &options), Ok(json!(true)));
+
+        let options = TransformOptions {
+            strict_not: true,
+            ..Default::default()
+        };
+        assert_eq!(resolve("/in_stock", &options), Ok(json!(false)));
+        assert_eq!(resolve("/discount", &options), Ok(json!(true)));
+        assert_eq!(
+            resolve("/stock", &options),
+            Err(
+                "Failed to resolve not; the value should be a boolean, not a number: 0".to_string()
+            )
+        );
+        assert_eq!(
+            resolve("/name", &options),
+            Err(
+                "Failed to resolve not; the value should be a boolean, not a string: \"Red Shoes\""
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        let input = json!({"status": "S", "unknown": "X", "code": 2, "tags": ["S"], "none": null});
+        let resolve = |args: &[&str]| {
+            resolve_function(&[&input], "translate", args, &Default::default())
+                .map(|result| result.map_err(|err| err.to_string()))
+                .map_err(|err| err.to_string())
+        };
+        let table = "{'S': 'shipped', 'P': 'pending', 'It''s': \"it's\", '2': 'two'}";
+
+        assert_eq!(resolve(&["/status", table]), Ok(Ok(json!("shipped"))));
+        assert_eq!(resolve(&["/code", table]), Ok(Ok(json!("two"))));
+        assert_eq!(resolve(&["'It''s'", table]), Ok(Ok(json!("it's"))));
+        assert_eq!(
+            resolve(&["/status", r#"{"S": "shipped", "P": 1}"#]),
+            Ok(Ok(json!("shipped")))
+        );
+        assert_eq!(
+            resolve(&["/status", "{'S': true, 'P': null}"]),
+            Ok(Ok(json!(true)))
+        );
+        assert_eq!(
+            resolve(&["/unknown", table, "'other'"]),
+            Ok(Ok(json!("other")))
+        );
+        assert_eq!(resolve(&["/none", table, "/status"]), Ok(Ok(json!("S"))));
+        assert_eq!(
+            resolve(&["/unknown", table]),
+            Ok(Err(
+                "Failed to resolve translate; the value has no entry in the table: \"X\""
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/tags", table]),
+            Ok(Err("Failed to resolve translate; the value should be a string or a number, not a array: [\"S\"]".to_string()))
+        );
+        assert_eq!(
+            resolve(&["/status", "{'S' 'shipped'}"]),
+            Err("Bad function format; the object literal should map quoted keys to values: {'S' 'shipped'}".to_string())
+        );
+        assert_eq!(
+            resolve(&["/status", "/tags"]),
+            Err("Bad function format; the translate table should be an object literal, not a array: [\"S\"]".to_string())
+        );
+        assert_eq!(
+            resolve(&["/status"]),
+            Err("Bad function format; translate expects 2 arguments, got 1".to_string())
+        );
+        assert_eq!(
+            resolve(&["/status", table, "{'X': 'unknown'}"]),
+            Err("Bad function format; an object literal is only allowed as the translate table: {'X': 'unknown'}".to_string())
+        );
+        assert_eq!(
+            resolve_function(&[&input], "size", &["{'S': 'shipped'}"], &Default::default())
+                .unwrap_err()
+                .to_string(),
+            "Bad function format; an object literal is only allowed as the translate table: {'S': 'shipped'}"
+        );
+    }
+
+    #[test]
+    fn test_ratio() {
+        let input = json!({"sold": 3, "total": 4, "price": 2.5, "none": 0, "hundred": 100, "name": "Red Shoes"});
+        let resolve = |args: &[&str], options: &TransformOptions| {
+            resolve_function(&[&input], "ratio", args, options)
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+        let options = TransformOptions::default();
+
+        assert_eq!(resolve(&["/sold", "/total"], &options), Ok(json!(0.75)));
+        assert_eq!(resolve(&["/total", "/total"], &options), Ok(json!(1.0)));
+        assert_eq!(resolve(&["/price", "/total"], &options), Ok(json!(0.625)));
+        assert_eq!(
+            resolve(&["/name", "/total"], &options),
+            Err(
+                "Failed to resolve ratio; the operands should be numbers, not a string: \"Red Shoes\""
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve(&["/sold", "/none"], &options),
+            Err("Failed to resolve ratio; division by zero: 3 / 0".to_string())
+        );
+
+        let options = TransformOptions {
+            zero_ratio_on_zero_denominator: true,
+            ..Default::default()
+        };
+        assert_eq!(resolve(&["/sold", "/none"], &options), Ok(json!(0.0)));
+        assert!(resolve(&["/sold", "/name"], &options).is_err());
+
+        // composes with format into percentages
+        assert_eq!(
+            resolve_alternative(
+                &Contexts::new(&[&input]),
+                "format(mul(ratio(/sold, /total), /hundred), '%.1f%%')",
+                &options,
+            )
+            .unwrap()
+            .unwrap(),
+            json!("75.0%")
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let input = json!({"sold": 10, "total": 3, "pairs": 2, "price": 2.5, "name": "Red Shoes"});
+        let resolve = |name: &str, args: &[&str]| {
+            resolve_function(&[&input], name, args, &Default::default())
+                .map(|result| result.map_err(|err| err.to_string()))
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve("add", &["/sold", "/total"]), Ok(Ok(json!(13))));
+        assert_eq!(resolve("sub", &["/total", "/sold"]), Ok(Ok(json!(-7))));
+        assert_eq!(resolve("mul", &["/price", "/total"]), Ok(Ok(json!(7.5))));
+        assert_eq!(resolve("div", &["/sold", "/pairs"]), Ok(Ok(json!(5))));
+        assert_eq!(
+            resolve("div", &["add(/sold, /pairs)", "/total"]),
+            Ok(Ok(json!(4)))
+        );
+        assert_eq!(
+            resolve("div", &["/sold", "/total"]),
+            Ok(Ok(json!(10.0 / 3.0)))
+        );
+        assert_eq!(
+            resolve("div", &["/sold", "sub(/total, /total)"]),
+            Ok(Err(
+                "Failed to resolve div; division by zero: 10 / 0".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve("add", &["/sold", "/name"]),
+            Ok(Err(
+                "Failed to resolve add; the operands should be numbers, not a string: \"Red Shoes\""
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            resolve("mul", &["/sold"]),
+            Err("Bad function format; mul expects 2 arguments, got 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let input = json!({
+            "id": 42,
+            "balance": -7,
+            "price": 12.5,
+            "big": u64::MAX,
+            "name": "Red Shoes",
+            "city": "Zürich",
+            "flag": true
+        });
+        let resolve = |args: &[&str]| {
+            resolve_function(&[&input], "format", args, &Default::default())
+                .map(|result| result.map_err(|err| err.to_string()))
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve(&["/id", "'%08d'"]), Ok(Ok(json!("00000042"))));
+        assert_eq!(resolve(&["/id", "'PO-%d'"]), Ok(Ok(json!("PO-42"))));
+        assert_eq!(resolve(&["/id", "'%5d|'"]), Ok(Ok(json!("   42|"))));
+        assert_eq!(resolve(&["/id", "'%-5d|'"]), Ok(Ok(json!("42   |"))));
+        assert_eq!(resolve(&["/id", "'%+d'"]), Ok(Ok(json!("+42"))));
+        assert_eq!(resolve(&["/balance", "'%05d'"]), Ok(Ok(json!("-0007"))));
+        assert_eq!(resolve(&["/id", "'%04X'"]), Ok(Ok(json!("002A"))));
+        assert_eq!(
+            resolve(&["/big", "'%x'"]),
+            Ok(Ok(json!("ffffffffffffffff")))
+        );
+        assert_eq!(resolve(&["/price", "'%.2f%%'"]), Ok(Ok(json!("12.50%"))));
+        assert_eq!(resolve(&["/price", "'%08.3f'"]), Ok(Ok(json!("0012.500"))));
+        assert_eq!(resolve(&["/id", "'%f'"]), Ok(Ok(json!("42.000000"))));
+        assert_eq!(
+            resolve(&["/name", "'%-10s|'"]),
+            Ok(Ok(json!("Red Shoes |")))
+        );
+        assert_eq!(resolve(&["/name", "'%.3s'"]), Ok(Ok(json!("Red"))));
+        assert_eq!(resolve(&["/city", "'%.2s'"]), Ok(Ok(json!("Zü"))));
+        assert_eq!(resolve(&["/city", "'%-8s|'"]), Ok(Ok(json!("Zürich  |"))));
+        assert_eq!(resolve(&["/name", "'%010s'"]), Ok(Ok(json!(" Red Shoes"))));
+        assert_eq!(resolve(&["/missing?", "'%d'"]), Ok(Ok(Value::Null)));
+
+        assert_eq!(
+            resolve(&["/price", "'%d'"]),
+            Ok(Err(
+                "Failed to resolve format; %d expects an integer, not a number: 12.5".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/balance", "'%x'"]),
+            Ok(Err(
+                "Failed to resolve format; %x expects a non-negative integer, not a number: -7"
+                    .to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/id", "'%s'"]),
+            Ok(Err(
+                "Failed to resolve format; %s expects a string, not a number: 42".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/flag", "'%f'"]),
+            Ok(Err(
+                "Failed to resolve format; %f expects a number, not a boolean: true".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["/id", "'%e'"]),
+            Err("Bad function format; unsupported conversion specifier in the format pattern %e, it should be one of %d, %f, %s, %x or %X".to_string())
+        );
+        assert_eq!(
+            resolve(&["/id", "'%99999999999999999999999d'"]),
+            Err("Bad function format; the width of the format pattern %99999999999999999999999d should be at most 1024".to_string())
+        );
+        assert_eq!(
+            resolve(&["/price", "'%.1025f'"]),
+            Err("Bad function format; the precision of the format pattern %.1025f should be at most 1024".to_string())
+        );
+        assert_eq!(
+            resolve(&["/id", "'%1024d'"])
+                .unwrap()
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .len(),
+            1024
+        );
+        assert_eq!(
+            resolve(&["/id", "'%.2d'"]),
+            Err("Bad function format; unsupported conversion specifier in the format pattern %.2d, it should be one of %d, %f, %s, %x or %X".to_string())
+        );
+        assert_eq!(
+            resolve(&["/id", "'%d-%d'"]),
+            Err(
+                "Bad function format; the format pattern %d-%d should have a single conversion specifier"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve(&["/id", "'100%%'"]),
+            Err(
+                "Bad function format; the format pattern 100%% has no conversion specifier"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve(&["/id", "/id"]),
+            Err("Bad function format; the format pattern should be a string: 42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_keys_and_values() {
+        let input = json!({
+            "warehouses": {"berlin": {"stock": 4}, "paris": {"stock": 3}},
+            "ids": ["34554543"]
+        });
+        let resolve = |name: &str, arg: &str| {
+            resolve_function(&[&input], name, &[arg], &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve("@keys", "/warehouses"),
+            Ok(json!(["berlin", "paris"]))
+        );
+        assert_eq!(
+            resolve("@values", "/warehouses"),
+            Ok(json!([{"stock": 4}, {"stock": 3}]))
+        );
+        assert_eq!(
+            resolve("@keys", "/ids"),
+            Err(
+                "Failed to resolve @keys; the value should be an object: [\"34554543\"]"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve("@values", "/warehouses/berlin/stock"),
+            Err("Failed to resolve @values; the value should be an object: 4".to_string())
+        );
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_hash() {
+        let input = json!({
+            "product": {
+                "sku": "SKU-123",
+                "name": "Red Shoes",
+                "price": {"currency": "USD", "amount": 1250},
+                "tags": ["new", "sale"]
+            },
+            "discount": null
+        });
+        let resolve = |name: &str, arg: &str| {
+            resolve_function(&[&input], name, &[arg], &Default::default())
+                .unwrap()
+                .unwrap()
+        };
+
+        assert_eq!(
+            resolve("sha256", "/product"),
+            json!("d0e36647bd9c355bd11c9132087fbf23414b1205a7fa4d74119233013b096fd2")
+        );
+        assert_eq!(
+            resolve("md5", "/product"),
+            json!("b335fd35b3638e6b24d189196aacdedc")
+        );
+        assert_eq!(
+            resolve("sha256", "/product/sku"),
+            json!("3bf695a9e7e89e88314c6861f5b9ff028ca2527d25cfb07f5fe0a05023982e27")
+        );
+        assert_eq!(
+            resolve("md5", "/discount"),
+            json!("37a6259cc0c1dae299a7866489dff0bd")
+        );
+    }
+
+    #[cfg(not(feature = "hash"))]
+    #[test]
+    fn test_hash_requires_feature() {
+        let input = json!({"product": {"sku": "SKU-123"}});
+        assert_eq!(
+            resolve_function(&[&input], "sha256", &["/product"], &Default::default())
+                .unwrap_err()
+                .to_string(),
+            "Bad function format; the sha256 function requires the hash feature"
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex() {
+        let input = json!({"notes": "ship PO-1234 today", "count": 1});
+        let resolve = |args: &[&str]| {
+            resolve_function(&[&input], "regex", args, &Default::default())
+                .unwrap()
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(resolve(&["/notes", r"'PO-(\d+)'"]), Ok(json!("1234")));
+        assert_eq!(resolve(&["/notes", r"'PO-\d+'"]), Ok(json!("PO-1234")));
+        assert_eq!(
+            resolve(&["/notes", r"'SO-(\d+)'"]),
+            Err(
+                r"Failed to resolve regex; the pattern SO-(\d+) doesn't match ship PO-1234 today"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve(&["/count", "'1'"]),
+            Err("Failed to resolve regex; the value should be a string: 1".to_string())
+        );
+        assert_eq!(
+            resolve_function(&[&input], "regex", &["/notes", "'('"], &Default::default())
+                .err()
+                .unwrap()
+                .to_string()
+                .lines()
+                .next(),
+            Some("Bad function format; invalid regex pattern (: regex parse error:")
+        );
+    }
+}
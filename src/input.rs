@@ -0,0 +1,200 @@
+use crate::diagnostic::DuplicateKeysError;
+use crate::options::TransformOptions;
+use crate::path::escape_pointer_key;
+use alloc::{format, string::String, vec, vec::Vec};
+use anyhow::{Context, Result};
+use core::fmt;
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+
+// Parses a JSON document, `name` tells the input from the output in the errors. JSON parsers keep
+// the last value of a duplicated key, with the `warn_on_duplicate_input_keys` option the JSON
+// pointers of the duplicated keys are returned along with the document, in document order; with
+// the `deny_duplicate_input_keys` option, a document with duplicated keys fails with them.
+pub fn parse_json(
+    content: &str,
+    name: &str,
+    options: &TransformOptions,
+) -> Result<(Value, Vec<String>)> {
+    if !options.warn_on_duplicate_input_keys && !options.deny_duplicate_input_keys {
+        let value = serde_json::from_str(content)
+            .with_context(|| format!("Failed to parse {} JSON", name))?;
+        return Ok((value, vec![]));
+    }
+    let (value, duplicate_keys) = parse_json_with_duplicates(content)
+        .with_context(|| format!("Failed to parse {} JSON", name))?;
+    if options.deny_duplicate_input_keys && !duplicate_keys.is_empty() {
+        return Err(DuplicateKeysError::new(duplicate_keys).into());
+    }
+    Ok((value, duplicate_keys))
+}
+
+// parses a JSON document along with the JSON pointers of its duplicated keys, in document order
+fn parse_json_with_duplicates(content: &str) -> serde_json::Result<(Value, Vec<String>)> {
+    let mut duplicate_keys = vec![];
+    let mut deserializer = serde_json::Deserializer::from_str(content);
+    let value = ValueSeed {
+        pointer: String::new(),
+        duplicate_keys: &mut duplicate_keys,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok((value, duplicate_keys))
+}
+
+// Deserializes a JSON value the same way as `Value` does, except that the duplicated keys of its
+// objects are recorded instead of being silently overridden
+struct ValueSeed<'a> {
+    // JSON pointer of the value being deserialized
+    pointer: String,
+    duplicate_keys: &'a mut Vec<String>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for ValueSeed<'a> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for ValueSeed<'a> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = vec![];
+        while let Some(value) = seq.next_element_seed(ValueSeed {
+            pointer: format!("{}/{}", self.pointer, values.len()),
+            duplicate_keys: &mut *self.duplicate_keys,
+        })? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut obj = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let pointer = format!("{}/{}", self.pointer, escape_pointer_key(&key));
+            let value = map.next_value_seed(ValueSeed {
+                pointer: pointer.clone(),
+                duplicate_keys: &mut *self.duplicate_keys,
+            })?;
+            if obj.insert(key, value).is_some() {
+                self.duplicate_keys.push(pointer);
+            }
+        }
+        Ok(Value::Object(obj))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_json_with_duplicates() {
+        let (value, duplicate_keys) = parse_json_with_duplicates(
+            r#"{"order": {"id": 1, "id": 2, "a/b": 1, "a/b": 2}, "items": [{"sku": "a", "sku": "b"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value,
+            json!({"order": {"id": 2, "a/b": 2}, "items": [{"sku": "b"}]})
+        );
+        assert_eq!(
+            duplicate_keys,
+            vec!["/order/id", "/order/a~1b", "/items/0/sku"]
+        );
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let options = TransformOptions {
+            deny_duplicate_input_keys: true,
+            ..Default::default()
+        };
+        let content = r#"{"id": 1.5, "tags": [null, true, "a"], "total": -2}"#;
+
+        assert_eq!(
+            parse_json(content, "input", &options).unwrap(),
+            parse_json(content, "input", &Default::default()).unwrap()
+        );
+        assert!(parse_json(content, "input", &options).unwrap().1.is_empty());
+        assert_eq!(
+            parse_json("{\"id\": 1} x", "input", &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to parse input JSON"
+        );
+    }
+
+    #[test]
+    fn test_parse_json_duplicate_keys() {
+        let options = TransformOptions {
+            deny_duplicate_input_keys: true,
+            ..Default::default()
+        };
+        let content = r#"{"id": 1, "items": [{"sku": "a", "sku": "b"}], "id": 2}"#;
+
+        assert_eq!(
+            parse_json(content, "input", &Default::default()).unwrap(),
+            (json!({"id": 2, "items": [{"sku": "b"}]}), vec![])
+        );
+        let warn_options = TransformOptions {
+            warn_on_duplicate_input_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_json(content, "input", &warn_options).unwrap(),
+            (
+                json!({"id": 2, "items": [{"sku": "b"}]}),
+                vec!["/items/0/sku".to_string(), "/id".to_string()]
+            )
+        );
+        let err = parse_json(content, "input", &options).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse input JSON; duplicated keys: /items/0/sku, /id"
+        );
+        assert_eq!(
+            err.downcast_ref::<DuplicateKeysError>().unwrap().pointers(),
+            ["/items/0/sku", "/id"]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::transformer::Contexts;
+use anyhow::Result;
+use serde_json::Value;
+
+// prefix of a mapping resolved as a JSONPath query against the whole input, example
+// `jsonpath:$.order.shipments[*].tracking_number`
+pub const JSONPATH_PREFIX: &str = "jsonpath:";
+
+// Resolves a `jsonpath:` mapping against the input roots, tried in order until one has a node
+// selected by the query, `None` is returned if the value is not a `jsonpath:` mapping. A singular
+// query resolves to the node it selects, or fails to resolve if it selects none, any other query
+// resolves to the array of the nodes it selects. The outer result is an error if the query is
+// malformed.
+pub fn resolve_jsonpath(contexts: &Contexts, value: &str) -> Result<Option<Result<Value>>> {
+    let query = match value.strip_prefix(JSONPATH_PREFIX) {
+        Some(query) => query.trim(),
+        None => return Ok(None),
+    };
+    let roots = contexts.get(0).unwrap_or_default();
+    query_roots(roots, query).map(Some)
+}
+
+#[cfg(feature = "jsonpath")]
+fn query_roots(roots: &[&Value], query: &str) -> Result<Result<Value>> {
+    use anyhow::anyhow;
+    use serde_json_path::JsonPath;
+
+    let name = JSONPATH_PREFIX.trim_end_matches(':');
+    let path =
+        JsonPath::parse(query).map_err(|err| anyhow!("Bad {} format; {}: {}", name, err, query))?;
+    let nodes = roots
+        .iter()
+        .map(|root| path.query(root).all())
+        .find(|nodes| !nodes.is_empty())
+        .unwrap_or_default();
+    if !is_singular(query) {
+        return Ok(Ok(Value::Array(nodes.into_iter().cloned().collect())));
+    }
+    Ok(match nodes.first() {
+        Some(node) => Ok((*node).clone()),
+        None => Err(anyhow!(
+            "Failed to resolve {}; the query selects no node: {}",
+            name,
+            query
+        )),
+    })
+}
+
+#[cfg(not(feature = "jsonpath"))]
+fn query_roots(_: &[&Value], query: &str) -> Result<Result<Value>> {
+    anyhow::bail!(
+        "Bad {} format; JSONPath queries require the jsonpath feature: {}",
+        JSONPATH_PREFIX.trim_end_matches(':'),
+        query
+    )
+}
+
+// Returns true if the query is singular, that is only made of name and index selectors so that it
+// selects at most one node, example `$.order.id` or `$.items[0]['sku']`. The wildcards, the
+// descendant segments, the slices, the unions and the filters make it select a list of nodes.
+#[cfg(feature = "jsonpath")]
+fn is_singular(query: &str) -> bool {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous = None;
+    for c in query.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote.is_some() => escaped = true,
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            _ if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {}
+            '*' | '?' | ':' | ',' => return false,
+            '.' if previous == Some('.') => return false,
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(feature = "jsonpath")]
+    #[test]
+    fn test_resolve_jsonpath() {
+        let input = json!({
+            "order": {
+                "id": 1,
+                "shipments": [
+                    {"tracking_number": "1Z999", "items": [{"sku": "SKU-123", "quantity": 4}]},
+                    {"tracking_number": "1Z888", "items": [{"sku": "SKU-343", "quantity": 1}]}
+                ]
+            }
+        });
+        let resolve = |value: &str| {
+            resolve_jsonpath(&Contexts::new(&[&input]), value)
+                .map(|result| result.unwrap().map_err(|err| err.to_string()))
+                .map_err(|err| err.to_string())
+        };
+
+        assert!(resolve_jsonpath(&Contexts::new(&[&input]), "/order/id")
+            .unwrap()
+            .is_none());
+        assert_eq!(resolve("jsonpath:$.order.id"), Ok(Ok(json!(1))));
+        assert_eq!(
+            resolve("jsonpath:$.order.shipments[*].tracking_number"),
+            Ok(Ok(json!(["1Z999", "1Z888"])))
+        );
+        assert_eq!(
+            resolve("jsonpath:$..items[?@.quantity > 1].sku"),
+            Ok(Ok(json!(["SKU-123"])))
+        );
+        assert_eq!(
+            resolve("jsonpath:$.order.shipments[-1]['tracking_number']"),
+            Ok(Ok(json!("1Z888")))
+        );
+        assert_eq!(resolve("jsonpath:$.order.returns[*]"), Ok(Ok(json!([]))));
+        assert_eq!(
+            resolve("jsonpath:$.order.returns"),
+            Ok(Err(
+                "Failed to resolve jsonpath; the query selects no node: $.order.returns"
+                    .to_string()
+            ))
+        );
+        assert!(resolve("jsonpath:$.order[").is_err());
+    }
+
+    #[cfg(feature = "jsonpath")]
+    #[test]
+    fn test_is_singular() {
+        assert!(is_singular("$.order.id"));
+        assert!(is_singular("$.items[0]['a.b*']"));
+        assert!(!is_singular("$.items[*]"));
+        assert!(!is_singular("$..sku"));
+        assert!(!is_singular("$.items[0:2]"));
+        assert!(!is_singular("$.items[0,1]"));
+        assert!(!is_singular("$.items[?@.sku]"));
+    }
+
+    #[cfg(not(feature = "jsonpath"))]
+    #[test]
+    fn test_resolve_jsonpath_requires_feature() {
+        let input = json!({"order": {"id": 1}});
+        assert_eq!(
+            resolve_jsonpath(&Contexts::new(&[&input]), "jsonpath:$.order.id")
+                .unwrap_err()
+                .to_string(),
+            "Bad jsonpath format; JSONPath queries require the jsonpath feature: $.order.id"
+        );
+    }
+}
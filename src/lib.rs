@@ -1,9 +1,69 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod arithmetic;
+mod builder;
+mod condition;
+#[cfg(feature = "csv")]
+mod csv;
+mod diagnostic;
+mod diff;
+mod dsl;
+mod explain;
+mod function;
+mod input;
+mod jsonpath;
+mod merge;
+mod observer;
+mod options;
+pub mod path;
+mod resolver;
+#[cfg(feature = "jsonschema")]
+mod schema;
+mod template;
+mod timestamp;
 mod transformer;
+#[cfg(feature = "xml")]
+mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
 
-use crate::transformer::{process_array_convertible_objs, traverse_mut};
-use anyhow::{anyhow, Result};
+pub use crate::builder::TemplateBuilder;
+pub use crate::diagnostic::{BatchError, DiagnosticError, DuplicateKeysError, ElementError};
+pub use crate::diff::TemplateChange;
+pub use crate::explain::ArrayConversionReport;
+pub use crate::observer::TransformObserver;
+pub use crate::options::{ArrayMergePolicy, RelativePathPolicy, SpreadPolicy, TransformOptions};
+pub use crate::resolver::{DefaultLeafResolver, LeafContext, LeafResolver};
+pub use crate::template::{SplitIter, Template};
+pub use crate::transformer::SplitObjects;
+#[cfg(feature = "xml")]
+pub use crate::xml::XML_ATTRIBUTE_PREFIX;
+
+use crate::builder::pointers_to_output;
+use crate::condition::{is_included, WHEN_KEY};
+use crate::diagnostic::{prefix_field_error, FieldError};
+use crate::diff::{collect_mappings, diff_mappings};
+use crate::explain::{explain_array_conversions, mark_nested_spread_fields};
+use crate::merge::merge_values;
+use crate::path::{clean_key, is_comment};
+use crate::transformer::{
+    convert_array_convertible_objs, describe_types, empty_to_null, traverse_mut_in, Contexts,
+    IndexFields,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{format, string::String, vec, vec::Vec};
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{de::DeserializeOwned, Serialize};
-use serde_json::{to_string_pretty, to_value, Value};
+use serde_json::{to_string_pretty, to_value, Map, Value};
+#[cfg(feature = "fs")]
+use std::{fs, path::Path};
+
+// name of the output object that is converted into an array without the wrapper object
+const ROOT_ARRAY_NAME: &str = "[]";
+// key of the metadata of an output object of the template, it is never transformed
+const META_KEY: &str = "@meta";
 
 /// Takes an input object and transform into an object that is the same structure as the passed output.
 /// The output object's field values must contains the mapping details from the input object.
@@ -127,13 +187,26 @@ use serde_json::{to_string_pretty, to_value, Value};
 /// `quantity` in the output object has mapping `/order/shipments/items/quantity`, which is a field
 /// inside an array, `shipments`, of arrays of objects, `items`.
 ///
+/// # Output names
+/// The top level keys of each output array element are the names of the output objects, an element
+/// can hold several of them, e.g. `[{"order": {...}, "customer": {...}}]`, they are all transformed
+/// into the same element. Names should be unique across the output array, duplicated names fail
+/// the transform unless the [`TransformOptions`] `allow_duplicate_names` option is set.
+/// [`transform_named`] returns the transformed output objects by name instead, e.g. to route each
+/// of them differently. They are in the order of the output array elements, and the names of an
+/// element are sorted, e.g. `customer` before `order`, since JSON objects keep their keys sorted.
 /// # Concerting objects to array
 /// Transform can also convert output object, or any descendant child object, into an array. In order to
 /// do that, the object must have an array child which will be spread across the array of the objects.
 /// Each object of the array, its original array field will be turned into a regular field instead of array
 /// and that field will take one element from the original array. To enable this feature, a special field
 /// decoration is required. To convert a specific object to array, it needs to be wrapped with square
-/// brackets, i.g: `[order]`, also the child array to be spread should look like `...ids`.
+/// brackets, i.g: `[order]`, also the child array to be spread should look like `...ids`. A
+/// spread field inside an array convertible object that resolves to a string, a number or a
+/// boolean fails the transform, since there is nothing to spread. The elements of the spread array
+/// can be objects, each object goes whole into its own object of the array, e.g.
+/// `{"[items]": {"...item": "/order/shipments/items"}}` gives one object per item with the item
+/// object under `item`.
 /// Here is an example of an output json object:
 /// ```json
 /// [
@@ -299,6 +372,210 @@ use serde_json::{to_string_pretty, to_value, Value};
 ///   }
 /// ]
 /// ```
+/// A spread array of nested arrays, i.g `[["SKU-123", "SKU-343"], ["SKU-1453"]]`, is flattened first so
+/// that each leaf element gets its own object.
+///
+/// A field of an array convertible object mapped to `@index` resolves to the index of each object
+/// in the array, i.g `{"[items]": {"...sku": "/skus", "line": "@index"}}` numbers the items from
+/// `0`. It fails the transform outside of an array convertible object or a
+/// [`@range`](#repeating-templates) body.
+///
+/// The output object itself can be converted into an array without the wrapper object by naming it
+/// `[]`, the transformed output element is then the array instead of an object. It should be the
+/// only field of the output object. Example:
+///
+/// ```json
+/// [
+///   {
+///     "[]": {
+///       "...item_id": "/ids",
+///       "account_id": "/retailer/id"
+///     }
+///   }
+/// ]
+/// ```
+/// The above result will be:
+/// ```json
+/// [
+///   [
+///     {"item_id": "34554543", "account_id": "12342"},
+///     {"item_id": "7643534", "account_id": "12342"},
+///     {"item_id": "512342", "account_id": "12342"}
+///   ]
+/// ]
+/// ```
+/// # Iterating arrays
+/// An output object with an `@each` key resolves to an array, its other fields are resolved once
+/// per element of the input array `@each` maps to. Inside an `@each` body, paths that don't start
+/// with `/` are relative to the current element, while paths starting with `/` are still resolved
+/// against the input root. Each leading `..` moves one iteration out, so nested `@each` bodies can
+/// reach the element of the enclosing iteration, e.g. `../tracking_number`; going above the input
+/// root is an error. Outside of `@each` bodies relative paths are resolved against the input root,
+/// unless the [`TransformOptions`] `relative_paths` option is [`RelativePathPolicy::Strict`].
+/// `@sibling:field` reads a field of the current element, it can't reach out of the element and is
+/// an error outside of an `@each` body. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"shipments": [
+///     {"tracking_number": "1234567", "items": [{"sku": "SKU-123"}, {"sku": "SKU-343"}]},
+///     {"tracking_number": "98776", "items": [{"sku": "SKU-1453"}]}
+/// ]});
+/// let output = json!([{
+///     "shipments": {
+///         "@each": "/shipments",
+///         "items": {"@each": "items", "sku": "sku", "tracking_number": "../tracking_number"}
+///     }
+/// }]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"shipments": [
+///         {"items": [
+///             {"sku": "SKU-123", "tracking_number": "1234567"},
+///             {"sku": "SKU-343", "tracking_number": "1234567"}
+///         ]},
+///         {"items": [{"sku": "SKU-1453", "tracking_number": "98776"}]}
+///     ]}])
+/// );
+/// ```
+/// # Repeating templates
+/// An output object with a `@range` key resolves to an array, its other fields are resolved as many
+/// times as the count `@range` maps to, a number or a mapping such as `count(/ids)`, and `@index`
+/// fields resolve to the iteration index from `0`. The `@index` fields of the array convertible
+/// objects of the body keep resolving to the index of their own objects. The count is capped by
+/// the [`TransformOptions`] `max_array_elements` option. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"warehouse": "berlin"});
+/// let output = json!([{"slots": {"@range": 3, "slot": "@index", "warehouse": "/warehouse"}}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"slots": [
+///         {"slot": 0, "warehouse": "berlin"},
+///         {"slot": 1, "warehouse": "berlin"},
+///         {"slot": 2, "warehouse": "berlin"}
+///     ]}])
+/// );
+/// ```
+/// # Output depth
+/// A field mapped to `@depth` resolves to how deeply nested it is in its transformed output object,
+/// the number of keys and array indexes from the object to the field, e.g. for generating test
+/// fixtures or debugging templates. In `{"order": {"items": {"@each": "/items", "depth": "@depth"}}}`
+/// the `depth` fields resolve to `4`, for `/order/items/0/depth`.
+/// # Grouping arrays
+/// An output object with a `@groupBy` key resolves to an object of arrays, the elements of the
+/// input array `@groupBy` maps to are grouped by their `key` field and the `value` field of every
+/// element is collected into the array of its group. Without a `value` field the whole elements are
+/// collected. The `key` and `value` paths are relative to the element, keys should be strings,
+/// numbers or bools, and an element missing either field is an error. Keys keep their type, so the
+/// keys `10` and `"10"` naming the same group are an error. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"orders": [
+///     {"region": "us", "amount": 10},
+///     {"region": "eu", "amount": 5},
+///     {"region": "us", "amount": 20}
+/// ]});
+/// let output = json!([{
+///     "amounts": {"@groupBy": "/orders", "key": "region", "value": "amount"}
+/// }]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"amounts": {"eu": [5], "us": [10, 20]}}])
+/// );
+/// ```
+/// # Merging arrays
+/// An output object with a `@mergeBy` key resolves to an array of merged objects, the objects of the
+/// input array `@mergeBy` maps to that have the same `key` field are merged into one object. The
+/// numeric fields listed in `sum` are added up and the other fields keep the value of the first
+/// object of the key. Nested arrays are flattened, so the objects of several input arrays can be
+/// merged, and the merged objects are in the order of the first object of their key. Keys keep
+/// their type, so the keys `10` and `"10"` are merged apart. An element that isn't an object, is
+/// missing the key or has a sum field that isn't a number is an error. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"shipments": [
+///     {"items": [{"sku": "SKU-123", "quantity": 4}, {"sku": "SKU-343", "quantity": 3}]},
+///     {"items": [{"sku": "SKU-123", "quantity": 1}]}
+/// ]});
+/// let output = json!([{
+///     "items": {"@mergeBy": "/shipments/items", "key": "sku", "sum": ["quantity"]}
+/// }]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"items": [{"sku": "SKU-123", "quantity": 5}, {"sku": "SKU-343", "quantity": 3}]}])
+/// );
+/// ```
+/// # Indexing arrays
+/// An output object with an `@indexed` key resolves to an object with one key per element of the
+/// input array `@indexed` maps to, the keys are the `prefix` field followed by the element index.
+/// The `field` mapping, relative to the element, picks the value of each element, the whole element
+/// is used without it. An empty or missing optional array resolves to an empty object. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"shipments": [{"tracking_number": "1234567"}, {"tracking_number": "98776"}]});
+/// let output = json!([{
+///     "trackings": {"@indexed": "/shipments", "prefix": "shipment_", "field": "tracking_number"}
+/// }]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"trackings": {"shipment_0": "1234567", "shipment_1": "98776"}}])
+/// );
+/// ```
+/// # Zipping arrays
+/// An output object with an `@zip` key maps field names to input arrays, it resolves to an array of
+/// objects with these fields, the i-th object takes the i-th element of every array. The arrays are
+/// combined with the [`TransformOptions`] `spread_policy` option like spread arrays, except that
+/// arrays of different lengths fail the transform with [`SpreadPolicy::Zip`], the default. A missing
+/// optional array has no elements. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"skus": ["SKU-123", "SKU-343"], "quantities": [4, 3]});
+/// let output = json!([{"order": {"items": {"@zip": {"sku": "/skus", "qty": "/quantities"}}}}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"items": [{"sku": "SKU-123", "qty": 4}, {"sku": "SKU-343", "qty": 3}]}}])
+/// );
+/// ```
+/// # Fixed arrays
+/// An array in the output object resolves element-wise, each element is a mapping of its own, a hard
+/// coded value, an object or another array, e.g. for a coordinate pair. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"geo": {"lat": 52.52, "lng": 13.405}});
+/// let output = json!([{"coordinates": ["/geo/lat", "/geo/lng"], "crs": ["'WGS84'", {"lat": "/geo/lat"}]}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"coordinates": [52.52, 13.405], "crs": ["WGS84", {"lat": 52.52}]}])
+/// );
+/// ```
 /// # Hard coded Values
 /// Any field in the output object can be have hard coded value instead of mapping value. To hard code
 /// a field value, simply use '', Example:
@@ -311,258 +588,3281 @@ use serde_json::{to_string_pretty, to_value, Value};
 ///    }
 ///  ]
 /// ```
+/// A single quote inside a hard coded value is escaped with a backslash or by doubling it, e.g.
+/// `'O\'Brien'` and `'O''Brien'` give `O'Brien`, and a backslash is escaped with another one, e.g.
+/// `'C:\\temp\\'` gives `C:\temp\`. Any other backslash is kept as is, so that `'PO-(\d+)'` gives
+/// `PO-(\d+)`.
+/// # Timestamps
+/// A field with the value `@now` resolves to the time of the transform as RFC3339 UTC timestamp,
+/// e.g. `2024-01-02T03:04:05Z`, and `@now:unix` to the seconds since the Unix epoch. The directives
+/// are only enabled with the [`TransformOptions`] `enable_time` option, so transforms are
+/// deterministic by default, and the `now` option fixes the time, e.g. for reproducible tests.
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use serde_json::json;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use transformer_rs::{transform_with_options, TransformOptions};
+///
+/// let options = TransformOptions {
+///     enable_time: true,
+///     now: Some(UNIX_EPOCH + Duration::from_secs(1_704_164_645)),
+///     ..Default::default()
+/// };
+/// let output = json!([{"audit": {"created_at": "@now", "created_at_unix": "@now:unix"}}]);
+///
+/// let transformed_output = transform_with_options(&json!({}), &output, &options).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"audit": {"created_at": "2024-01-02T03:04:05Z", "created_at_unix": 1_704_164_645}}])
+/// );
+/// # }
+/// ```
+/// # Fallbacks
+/// Several mappings can be separated by `||`, they are tried in order and the first one that
+/// resolves wins, a hard coded value always resolves. Example:
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "carrier": "/order/carrier/name || /order/shipments/carrier || 'unknown'"
+///      }
+///    }
+///  ]
+/// ```
+/// # Functions
+/// A field can call a function with `name(arg, ...)`, the arguments are mappings, hard coded
+/// values or other function calls, and they can be used as fallbacks, e.g.
+/// `regex(/notes, 'PO-(\\d+)') || 'unknown'`. The supported functions are:
+/// - `regex(text, 'pattern')` returns the first capture group of the pattern in the text, or the
+///   whole match if the pattern has no group. Requires the `regex` feature.
+/// - `size(value)`, or its alias `count(value)`, returns the number of elements of an array, the
+///   number of keys of an object or the number of characters of a string, its Unicode scalar values
+///   rather than its bytes. `null` has a size of `0`, numbers and booleans have no size and fail to
+///   resolve.
+/// - `upper(text)` and `lower(text)` convert a string to upper or lower case with the Unicode case
+///   mappings, regardless of the locale, e.g. `Straße` -> `STRASSE`, and `trim(text)` removes its
+///   leading and trailing Unicode whitespace. `null` stays `null`.
+/// - `asArray(value)` wraps a value in a one-element array, for targets that always expect arrays.
+///   Arrays are left untouched and `null` gives an empty array.
+/// - `flatten(array)` flattens the nested arrays of an array into a single level array at any depth,
+///   e.g. `[[["a", "b"], ["c"]], "d"]` gives `["a", "b", "c", "d"]`, while a path through nested
+///   arrays only extends them one level at a time. Other values are wrapped like with `asArray`.
+/// - `@keys(object)` returns the keys of an object as an array of strings, and `@values(object)`
+///   its values, both fail to resolve if the value isn't an object.
+/// - `pick(object, 'field', ...)` returns a new object with only the named fields of the object,
+///   the missing ones are left out, or fail to resolve with the `strict_pick` option.
+/// - `omit(object, 'field', ...)` returns a copy of the object without the named fields, e.g. with
+///   the `**` passthrough to redact fields of a copied subtree.
+/// - `toSet(array)` returns an object with the strings or numbers of the array as keys mapped to
+///   `true`, for fast membership checks, e.g. `{"34554543": true, "7643534": true}`.
+/// - `not(value)` returns the boolean negation of a value, e.g. `not(/product/in_stock)`. Other
+///   values are coerced with the same truthiness rules as the [`@when`](#conditional-objects)
+///   conditions, `null`, `0`, empty strings, arrays and objects being false, or fail to resolve
+///   with the `strict_not` option, which only accepts booleans and `null`.
+/// - `add(a, b)`, `sub(a, b)`, `mul(a, b)` and `div(a, b)` add, subtract, multiply and divide two
+///   numbers, e.g. `mul(/item/price, /item/quantity)`. Integers stay integers when the result is
+///   exact: `div` of `10` and `2` gives `5` while `div` of `10` and `4` gives `2.5`, and a result
+///   overflowing 64 bits becomes a float. A float operand gives a float, even if the result is
+///   integral. Operands that are not numbers and divisions by zero fail to resolve.
+/// - `ratio(a, b)` divides two numbers as floats, e.g. `ratio(/sold, /total)` gives `0.75` for `3`
+///   and `4`, and `format(mul(ratio(/sold, /total), /hundred), '%.1f%%')` gives `75.0%` with a
+///   `hundred` field of `100`. A zero denominator fails to resolve, or gives `0.0` with the
+///   `zero_ratio_on_zero_denominator` option, and operands that are not numbers fail to resolve.
+/// - `format(value, 'pattern')` formats a value with a printf style pattern, e.g.
+///   `format(/order/id, '%08d')` zero-pads an id to 8 digits. The pattern has a single conversion
+///   specifier: `%d` for integers, `%f` for numbers, `%x` and `%X` for non-negative integers in
+///   hexadecimal and `%s` for strings, with the `-` (align left), `0` (pad with zeros) and `+`
+///   (sign positive numbers) flags, a width and a precision, the number of decimals of `%f` or the
+///   maximum length of `%s`, both at most 1024. The text around the specifier is kept and `%%` is a
+///   `%`. A value of the wrong type fails to resolve, and `null` formats to `null`.
+/// - `translate(value, {'key': 'translation', ...}, default)` looks up a string or a number in a
+///   translation table, e.g. `translate(/order/status, {'S': 'shipped', 'P': 'pending'})` maps
+///   status codes to labels. The table is an object literal whose keys and values are quoted like the
+///   hard coded values, or a JSON object, and its values that are not quoted are parsed as JSON, e.g.
+///   `{'1': true, '0': false}`. The optional default is used for the values with no entry, `null`
+///   included, without it they fail to resolve. The table is the only argument of a function that
+///   can be an object literal.
+/// - `exists(path)` returns whether the mapping path resolves, `true` or `false`, instead of failing
+///   when it is missing, e.g. `exists(/order/discount_code)`. A field that is present but null
+///   exists, and a missing optional field doesn't, e.g. `exists(/order/gift?)` is `false` without a
+///   gift.
+/// - `coalesceArray(path, ...)` collects the values of the mapping paths that resolve into an
+///   array, in order, the missing and null ones being skipped, e.g.
+///   `coalesceArray(/phone_home, /phone_work, /phone_mobile)` gives the phone numbers the input has.
+/// - `sha256(value)` and `md5(value)` return the hex digest of the canonical JSON of a value, its
+///   compact serialization with the object keys sorted, e.g. `sha256(/product)` as a fingerprint of
+///   a subtree for change detection, dedup keys or idempotency tokens. Requires the `hash` feature.
+///
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "po_number": "regex(/order/notes, 'PO-(\\d+)')"
+///      }
+///    }
+///  ]
+/// ```
+/// # Optional fields
+/// A field name in a mapping path can be marked as optional with a trailing `?`. When an optional
+/// field is missing or null, the whole mapping resolves to `null` instead of failing, and array
+/// elements missing it are skipped. Example:
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "discount_code": "/order/discount?/code"
+///      }
+///    }
+///  ]
+/// ```
+/// # Null and missing fields
+/// A mapping to a field that is present in the input but null resolves to `null`, and so does a
+/// mapping to a field under it, e.g. `/order/discount/code` with a null discount. A field that is
+/// absent, missing from its object or looked up under a string, a number or a boolean, fails the
+/// transform instead, unless it is marked as optional or the `default_on_missing` option is set.
+/// # Default objects
+/// An output object with a `@defaultObject` key resolves to the object the key holds when none of
+/// the mappings of its other fields can be found in the input, e.g. when the input subtree they map
+/// to is missing; the hard coded values and the directives such as `@index` are not mappings. When
+/// only some of them are found, the missing ones fail as usual. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"product": {"name": "Red Shoes"}});
+/// let output = json!([{"details": {
+///     "@defaultObject": {"color": "unknown"},
+///     "color": "/product/details/color",
+///     "size": "/product/details/size"
+/// }}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"details": {"color": "unknown"}}]));
+/// ```
+/// # Filtering arrays
+/// A field name in a mapping path can be followed by a predicate in square brackets, the elements
+/// of the field array that don't satisfy it are filtered out before the rest of the path is
+/// resolved. The predicate has the same format as the `@when` conditions, see
+/// [Conditional objects](#conditional-objects), and its paths are resolved against the element. It
+/// is either a comparison or a condition prefixed with `?`, e.g. `/items[?gift_wrap]/sku`, other
+/// square brackets are part of the field name, e.g. `/tags[0]`. A field that is not an array
+/// should satisfy the predicate. Markers follow the predicate, e.g.
+/// `/items[quantity > #1]?/sku`. An equality predicate selects elements by a field value, when a
+/// single element matches it the rest of the path resolves to a scalar instead of a one-element
+/// array, e.g. `/items[sku == 'SKU-1453']/quantity` gives `1`. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"items": [
+///     {"sku": "SKU-123", "quantity": 4},
+///     {"sku": "SKU-1453", "quantity": 1}
+/// ]});
+/// let output = json!([{"order": {"skus": "/items[quantity > #1]/sku"}}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"skus": ["SKU-123"]}}]));
+///
+/// let output = json!([{"order": {"quantity": "/items[sku == 'SKU-1453']/quantity"}}]);
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"quantity": 1}}]));
+/// ```
+/// The same filter reads more clearly for longer conditions as an output object with an `@filter`
+/// key, it resolves to the elements of the input array `@filter` maps to that satisfy its `where`
+/// condition, projected on its `select` mapping. Both are resolved against the element like the
+/// predicates, and both are optional, the whole element is kept without `select`. A missing optional
+/// array resolves to an empty array. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"items": [
+///     {"sku": "SKU-123", "quantity": 4},
+///     {"sku": "SKU-1453", "quantity": 1}
+/// ]});
+/// let output = json!([{"order": {
+///     "skus": {"@filter": "/items", "where": "/quantity > #1", "select": "sku"}
+/// }}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"skus": ["SKU-123"]}}]));
+/// ```
+/// # Embedded JSON
+/// A field name in a mapping path marked with a trailing `~` holds a JSON encoded string, it is
+/// parsed and the rest of the path is resolved against the parsed value, e.g. `/payload~/id`
+/// resolves `{"payload": "{\"id\": 5}"}` to `5`. It can be combined with `?`, e.g. `/payload~?/id`.
+/// # Passthrough
+/// A mapping path ending with `**` copies the whole input subtree into the output as is, which is
+/// handy for opaque blobs whose fields don't need to be enumerated. Example:
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "raw_product": "/product/**"
+///      }
+///    }
+///  ]
+/// ```
+/// # Wildcards
+/// A `*` token in a mapping path resolves the rest of the path against every element of an array, or
+/// every value of an object, and collects the results into an array, e.g.
+/// `/order/shipments/*/tracking_number`. Unlike the implicit flattening of the paths through arrays,
+/// a wildcard keeps one array level per element, `/order/shipments/*/items/sku` gives one array of
+/// SKUs per shipment, while a wildcard further down the path iterates its own level into the same
+/// array, `/order/shipments/*/items/*/sku` gives all the SKUs. A field named `*` is quoted, `["*"]`.
+/// With the `strict_array_access` option the fields of arrays are only reached through wildcards,
+/// `/order/shipments/tracking_number` fails, so templates tell where the arrays are.
+/// # JSONPath queries
+/// A mapping starting with `jsonpath:` is resolved as a
+/// [JSONPath](https://www.rfc-editor.org/rfc/rfc9535) query against the whole input, for the users
+/// fluent in JSONPath or the queries the native paths can't express, e.g.
+/// `jsonpath:$.order.shipments[*].tracking_number`. It requires the `jsonpath` feature, the native
+/// paths stay the default. A singular query, only made of names and indexes like `$.order.id` or
+/// `$.items[-1]`, resolves to the value it selects and fails to resolve when it selects none, any
+/// other query resolves to the array of the values it selects, possibly empty. The queries are
+/// resolved against the input root even in `@each` bodies. A filter using `||` must be wrapped in
+/// parentheses, e.g. `[?(@.a || @.b)]`, otherwise the `||` is taken for a [fallback](#fallbacks)
+/// and the query fails to parse.
+///
+/// The native paths support the optional `?` markers, the relative paths and `..` in `@each`
+/// bodies, `@sibling:`, the `**` passthrough and the embedded JSON. The JSONPath queries support
+/// the array indexes, negative ones included, the slices `[0:2]`, the unions `[0,1]`, the
+/// descendant segments `..` and the filters `[?@.quantity > 1]` with the `length`, `count`,
+/// `match`, `search` and `value` functions. Both support the wildcards, the fallbacks, the
+/// `default_on_missing` option and being arguments of the [functions](#functions). Example:
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "trackings": "jsonpath:$.order.shipments[*].tracking_number",
+///        "bulk_skus": "jsonpath:$..items[?@.quantity > 1].sku"
+///      }
+///    }
+///  ]
+/// ```
+/// # Quoted keys
+/// Input keys that contain `/` or `.` can be addressed by quoting them with `["` and `"]`, the
+/// quoted key is taken literally. Example:
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "value": "/order/[\"a/b\"]/value"
+///      }
+///    }
+///  ]
+/// ```
+/// # Interpolated keys
+/// An output key can interpolate mappings with `${...}`, they are resolved before the value of
+/// the key and should resolve to strings, numbers or bools. A key interpolated into the name of
+/// another key of the same object fails the transform. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"order": {"id": 1, "total": 150}});
+/// let output = json!([{"orders": {"order_${/order/id}": {"total": "/order/total"}}}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"orders": {"order_1": {"total": 150}}}]));
+/// ```
+/// # Comments
+/// Fields whose name starts with `//` are treated as comments; they are skipped and never appear
+/// in the transformed output. Example:
+/// ```json
+///  [
+///    {
+///      "order": {
+///        "//note": "this maps the tracking numbers",
+///        "trackings": "/order/shipments/tracking_number"
+///      }
+///    }
+///  ]
+/// ```
+/// # Key order
+/// The keys of the transformed objects are always sorted, whatever their order in the template.
+/// Ordering them with an `@order` key is not supported and fails the transformation.
+///
+/// The keys of the input objects are sorted too when the input is serialized to a JSON value, so
+/// the values collected by a wildcard over an object, e.g. from a `HashMap`, come in the order of
+/// their keys whatever the order the input was built in. This doesn't hold when the
+/// `preserve_order` feature of serde_json is enabled, e.g. by another crate of the build, the
+/// input objects then keep their insertion order.
+/// # Template metadata
+/// An output object can hold a `@meta` field with metadata of the template, e.g. its version and
+/// description. It is never transformed and never appears in the transformed output, and
+/// [`template_meta`] returns it by output name. Example:
+/// ```json
+///  [
+///    {
+///      "@meta": {"version": "3", "description": "orders export"},
+///      "order": {
+///        "id": "/order_id"
+///      }
+///    }
+///  ]
+/// ```
+/// # Conditional objects
+/// An output object with a `@when` condition is only included in the output if the condition
+/// holds, otherwise it is removed along with its key. The condition is either a single operand
+/// checked for truthiness (`null`, `false`, `0`, `""`, `[]` and `{}` are falsy) or two operands
+/// compared with `==`, `!=`, `>`, `>=`, `<` or `<=`, separated by spaces. Operands are mapping
+/// paths, `'string'` literals or `#` prefixed JSON literals such as `#100`, `#true` or `#null`;
+/// paths that can't be resolved are `null`. Ordering operators only compare numbers with numbers
+/// and strings with strings. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let input = json!({"order": {"id": 1, "total": 150, "status": "shipped"}});
+/// let output = json!([{
+///     "order": {
+///         "id": "/order/id",
+///         "large_order": {"@when": "/order/total > #100", "total": "/order/total"},
+///         "pending": {"@when": "/order/status == 'pending'", "status": "/order/status"}
+///     }
+/// }]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"id": 1, "large_order": {"total": 150}}}])
+/// );
+/// ```
+/// # Switching on types
+/// An output object with a `@typeswitch` key resolves to one of its other fields depending on the
+/// JSON type of the value the key maps to, e.g. for inputs that store a field in several shapes.
+/// The fields are named after the types, `null`, `boolean`, `number`, `string`, `array` and
+/// `object`, and the selected one is transformed in place of the object. A type without field fails
+/// the transform. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// let output = json!([{"product": {"price": {
+///     "@typeswitch": "/price",
+///     "number": "/price",
+///     "object": "/price/amount"
+/// }}}]);
+///
+/// let input = json!({"price": 12.5});
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"product": {"price": 12.5}}]));
+///
+/// let input = json!({"price": {"amount": 10, "currency": "EUR"}});
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"product": {"price": 10}}]));
+/// ```
+/// # Typed inputs
+/// The input and the output are serialized into JSON values with serde before being transformed,
+/// so serde attributes like `#[serde(rename)]`, `#[serde(rename_all)]` or `#[serde(skip)]` apply,
+/// and the mapping paths must use the serialized field names. Example:
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use serde_json::json;
+/// use transformer_rs::transform;
+///
+/// #[derive(Serialize, Deserialize)]
+/// #[serde(rename_all = "camelCase")]
+/// struct Order {
+///     po_number: String,
+/// }
+///
+/// let input = Order { po_number: "573832".to_string() };
+/// let output = json!([{"order": {"po_number": "/poNumber"}}]);
+///
+/// let transformed_output = transform(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"po_number": "573832"}}]));
+/// ```
+/// Use [`transform_value`] when the input and the output are already JSON values, the generic
+/// functions serialize their arguments then run the same transform.
+#[allow(clippy::needless_doctest_main)]
 pub fn transform<I, O>(input: &I, output: &O) -> Result<Value>
 where
     I: Serialize + DeserializeOwned,
     O: Serialize + DeserializeOwned,
 {
-    let mut output: Value = to_value(output).unwrap();
-    let input: Value = to_value(input).unwrap();
+    transform_with_options(input, output, &TransformOptions::default())
+}
 
-    let mut result: Vec<Value> = Vec::new();
+/// Same as [`transform`], but the transformation behaviour can be tweaked with [`TransformOptions`].
+/// # Example
+/// ```
+/// use serde_json::{json, Value};
+/// use transformer_rs::{transform_with_options, TransformOptions};
+///
+/// let input = json!({"retailer": {"id": "12342"}});
+/// let output = json!([{"order": {"account_id": "/retailer/id", "discount": "/discount"}}]);
+/// let options = TransformOptions {
+///     default_on_missing: Some(Value::Null),
+///     ..Default::default()
+/// };
+///
+/// let transformed_output = transform_with_options(&input, &output, &options).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"account_id": "12342", "discount": null}}])
+/// );
+/// ```
+pub fn transform_with_options<I, O>(
+    input: &I,
+    output: &O,
+    options: &TransformOptions,
+) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    transform_values(&input, output, options)
+}
 
-    for mut obj in output
-        .as_array_mut()
+/// Same as [`transform`], but takes already parsed JSON values, which avoids serializing the input
+/// and the output again.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_value;
+///
+/// let input = json!({"retailer": {"id": "12342"}});
+/// let output = json!([{"order": {"account_id": "/retailer/id"}}]);
+///
+/// let transformed_output = transform_value(&input, &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"account_id": "12342"}}]));
+/// ```
+pub fn transform_value(input: &Value, output: &Value) -> Result<Value> {
+    transform_value_with_options(input, output, &TransformOptions::default())
+}
+
+/// Same as [`transform_with_options`], but takes already parsed JSON values like
+/// [`transform_value`].
+/// # Example
+/// ```
+/// use serde_json::{json, Value};
+/// use transformer_rs::{transform_value_with_options, TransformOptions};
+///
+/// let input = json!({"retailer": {"id": "12342"}});
+/// let output = json!([{"order": {"account_id": "/retailer/id", "discount": "/discount"}}]);
+/// let options = TransformOptions {
+///     default_on_missing: Some(Value::Null),
+///     ..Default::default()
+/// };
+///
+/// let transformed_output = transform_value_with_options(&input, &output, &options).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"account_id": "12342", "discount": null}}])
+/// );
+/// ```
+pub fn transform_value_with_options(
+    input: &Value,
+    output: &Value,
+    options: &TransformOptions,
+) -> Result<Value> {
+    transform_values(input, output.clone(), options)
+}
+
+/// Same as [`transform`], but the transformed output objects are returned by name, e.g. to route
+/// each of them differently. They are in the order of the output array elements, an element
+/// holding several names gives one entry per name, sorted by name, and the array of a root array
+/// element, see [Concerting objects to array](#concerting-objects-to-array), is named `[]`.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_named;
+///
+/// let input = json!({"order_id": 1, "customer": {"name": "O'Brien"}, "ids": [1, 2]});
+/// let output = json!([
+///     {"order": {"id": "/order_id"}, "customer": {"name": "/customer/name"}},
+///     {"[]": {"...id": "/ids"}},
+/// ]);
+///
+/// let transformed_outputs = transform_named(&input, &output).unwrap();
+/// assert_eq!(
+///     transformed_outputs,
+///     vec![
+///         ("customer".to_string(), json!({"name": "O'Brien"})),
+///         ("order".to_string(), json!({"id": 1})),
+///         ("[]".to_string(), json!([{"id": 1}, {"id": 2}])),
+///     ]
+/// );
+/// ```
+pub fn transform_named<I, O>(input: &I, output: &O) -> Result<Vec<(String, Value)>>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    let transformed_outputs = transform_output_objs(
+        &Contexts::new(&[&input]),
+        output,
+        &TransformOptions::default(),
+        &mut Vec::new(),
+    )?;
+    let mut named = Vec::new();
+    for transformed in transformed_outputs {
+        match transformed {
+            Value::Object(obj) => named.extend(obj),
+            // the root array, or null with the `empty_as_null` option
+            value => named.push((String::from(ROOT_ARRAY_NAME), value)),
+        }
+    }
+    Ok(named)
+}
+
+/// Returns the metadata of the output objects of a template by output name, in the same order as
+/// [`transform_named`]. An output object can hold a `@meta` field, e.g. a version or a description
+/// of the template, that travels with the template but is never transformed nor found in the
+/// transformed output. The names are the ones [`transform_named`] gives, an output object holding
+/// several names gives one entry per name, and the objects without metadata are left out.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::{template_meta, transform};
+///
+/// let output = json!([{
+///     "@meta": {"version": "3", "description": "orders export"},
+///     "order": {"id": "/order_id"}
+/// }]);
+///
+/// let meta = template_meta(&output).unwrap();
+/// assert_eq!(
+///     meta,
+///     vec![("order".to_string(), json!({"version": "3", "description": "orders export"}))]
+/// );
+/// let transformed_output = transform(&json!({"order_id": 1}), &output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"id": 1}}]));
+/// ```
+pub fn template_meta<O: Serialize>(output: &O) -> Result<Vec<(String, Value)>> {
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    let mut named = Vec::new();
+    for obj in output
+        .as_array()
         .ok_or_else(|| anyhow!("output should be in an array of object structure"))?
-        .iter_mut()
     {
-        let string_pretty = to_string_pretty(&obj)?;
-        let _obj_name = obj
-            .as_object()
-            .ok_or_else(|| {
-                anyhow!(
-                    "output array elements should be in object structure: {}",
-                    string_pretty
-                )
-            })?
-            .keys()
-            .next()
-            .ok_or_else(|| anyhow!("failed to get the name of the output: {}", string_pretty))?
-            .clone();
-        traverse_mut(&input, &mut obj, "", "")?;
-        process_array_convertible_objs(
-            &obj.clone(),
-            &mut obj,
-            "",
-            "",
-            &mut Default::default(),
-            &mut Default::default(),
-        )?;
-
-        result.push(obj.clone());
+        let fields = obj.as_object().ok_or_else(|| {
+            anyhow!(
+                "output array elements should be in object structure: {}",
+                obj
+            )
+        })?;
+        if let Some(meta) = fields.get(META_KEY) {
+            for name in fields.keys().filter(|k| is_output_name(k)) {
+                let name = match name.as_str() {
+                    ROOT_ARRAY_NAME => name.as_str(),
+                    name => clean_key(name)?,
+                };
+                named.push((String::from(name), meta.clone()));
+            }
+        }
     }
+    Ok(named)
+}
 
-    Ok(to_value(result)?)
+/// Same as [`transform`], but the mapping paths are resolved against several inputs, e.g. a base
+/// record and an override record. The inputs are tried in order and the first one that resolves a
+/// path wins, like a [fallback](#fallbacks) across whole documents rather than per field. An
+/// optional path that resolves to null moves on to the next input, it is only null if no input
+/// has the field. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_layered;
+///
+/// let overrides = json!({"order": {"status": "cancelled"}});
+/// let base = json!({"order": {"id": 1, "status": "shipped"}});
+/// let output = json!([{"order": {"id": "/order/id", "status": "/order/status"}}]);
+///
+/// let transformed_output = transform_layered(&[&overrides, &base], &output).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"id": 1, "status": "cancelled"}}])
+/// );
+/// ```
+pub fn transform_layered<I, O>(inputs: &[&I], output: &O) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    if inputs.is_empty() {
+        bail!("inputs should have at least one input to transform");
+    }
+    let inputs = inputs
+        .iter()
+        .map(|input| to_value(input).context("Failed to serialize input to JSON value"))
+        .collect::<Result<Vec<Value>>>()?;
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    let inputs: Vec<&Value> = inputs.iter().collect();
+    transform_in(
+        &Contexts::new(&inputs),
+        output,
+        &TransformOptions::default(),
+    )
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use once_cell::sync::Lazy;
-    use serde_json::{from_str, json, Value};
-    use std::fs;
-    use std::sync::Mutex;
+/// Same as [`transform`], but the output object is written as a flat map of JSON pointers to
+/// mappings, the nested output template is built from the pointers. Every pointer token is an
+/// object key, and a pointer can't be under another mapped pointer. Example:
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_pointers;
+///
+/// let input = json!({"retailer": {"id": "12342"}, "order_id": 1});
+/// let pointers = json!({"/order/sub_order/account_id": "/retailer/id", "/order/id": "/order_id"});
+///
+/// let transformed_output = transform_pointers(&input, pointers.as_object().unwrap()).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"id": 1, "sub_order": {"account_id": "12342"}}}])
+/// );
+/// ```
+pub fn transform_pointers<I>(input: &I, pointers: &Map<String, Value>) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    let output = Value::Array(vec![pointers_to_output(pointers)?]);
+    transform_values(&input, output, &TransformOptions::default())
+}
 
-    const OUTPUT_JSON_FILES_DIR: &str = "./test/output";
-    static INPUT_JSON_FILE: Lazy<Mutex<Value>> = Lazy::new(|| {
-        let input = fs::read_to_string("./test/input.json").expect("Unable to read input file");
+/// Same as [`transform`], but the `observer` is notified of the resolution of every output field,
+/// e.g. to log or count the missing mappings in production without changing the transform result.
+/// Example:
+/// ```
+/// use serde_json::{json, Value};
+/// use std::cell::RefCell;
+/// use transformer_rs::{transform_with_observer, TransformObserver};
+///
+/// #[derive(Default)]
+/// struct MissingFields(RefCell<Vec<String>>);
+///
+/// impl TransformObserver for MissingFields {
+///     fn on_missing(&self, output_path: &str, _input_path: &str) {
+///         self.0.borrow_mut().push(output_path.to_string());
+///     }
+/// }
+///
+/// let input = json!({"order": {"id": 1}});
+/// let output = json!([{"order": {"id": "/order/id", "note": "/order/note || 'none'"}}]);
+/// let observer = MissingFields::default();
+///
+/// transform_with_observer(&input, &output, &observer).unwrap();
+/// assert!(observer.0.borrow().is_empty());
+///
+/// let output = json!([{"order": {"id": "/order/id", "note": "/order/note"}}]);
+/// assert!(transform_with_observer(&input, &output, &observer).is_err());
+/// assert_eq!(*observer.0.borrow(), vec!["/order/note"]);
+/// ```
+pub fn transform_with_observer<I, O>(
+    input: &I,
+    output: &O,
+    observer: &dyn TransformObserver,
+) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    transform_in(
+        &Contexts::new(&[&input]).with_observer(observer),
+        output,
+        &TransformOptions::default(),
+    )
+}
+
+/// Same as [`transform_with_options`], but also returns the errors of the output objects skipped
+/// with the `continue_on_element_error` option, in the template order. Each [`ElementError`] carries
+/// the index of its output object in the template. Without the option, the first failing output
+/// object fails the transform and the report is always empty.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::{transform_with_report, TransformOptions};
+///
+/// let input = json!({"order": {"id": 1}});
+/// let output = json!([
+///     {"order": {"id": "/order/id"}},
+///     {"refund": {"id": "/refund/id"}},
+/// ]);
+/// let options = TransformOptions {
+///     continue_on_element_error: true,
+///     ..Default::default()
+/// };
+///
+/// let (transformed_output, errors) = transform_with_report(&input, &output, &options).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"id": 1}}]));
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].index(), 1);
+/// ```
+pub fn transform_with_report<I, O>(
+    input: &I,
+    output: &O,
+    options: &TransformOptions,
+) -> Result<(Value, Vec<ElementError>)>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    let mut errors = Vec::new();
+    let transformed_output =
+        transform_in_with_report(&Contexts::new(&[&input]), output, options, &mut errors)?;
+    Ok((transformed_output, errors))
+}
+
+/// Same as [`transform`], but the mappings of the output fields are resolved by the `resolver`
+/// instead of the built-in resolution, e.g. to look up some of the fields in a database. A custom
+/// resolver can fall back on [`DefaultLeafResolver`] for the other fields. Example:
+/// ```
+/// use anyhow::Result;
+/// use serde_json::{json, Value};
+/// use transformer_rs::{transform_with_resolver, DefaultLeafResolver, LeafContext, LeafResolver};
+///
+/// struct Prices;
+///
+/// impl LeafResolver for Prices {
+///     fn resolve(
+///         &self,
+///         context: &LeafContext,
+///         mapping: &str,
+///         output_path: &str,
+///     ) -> Result<Value> {
+///         match mapping.strip_prefix("price:") {
+///             Some("SKU-123") => Ok(json!(12.5)),
+///             Some(sku) => anyhow::bail!("no price for {}", sku),
+///             None => DefaultLeafResolver.resolve(context, mapping, output_path),
+///         }
+///     }
+/// }
+///
+/// let input = json!({"product": {"sku": "SKU-123"}});
+/// let output = json!([{"product": {"sku": "/product/sku", "price": "price:SKU-123"}}]);
+///
+/// let transformed_output = transform_with_resolver(&input, &output, &Prices).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"product": {"sku": "SKU-123", "price": 12.5}}])
+/// );
+/// ```
+pub fn transform_with_resolver<I, O>(
+    input: &I,
+    output: &O,
+    resolver: &dyn LeafResolver,
+) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    let output = to_value(output).context("Failed to serialize output template to JSON value")?;
+    transform_in(
+        &Contexts::new(&[&input]).with_resolver(resolver),
+        output,
+        &TransformOptions::default(),
+    )
+}
+
+// transforms the input JSON value, the output template is modified in place while it is resolved
+fn transform_values(input: &Value, output: Value, options: &TransformOptions) -> Result<Value> {
+    transform_in(&Contexts::new(&[input]), output, options)
+}
+
+// same as `transform_values`, the mappings are resolved against the contexts, the input roots and
+// the observer
+fn transform_in(contexts: &Contexts, output: Value, options: &TransformOptions) -> Result<Value> {
+    transform_in_with_report(contexts, output, options, &mut Vec::new())
+}
+
+// same as `transform_in`, the errors of the output objects skipped with the
+// `continue_on_element_error` option are collected in `errors`
+fn transform_in_with_report(
+    contexts: &Contexts,
+    output: Value,
+    options: &TransformOptions,
+    errors: &mut Vec<ElementError>,
+) -> Result<Value> {
+    Ok(Value::Array(transform_output_objs(
+        contexts, output, options, errors,
+    )?))
+}
+
+// transforms the output objects of the template, the objects left out by their `@when` condition or
+// skipped with the `continue_on_element_error` option are not in the returned ones
+fn transform_output_objs(
+    contexts: &Contexts,
+    mut output: Value,
+    options: &TransformOptions,
+    errors: &mut Vec<ElementError>,
+) -> Result<Vec<Value>> {
+    let mut result: Vec<Value> = Vec::new();
+    let mut names = BTreeSet::new();
+    let mut duplicate_names = BTreeSet::new();
+
+    for (i, obj) in output
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("output should be in an array of object structure"))?
+        .iter_mut()
+        .enumerate()
+    {
+        // the output object is only printed when it is malformed
+        let obj_names: Vec<String> = match obj.as_object() {
+            Some(fields) => fields
+                .keys()
+                .filter(|k| is_output_name(k))
+                .cloned()
+                .collect(),
+            None => bail!(
+                "output array elements should be in object structure: {}",
+                to_string_pretty(&obj)?
+            ),
+        };
+        if obj_names.is_empty() {
+            bail!(
+                "failed to get the name of the output: {}",
+                to_string_pretty(&obj)?
+            );
+        }
+        let is_root_array = obj_names.iter().any(|name| name == ROOT_ARRAY_NAME);
+        if is_root_array && obj_names.len() > 1 {
+            bail!(
+                "output object marked as root array {} should have no other fields: {}",
+                ROOT_ARRAY_NAME,
+                to_string_pretty(&obj)?
+            );
+        }
+        match transform_obj(contexts, obj, i, is_root_array, options) {
+            Ok(Some(transformed)) => result.push(transformed),
+            // output objects whose `@when` condition is false are left out of the result
+            Ok(None) => continue,
+            Err(err) if options.continue_on_element_error => {
+                errors.push(ElementError::new(i, err));
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+        for obj_name in obj_names {
+            if obj_name != ROOT_ARRAY_NAME && !names.insert(obj_name.clone()) {
+                duplicate_names.insert(obj_name);
+            }
+        }
+    }
+    if !duplicate_names.is_empty() && !options.allow_duplicate_names {
+        bail!(
+            "output objects should have unique names, duplicated names: {}",
+            duplicate_names
+                .into_iter()
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+
+    Ok(result)
+}
+
+// returns true if the key of an output object of the template names an output, the comments and
+// the directives of the object aside
+fn is_output_name(key: &str) -> bool {
+    !is_comment(key) && ![WHEN_KEY, META_KEY].contains(&key)
+}
+
+// removes the metadata of an output object of the template, so that it is not transformed
+fn strip_meta(obj: &mut Value) -> Option<Value> {
+    obj.as_object_mut()
+        .and_then(|fields| fields.remove(META_KEY))
+}
+
+// transforms the output object `i` of the template, `None` is returned if it is left out by its
+// `@when` condition
+fn transform_obj(
+    contexts: &Contexts,
+    obj: &mut Value,
+    i: usize,
+    is_root_array: bool,
+    options: &TransformOptions,
+) -> Result<Option<Value>> {
+    if !is_included(contexts, obj, options)
+        .map_err(|err| FieldError::new(format!("/{}/{}", i, WHEN_KEY), err))?
+    {
+        return Ok(None);
+    }
+    strip_meta(obj);
+    let index_fields = IndexFields::new(0);
+    traverse_mut_in(
+        &contexts.clone().with_index_fields(&index_fields),
+        obj,
+        "",
+        "",
+        options,
+    )
+    .map_err(|err| prefix_field_error(err, &format!("/{}", i)))?;
+    convert_array_convertible_objs(obj, &index_fields.fields(), options)?;
+
+    // the root array marker is cleaned into an empty name whose value is the converted array
+    let mut transformed = if is_root_array {
+        obj.get_mut("").map(Value::take).unwrap_or_default()
+    } else {
+        obj.take()
+    };
+    if options.empty_as_null {
+        empty_to_null(&mut transformed);
+    }
+    Ok(Some(transformed))
+}
+
+/// Same as [`transform`], but parses the input and the output JSON strings first. Parse errors tell
+/// which of them is malformed. JSON objects with duplicated keys keep the last value of the key;
+/// with the [`TransformOptions`] `warn_on_duplicate_input_keys` option, see
+/// [`transform_str_with_duplicate_keys`], the JSON pointers of the duplicated keys of the input are
+/// returned along with the transformed output, and with the `deny_duplicate_input_keys` option an
+/// input with duplicated keys fails with a [`DuplicateKeysError`] that carries them.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_str;
+///
+/// let input = r#"{"retailer": {"id": "12342"}}"#;
+/// let output = r#"[{"order": {"account_id": "/retailer/id"}}]"#;
+///
+/// let transformed_output = transform_str(input, output).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"account_id": "12342"}}]));
+/// ```
+pub fn transform_str(input: &str, output: &str) -> Result<Value> {
+    transform_str_with_options(input, output, &TransformOptions::default())
+}
+
+/// Same as [`transform_str`], but the transformation behaviour can be tweaked with
+/// [`TransformOptions`].
+pub fn transform_str_with_options(
+    input: &str,
+    output: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    transform_str_with_duplicate_keys(input, output, options).map(|(transformed, _)| transformed)
+}
+
+/// Same as [`transform_str_with_options`], but also returns the JSON pointers of the duplicated keys
+/// of the input, in document order, when the `warn_on_duplicate_input_keys` option is set. Only the
+/// last value of a duplicated key is transformed, the pointers tell which values were dropped.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::{transform_str_with_duplicate_keys, TransformOptions};
+///
+/// let input = r#"{"order": {"id": 1, "id": 2}}"#;
+/// let output = r#"[{"order": {"id": "/order/id"}}]"#;
+/// let options = TransformOptions {
+///     warn_on_duplicate_input_keys: true,
+///     ..Default::default()
+/// };
+///
+/// let (transformed_output, duplicate_keys) =
+///     transform_str_with_duplicate_keys(input, output, &options).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"id": 2}}]));
+/// assert_eq!(duplicate_keys, vec!["/order/id"]);
+/// ```
+pub fn transform_str_with_duplicate_keys(
+    input: &str,
+    output: &str,
+    options: &TransformOptions,
+) -> Result<(Value, Vec<String>)> {
+    let (input, duplicate_keys) = input::parse_json(input, "input", options)?;
+    let output = serde_json::from_str(output).context("Failed to parse output JSON")?;
+    let transformed_output = transform_values(&input, output, options)?;
+    Ok((transformed_output, duplicate_keys))
+}
+
+/// Transforms each of the inputs with the same output template, the transformed outputs are in the
+/// inputs order. The output template is serialized once for the whole batch, see
+/// [`Template::transform_each`]. With the `rayon` feature, large batches are transformed in
+/// parallel. A failure of an input is a [`BatchError`] that carries the index of the input, an
+/// output template that fails to serialize fails the first input.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_each;
+///
+/// let inputs = vec![json!({"order_id": 1}), json!({"order_id": 2})];
+/// let output = json!([{"order": {"id": "/order_id"}}]);
+///
+/// let transformed_outputs = transform_each(&inputs, &output).unwrap();
+/// assert_eq!(
+///     transformed_outputs,
+///     vec![json!([{"order": {"id": 1}}]), json!([{"order": {"id": 2}}])]
+/// );
+///
+/// let inputs = vec![json!({"order_id": 1}), json!({}), json!({"order_id": 3})];
+/// let err = transform_each(&inputs, &output).unwrap_err();
+/// assert_eq!(err.index(), 1);
+/// ```
+pub fn transform_each<I, O>(
+    inputs: &[I],
+    output: &O,
+) -> core::result::Result<Vec<Value>, BatchError>
+where
+    I: Serialize + Sync,
+    O: Serialize,
+{
+    Template::new(output)
+        .map_err(|err| BatchError::new(0, err))?
+        .transform_each(inputs)
+}
+
+/// Transforms the input with each of the named output templates, the transformed outputs are
+/// returned under the name of their template. The input is serialized once for all the templates,
+/// which is tidier than transforming it once per template when one input fans out into several
+/// differently shaped documents. The error of a failing template names the template.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use std::collections::HashMap;
+/// use transformer_rs::transform_multi;
+///
+/// let input = json!({"order_id": 1, "customer": {"name": "O'Brien"}});
+/// let outputs = HashMap::from([
+///     ("order".to_string(), json!([{"order": {"id": "/order_id"}}])),
+///     ("invoice".to_string(), json!([{"invoice": {"name": "/customer/name"}}])),
+/// ]);
+///
+/// let transformed_outputs = transform_multi(&input, &outputs).unwrap();
+/// assert_eq!(transformed_outputs["order"], json!([{"order": {"id": 1}}]));
+/// assert_eq!(transformed_outputs["invoice"], json!([{"invoice": {"name": "O'Brien"}}]));
+/// ```
+#[cfg(feature = "std")]
+pub fn transform_multi<I>(
+    input: &I,
+    outputs: &std::collections::HashMap<String, Value>,
+) -> Result<std::collections::HashMap<String, Value>>
+where
+    I: Serialize,
+{
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+    outputs
+        .iter()
+        .map(|(name, output)| {
+            let transformed_output =
+                transform_values(&input, output.clone(), &TransformOptions::default())
+                    .with_context(|| format!("Failed to transform the template {}", name))?;
+            Ok((name.clone(), transformed_output))
+        })
+        .collect()
+}
+
+/// Same as [`transform`], but failures are returned as a [`DiagnosticError`] that knows the JSON
+/// pointer of the failing field in the output template and can render the template with the
+/// failing field marked, which is easier to locate for template authors than a plain message.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_diagnostic;
+///
+/// let input = json!({"product": {"id": "654654"}});
+/// let output = json!([{"order": {"id": "/product/idd"}}]);
+///
+/// let err = transform_diagnostic(&input, &output).unwrap_err();
+/// assert_eq!(err.pointer(), Some("/0/order/id"));
+/// println!("{}", err.render());
+/// ```
+pub fn transform_diagnostic<I, O>(
+    input: &I,
+    output: &O,
+) -> core::result::Result<Value, DiagnosticError>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let template = to_value(output)
+        .context("Failed to serialize output template to JSON value")
+        .map_err(|err| DiagnosticError::new(&err, Value::Null))?;
+    transform(input, &template).map_err(|err| DiagnosticError::new(&err, template))
+}
+
+/// Resolves every mapping of the output against the input without building the transformed output,
+/// and returns the JSON type each mapping resolves to, paired with the output path of the field.
+/// The output paths are the template paths, including the array conversion decorations. This is
+/// useful to validate a template against a sample input before using it.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::describe_transform;
+///
+/// let input = json!({"retailer": {"id": "12342"}, "ids": ["34554543", "7643534"]});
+/// let output = json!([{"order": {"account_id": "/retailer/id", "item_ids": "/ids"}}]);
+///
+/// let types = describe_transform(&input, &output).unwrap();
+/// assert_eq!(
+///     types,
+///     vec![
+///         ("/order/account_id".to_string(), "string".to_string()),
+///         ("/order/item_ids".to_string(), "array".to_string()),
+///     ]
+/// );
+/// ```
+pub fn describe_transform<I, O>(input: &I, output: &O) -> Result<Vec<(String, String)>>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let mut output =
+        to_value(output).context("Failed to serialize output template to JSON value")?;
+    let input = to_value(input).context("Failed to serialize input to JSON value")?;
+
+    let mut types = vec![];
+    for obj in output
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("output should be in an array of object structure"))?
+    {
+        if !obj.is_object() {
+            bail!(
+                "output array elements should be in object structure: {}",
+                to_string_pretty(obj)?
+            );
+        }
+        strip_meta(obj);
+        describe_types(
+            &input,
+            obj,
+            "",
+            "",
+            &TransformOptions::default(),
+            &mut types,
+        )?;
+    }
+
+    Ok(types)
+}
+
+/// Parses a line based template DSL into the equivalent JSON output template, a lighter way to write
+/// templates for quick scripts. Every `key = mapping` line maps an output field, the key is the
+/// output path of the field with its keys separated by `.`, and the mapping is kept as written, so
+/// hard coded values, fallbacks and functions work the same as in JSON templates. A spread marker
+/// `...` starts a new key, e.g. `[items]...sku` is the `...sku` field of the `[items]` array
+/// convertible object. A `---` line starts a new output object, and the empty lines and the lines
+/// starting with `#` are skipped.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::{parse_dsl, transform};
+///
+/// let output = parse_dsl(
+///     "
+///     order.account_id = /retailer/id
+///     order.source = 'edi'
+///     [items]...sku = /skus
+///     ",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     output,
+///     json!([{
+///         "order": {"account_id": "/retailer/id", "source": "'edi'"},
+///         "[items]": {"...sku": "/skus"}
+///     }])
+/// );
+///
+/// let input = json!({"retailer": {"id": "12342"}, "skus": ["SKU-123", "SKU-343"]});
+/// assert_eq!(
+///     transform(&input, &output).unwrap(),
+///     json!([{
+///         "order": {"account_id": "12342", "source": "edi"},
+///         "items": [{"sku": "SKU-123"}, {"sku": "SKU-343"}]
+///     }])
+/// );
+/// ```
+pub fn parse_dsl(text: &str) -> Result<Value> {
+    dsl::parse(text)
+}
+
+/// Compares two versions of an output template and returns the mappings that were added, removed or
+/// retargeted, ordered by output path. The output paths are cleaned from the spread markers, the
+/// array convertible objects keep their brackets since `[order]` and `order` are different outputs,
+/// and comments and the `@meta` metadata are ignored. This is useful to review the changes of a
+/// template.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::diff_templates;
+///
+/// let old = json!([{"[order]": {"id": "/product/id", "name": "/product/name", "...ids": "/ids"}}]);
+/// let new = json!([{"[order]": {"id": "/product/sku", "...ids": "/ids", "...skus": "/skus"}}]);
+///
+/// let changes: Vec<String> = diff_templates(&old, &new)
+///     .iter()
+///     .map(ToString::to_string)
+///     .collect();
+/// assert_eq!(
+///     changes,
+///     vec![
+///         "/[order]/id retargeted from /product/id to /product/sku",
+///         "/[order]/name removed, it was /product/name",
+///         "/[order]/skus added as /skus",
+///     ]
+/// );
+/// ```
+pub fn diff_templates(old: &Value, new: &Value) -> Vec<TemplateChange> {
+    let mappings = |template: &Value| {
+        let mut mappings = BTreeMap::new();
+        match template {
+            Value::Array(objs) => {
+                for obj in objs {
+                    collect_mappings(obj, "", "", &mut mappings);
+                }
+            }
+            obj => collect_mappings(obj, "", "", &mut mappings),
+        }
+        mappings
+    };
+    diff_mappings(&mappings(old), &mappings(new))
+}
+
+/// Statically analyzes an output template and explains how each of its array convertible objects
+/// (`[]`) is converted into an array: the spread fields (`...`) it is split on, or that it fails the
+/// transform because it has none, and the spread markers of its subtree that don't split it along
+/// with the reason. A spread field splits its closest enclosing array convertible object, through
+/// plain nested objects, so the spread fields of a nested array convertible object don't split its
+/// parent. The reports are in the template order, nested array convertible objects after their
+/// parent.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::explain_array_conversion;
+///
+/// let output = json!([{
+///     "[order]": {"...id": "/ids", "[items]": {"...sku": "/skus"}},
+///     "[lines]": {"details": {"//note": "no spread field"}}
+/// }]);
+///
+/// let reports: Vec<String> = explain_array_conversion(&output)
+///     .iter()
+///     .map(ToString::to_string)
+///     .collect();
+/// assert_eq!(
+///     reports,
+///     vec![
+///         "/0/[lines] fails, no spread field was found",
+///         "/0/[order] is split on /0/[order]/...id; /0/[order]/[items]/...sku is ignored, it splits the nested array convertible object /0/[order]/[items]",
+///         "/0/[order]/[items] is split on /0/[order]/[items]/...sku",
+///     ]
+/// );
+/// ```
+pub fn explain_array_conversion(output: &Value) -> Vec<ArrayConversionReport> {
+    let mut reports = vec![];
+    match output {
+        Value::Array(objs) => {
+            for (i, obj) in objs.iter().enumerate() {
+                explain_array_conversions(obj, "", &format!("{}", i), &mut reports);
+            }
+        }
+        obj => explain_array_conversions(obj, "", "", &mut reports),
+    }
+    mark_nested_spread_fields(&mut reports);
+    reports
+}
+
+/// Collects the spread fields of the objects an array convertible object was split into back into
+/// arrays, the inverse of the [conversion to array](#concerting-objects-to-array) with the default
+/// [`SpreadPolicy::Zip`]. The array is the `array_key` field of the value and the spread fields are
+/// JSON pointers relative to its objects. The other fields are taken from the first object, since
+/// they are the same in every object, and the objects missing a spread field are skipped.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::collapse_array_to_obj;
+///
+/// let value = json!({"order": [
+///     {"item_id": "34554543", "account_id": "12342"},
+///     {"item_id": "7643534", "account_id": "12342"}
+/// ]});
+///
+/// assert_eq!(
+///     collapse_array_to_obj(&value, "order", &["/item_id"]).unwrap(),
+///     json!({"order": {"item_id": ["34554543", "7643534"], "account_id": "12342"}})
+/// );
+/// ```
+pub fn collapse_array_to_obj(
+    value: &Value,
+    array_key: &str,
+    spread_fields: &[&str],
+) -> Result<Value> {
+    transformer::collapse_array_to_obj(value, array_key, spread_fields)
+}
+
+/// Transforms the input the same way as [`transform`] and deep merges the transformed output onto
+/// `base`, so that static structure can be kept outside of the output template. `base` must be in
+/// the same array of objects structure as the output; each transformed element is merged onto the
+/// base element of the same index. Transformed values override base values, arrays are replaced.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_merge;
+///
+/// let input = json!({"retailer": {"id": "12342"}});
+/// let output = json!([{"order": {"account_id": "/retailer/id"}}]);
+/// let base = json!([{"order": {"account_id": "", "source": "edi"}}]);
+///
+/// let transformed_output = transform_merge(&input, &output, &base).unwrap();
+/// assert_eq!(
+///     transformed_output,
+///     json!([{"order": {"account_id": "12342", "source": "edi"}}])
+/// );
+/// ```
+pub fn transform_merge<I, O>(input: &I, output: &O, base: &Value) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    transform_merge_with_options(input, output, base, &TransformOptions::default())
+}
+
+/// Same as [`transform_merge`], but arrays found in both the base and the transformed output are
+/// merged based on [`TransformOptions::array_merge`].
+pub fn transform_merge_with_options<I, O>(
+    input: &I,
+    output: &O,
+    base: &Value,
+    options: &TransformOptions,
+) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let transformed_output = transform_with_options(input, output, options)?;
+    let mut result = base
+        .as_array()
+        .ok_or_else(|| anyhow!("base should be in an array of object structure"))?
+        .clone();
+
+    for (i, obj) in transformed_output
+        .as_array()
+        .ok_or_else(|| anyhow!("transformed output should be in an array structure"))?
+        .iter()
+        .enumerate()
+    {
+        match result.get_mut(i) {
+            Some(base_obj) => merge_values(base_obj, obj.clone(), options.array_merge),
+            None => result.push(obj.clone()),
+        }
+    }
+
+    Ok(Value::Array(result))
+}
+
+/// Transforms the input the same way as [`transform`] and serializes the transformed output to an
+/// XML document, for consumers that don't speak JSON. The naming convention is deterministic:
+/// - the output objects are the children of a `root` document element;
+/// - object keys become elements, keys prefixed with `@` become attributes of the object element
+///   and should be scalars, null attributes are left out;
+/// - arrays become repeated elements named after the array key, nested arrays are flattened and
+///   empty arrays are left out;
+/// - scalars become the element text and null becomes an empty element;
+/// - the elements of a root array are named `item`.
+///
+/// Keys that aren't valid XML names fail the conversion. Requires the `xml` feature.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_to_xml;
+///
+/// let input = json!({"order_id": "34554543", "skus": ["SKU-123", "SKU-343"]});
+/// let output = json!([{"order": {"@id": "/order_id", "sku": "/skus"}}]);
+///
+/// let xml = transform_to_xml(&input, &output).unwrap();
+/// assert_eq!(
+///     xml,
+///     r#"<?xml version="1.0" encoding="UTF-8"?><root><order id="34554543"><sku>SKU-123</sku><sku>SKU-343</sku></order></root>"#
+/// );
+/// ```
+#[cfg(feature = "xml")]
+pub fn transform_to_xml<I, O>(input: &I, output: &O) -> Result<String>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    xml::to_xml(&transform(input, output)?)
+}
+
+/// Transforms the input the same way as [`transform`] and writes the transformed output as CSV, a
+/// header row followed by one data row per object. Every output object should have been converted
+/// into an array of flat objects with the same fields, e.g. with `[order]`; the rows of all the
+/// output objects are written in order. Null fields are written as empty cells, nested fields and
+/// rows whose fields differ from the header fail the conversion. Requires the `csv` feature.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_to_csv;
+///
+/// let input = json!({"ids": ["34554543", "7643534"], "retailer": {"id": "12342"}});
+/// let output = json!([{"[order]": {"...id": "/ids", "account_id": "/retailer/id"}}]);
+///
+/// let csv = transform_to_csv(&input, &output).unwrap();
+/// assert_eq!(csv, "account_id,id\n12342,34554543\n12342,7643534\n");
+/// ```
+#[cfg(feature = "csv")]
+pub fn transform_to_csv<I, O>(input: &I, output: &O) -> Result<String>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    csv::to_csv(&transform(input, output)?)
+}
+
+/// Transforms the input the same way as [`transform`] and validates the transformed output against
+/// a JSON Schema before returning it, which catches template mistakes that give structurally valid
+/// but wrong output, e.g. a mapping to the wrong field type. The error lists every validation error
+/// along with the JSON pointer of the failing value. Requires the `jsonschema` feature.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::transform_validated;
+///
+/// let input = json!({"retailer": {"id": 12342}});
+/// let output = json!([{"order": {"account_id": "/retailer/id"}}]);
+/// let schema = json!({
+///     "type": "array",
+///     "items": {"properties": {"order": {"properties": {"account_id": {"type": "string"}}}}}
+/// });
+///
+/// let err = transform_validated(&input, &output, &schema).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Failed to validate the transformed output against the schema; /0/order/account_id: 12342 is not of type \"string\""
+/// );
+/// ```
+#[cfg(feature = "jsonschema")]
+pub fn transform_validated<I, O>(input: &I, output: &O, schema: &Value) -> Result<Value>
+where
+    I: Serialize + DeserializeOwned,
+    O: Serialize + DeserializeOwned,
+{
+    let transformed_output = transform(input, output)?;
+    schema::validate(&transformed_output, schema)?;
+    Ok(transformed_output)
+}
+
+/// Transforms a YAML input with a YAML output template and returns the transformed output as YAML.
+/// Both documents are parsed into JSON values, so the template syntax is the same as the JSON one,
+/// see [`transform`]. Multi-document YAML and YAML that has no JSON equivalent, e.g. non-string
+/// keys, fail the transform. Requires the `yaml` feature.
+/// # Example
+/// ```
+/// use transformer_rs::transform_yaml;
+///
+/// let input = "retailer:\n  id: '12342'\n";
+/// let output = "- order:\n    account_id: /retailer/id\n";
+///
+/// let transformed_output = transform_yaml(input, output).unwrap();
+/// assert_eq!(transformed_output, "- order:\n    account_id: '12342'\n");
+/// ```
+#[cfg(feature = "yaml")]
+pub fn transform_yaml(input_yaml: &str, output_yaml: &str) -> Result<String> {
+    let input = yaml::parse_yaml(input_yaml, "input")?;
+    let output = yaml::parse_yaml(output_yaml, "output")?;
+    let transformed_output = transform_values(&input, output, &TransformOptions::default())?;
+    serde_yaml::to_string(&transformed_output)
+        .context("Failed to serialize the transformed output to YAML")
+}
+
+/// Reads the input and the output JSON files and transforms them, see [`transform`].
+/// IO and parse errors name the offending file.
+/// # Example
+/// ```
+/// use std::path::Path;
+/// use transformer_rs::transform_files;
+///
+/// let transformed_output = transform_files(
+///     Path::new("./test/input.json"),
+///     Path::new("./test/output/default.json"),
+/// )
+/// .unwrap();
+/// ```
+#[cfg(feature = "fs")]
+pub fn transform_files(input_path: &Path, output_path: &Path) -> Result<Value> {
+    let input = read_json_file(input_path)?;
+    let output = read_json_file(output_path)?;
+    transform(&input, &output)
+}
+
+#[cfg(feature = "fs")]
+fn read_json_file(path: &Path) -> Result<Value> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read json file {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse json file {}", path.display()))
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_fun_call, clippy::needless_borrows_for_generic_args)]
+mod tests {
+    use super::*;
+    use once_cell::sync::Lazy;
+    use serde::Deserialize;
+    use serde_json::{from_str, json, Value};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::sync::Mutex;
+
+    const OUTPUT_JSON_FILES_DIR: &str = "./test/output";
+    static INPUT_JSON_FILE: Lazy<Mutex<Value>> = Lazy::new(|| {
+        let input = fs::read_to_string("./test/input.json").expect("Unable to read input file");
         Mutex::new(from_str(&input).expect("Unable to parse input json file to value"))
     });
 
     #[test]
-    fn transform_ok() {
-        let output = fs::read_to_string(&format!("{}/default.json", OUTPUT_JSON_FILES_DIR))
-            .expect("Unable to read file");
-        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+    fn transform_ok() {
+        let output = fs::read_to_string(&format!("{}/default.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let expected_transformed_output = fs::read_to_string(&format!(
+            "{}/transformed/default.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect(&format!(
+            "Unable to read file {}/transformed/default.json",
+            OUTPUT_JSON_FILES_DIR
+        ));
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect(&format!(
+                "Unable to parse file {}/transformed/default.json",
+                OUTPUT_JSON_FILES_DIR
+            ));
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        // println!(
+        //     "Output: {}",
+        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
+        // );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[test]
+    fn transform_ok_hard_coded_value() {
+        let output =
+            fs::read_to_string(&format!("{}/hard_coded_value.json", OUTPUT_JSON_FILES_DIR))
+                .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let expected_transformed_output = fs::read_to_string(&format!(
+            "{}/transformed/hard_coded_value.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect(&format!(
+            "Unable to read file {}/transformed/hard_coded_value.json",
+            OUTPUT_JSON_FILES_DIR
+        ));
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect(&format!(
+                "Unable to parse file {}/transformed/hard_coded_value.json",
+                OUTPUT_JSON_FILES_DIR
+            ));
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        // println!(
+        //     "Output: {}",
+        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
+        // );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[test]
+    fn transform_ok_object_to_array() {
+        let output = fs::read_to_string(&format!("{}/array_obj.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let expected_transformed_output = fs::read_to_string(&format!(
+            "{}/transformed/array_obj.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect(&format!(
+            "Unable to read file {}/transformed/array_obj.json",
+            OUTPUT_JSON_FILES_DIR
+        ));
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect(&format!(
+                "Unable to parse file {}/transformed/array_obj.json",
+                OUTPUT_JSON_FILES_DIR
+            ));
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        // println!(
+        //     "Output: {}",
+        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
+        // );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[test]
+    fn transform_ok_object_to_array_2() {
+        let output = fs::read_to_string(&format!("{}/array_obj_2.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let expected_transformed_output = fs::read_to_string(&format!(
+            "{}/transformed/array_obj_2.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect(&format!(
+            "Unable to read file {}/transformed/array_obj_2.json",
+            OUTPUT_JSON_FILES_DIR
+        ));
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect(&format!(
+                "Unable to parse file {}/transformed/array_obj_2.json",
+                OUTPUT_JSON_FILES_DIR
+            ));
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        // println!(
+        //     "Output: {}",
+        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
+        // );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[test]
+    fn transform_ok_spread_objects() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output =
+            json!([{"[items]": {"...item": "/order/shipments/items", "po": "/order/po_number"}}]);
+
+        let transformed_output = transform(&input, &output).unwrap();
+        assert_eq!(
+            transformed_output,
+            json!([{"items": [
+                {"item": {"quantity": 4, "sku": "SKU-123"}, "po": "573832"},
+                {"item": {"quantity": 3, "sku": "SKU-343"}, "po": "573832"},
+                {"item": {"quantity": 1, "sku": "SKU-1453"}, "po": "573832"},
+                {"item": {"quantity": 1, "sku": "SKU-543"}, "po": "573832"}
+            ]}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_root_array() {
+        let output = fs::read_to_string(format!("{}/array_root.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let expected_transformed_output = fs::read_to_string(format!(
+            "{}/transformed/array_root.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect("Unable to read file");
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect("Unable to parse file");
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[test]
+    fn transform_named_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([
+            {"[order]": {"...id": "/ids"}, "retailer": {"id": "/retailer/id"}},
+            {"@when": "/order/discount?", "discount": {"code": "/order/discount/code"}},
+            {"//note": "root array", "[]": {"...sku": "/order/shipments/items/sku"}}
+        ]);
+
+        let transformed_outputs = transform_named(&input, &output).unwrap();
+        assert_eq!(
+            transformed_outputs,
+            vec![
+                (
+                    "order".to_string(),
+                    json!([{"id": "34554543"}, {"id": "7643534"}, {"id": "512342"}])
+                ),
+                ("retailer".to_string(), json!({"id": "12342"})),
+                (
+                    "[]".to_string(),
+                    json!([
+                        {"sku": "SKU-123"},
+                        {"sku": "SKU-343"},
+                        {"sku": "SKU-1453"},
+                        {"sku": "SKU-543"}
+                    ])
+                ),
+            ]
+        );
+        assert!(transform_named(&input, &json!([{"order": {"id": "/order_idd"}}])).is_err());
+    }
+
+    #[test]
+    fn transform_err_root_array_with_other_fields() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{"[]": {"...ids": "/ids"}, "order": {"id": "/order_id"}}]);
+
+        let transformed_output = transform(&input, &output);
+        assert!(transformed_output.is_err());
+        assert!(transformed_output
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("output object marked as root array [] should have no other fields"));
+    }
+
+    #[test]
+    fn transform_ok_spread_product() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{
+            "[order]": {
+                "...item_id": "/ids",
+                "...tracking": "/order/shipments/tracking_number",
+                "account_id": "/retailer/id"
+            }
+        }]);
+        let options = TransformOptions {
+            spread_policy: SpreadPolicy::Product,
+            ..Default::default()
+        };
+
+        let transformed_output = transform_with_options(&input, &output, &options);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{
+                "order": [
+                    {"item_id": "34554543", "tracking": "1234567", "account_id": "12342"},
+                    {"item_id": "34554543", "tracking": "98776", "account_id": "12342"},
+                    {"item_id": "7643534", "tracking": "1234567", "account_id": "12342"},
+                    {"item_id": "7643534", "tracking": "98776", "account_id": "12342"},
+                    {"item_id": "512342", "tracking": "1234567", "account_id": "12342"},
+                    {"item_id": "512342", "tracking": "98776", "account_id": "12342"}
+                ]
+            }])
+        );
+    }
+
+    #[test]
+    fn transform_ok_spread_nested_arrays() {
+        let input = json!({
+            "shipments": [
+                {"items": ["SKU-123", "SKU-343"]},
+                {"items": ["SKU-1453", "SKU-543"]}
+            ],
+            "shipment_items": [["SKU-123", "SKU-343"], ["SKU-1453", "SKU-543"]]
+        });
+        let expected_transformed_output = json!([{
+            "details": [
+                {"sku": "SKU-123"},
+                {"sku": "SKU-343"},
+                {"sku": "SKU-1453"},
+                {"sku": "SKU-543"}
+            ]
+        }]);
+
+        // resolving through an array of objects flattens the items
+        let transformed_output = transform(
+            &input,
+            &json!([{"[details]": {"...sku": "/shipments/items"}}]),
+        );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+
+        // an array of arrays is flattened when spread
+        let transformed_output = transform(
+            &input,
+            &json!([{"[details]": {"...sku": "/shipment_items"}}]),
+        );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[test]
+    fn transform_err_array_convertible_obj_no_spread_array_field() {
+        let output = fs::read_to_string(&format!(
+            "{}/bad_array_convertible_obj_structure.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        // println!(
+        //     "Output: {}",
+        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
+        // );
+        assert!(transformed_output.is_err());
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "Failed to process array convertible object; a array convertible object [order] is detected but no spread array field was found"
+        );
+    }
+
+    #[test]
+    fn transform_err_no_array_convertible_obj_and_spread_array_field() {
+        let output = fs::read_to_string(&format!(
+            "{}/bad_array_convertible_obj_structure_2.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &output);
+        // println!(
+        //     "Output: {}",
+        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
+        // );
+        assert!(transformed_output.is_ok());
+    }
+
+    #[test]
+    fn transformer_bad_output_structure() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_result = transform(&input, &json!({}));
+        assert!(transformed_result.is_err());
+
+        assert_eq!(
+            transformed_result.err().unwrap().to_string(),
+            "output should be in an array of object structure"
+        );
+    }
+
+    #[test]
+    fn transformer_bad_output_array_element_structure() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &json!([[]]));
+        assert!(transformed_output.is_err());
+
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "output array elements should be in object structure: []"
+        );
+    }
+
+    #[test]
+    fn transformer_output_array_obj_element_without_name() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &json!([{}]));
+        assert!(transformed_output.is_err());
+
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "failed to get the name of the output: {}"
+        );
+    }
+
+    #[test]
+    fn transform_ok_comments() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{
+            "order": {
+                "//note": "this maps the tracking numbers",
+                "trackings": "/order/shipments/tracking_number"
+            }
+        }]);
+
+        let transformed_output = transform(&input, &output);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order": {"trackings": ["1234567", "98776"]}}])
+        );
+    }
+
+    #[test]
+    fn transform_err_order_key() {
+        let input = json!({"order": {"id": 1}});
+        let output = json!([{"order": {"@order": ["id"], "id": "/order/id"}}]);
+
+        let err = transform(&input, &output).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @order format; ordering the keys of an object is not supported, the keys of the \
+             transformed objects are always sorted"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/order/@order"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn transform_ok_sorted_input_keys() {
+        let mut warehouses = std::collections::HashMap::new();
+        warehouses.insert("west".to_string(), json!({"stock": 3}));
+        warehouses.insert("east".to_string(), json!({"stock": 5}));
+        warehouses.insert("north".to_string(), json!({"stock": 1}));
+        let input = std::collections::HashMap::from([("warehouses".to_string(), warehouses)]);
+        let output = json!([{"stocks": "/warehouses/*/stock"}]);
+
+        let transformed_output = transform(&input, &output).unwrap();
+        assert_eq!(transformed_output, json!([{"stocks": [5, 1, 3]}]));
+    }
+
+    #[test]
+    fn transform_ok_quoted_keys() {
+        let input = json!({"order": {"a/b": ["1", "2"]}});
+        let output = json!([{"[order/line]": {"...ids": "/order/[\"a/b\"]"}}]);
+
+        let transformed_output = transform(&input, &output);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order/line": [{"ids": "1"}, {"ids": "2"}]}])
+        );
+    }
+
+    #[test]
+    fn describe_transform_ok() {
+        let output = fs::read_to_string(format!("{}/default.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let types = describe_transform(&input, &output);
+        assert!(types.is_ok());
+        assert_eq!(
+            types.unwrap(),
+            vec![
+                ("/order/sub_order/account_id", "string"),
+                ("/order/sub_order/details/quantity", "array"),
+                ("/order/sub_order/details/trackings", "array"),
+                ("/order/sub_order/fulfillment_line_item_id", "string"),
+                ("/order/sub_order/item_ids", "array"),
+                ("/order/sub_order/product/id", "string"),
+            ]
+            .into_iter()
+            .map(|(path, type_name)| (path.to_string(), type_name.to_string()))
+            .collect::<Vec<(String, String)>>()
+        );
+    }
+
+    #[test]
+    fn transform_ok_depth() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([
+            {"order": {
+                "depth": "@depth",
+                "meta": {"depth": "@depth", "levels": ["@depth"]},
+                "shipments": {
+                    "@each": "/order/shipments",
+                    "depth": "@depth",
+                    "items": {"@each": "items", "depth": "@depth"}
+                },
+                "slots": {"@range": 1, "depth": "@depth"},
+                "[lines]": {"...id": "/ids", "depth": "@depth"}
+            }},
+            {"[]": {"...id": "/ids", "depth": "@depth"}}
+        ]);
+
+        let transformed_output = transform(&input, &output).unwrap();
+        assert_eq!(transformed_output[0]["order"]["depth"], json!(2));
+        assert_eq!(transformed_output[0]["order"]["meta"]["depth"], json!(3));
+        assert_eq!(transformed_output[0]["order"]["meta"]["levels"], json!([4]));
+        let shipment = &transformed_output[0]["order"]["shipments"][1];
+        assert_eq!(shipment["depth"], json!(4));
+        assert_eq!(shipment["items"][0]["depth"], json!(6));
+        assert_eq!(
+            transformed_output[0]["order"]["slots"][0]["depth"],
+            json!(4)
+        );
+        assert_eq!(
+            transformed_output[0]["order"]["lines"][2]["depth"],
+            json!(4)
+        );
+        assert_eq!(transformed_output[1][0]["depth"], json!(2));
+
+        assert_eq!(
+            describe_transform(&input, &json!([{"order": {"depth": "@depth"}}])).unwrap(),
+            vec![("/order/depth".to_string(), "number".to_string())]
+        );
+    }
+
+    #[test]
+    fn transform_ok_present_null() {
+        let input = json!({"order": {"id": "34554543", "discount": null}});
+        let output =
+            json!([{"order": {"discount": "/order/discount", "code": "/order/discount/code"}}]);
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"order": {"discount": null, "code": null}}])
+        );
+
+        let output = json!([{"order": {"coupon": "/order/coupon"}}]);
+        assert!(transform(&input, &output).is_err());
+        let output = json!([{"order": {"code": "/order/id/code"}}]);
+        assert!(transform(&input, &output).is_err());
+    }
+
+    #[test]
+    fn transform_ok_default_object() {
+        let output = json!([{"product": {
+            "name": "/product/name",
+            "details": {
+                "@defaultObject": {"material": "unknown", "weight": null},
+                "material": "/product/details/material",
+                "weight": "/product/details/weight"
+            }
+        }}]);
+
+        let input = json!({"product": {"name": "Red Shoes"}});
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"product": {"name": "Red Shoes", "details": {"material": "unknown", "weight": null}}}])
+        );
+        let input = json!({"product": {"name": "Red Shoes", "details": {"material": "leather", "weight": 2}}});
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"product": {"name": "Red Shoes", "details": {"material": "leather", "weight": 2}}}])
+        );
+        let input = json!({"product": {"name": "Red Shoes", "details": {"material": "leather"}}});
+        let err = transform_diagnostic(&input, &output).unwrap_err();
+        assert_eq!(err.pointer(), Some("/0/product/details/weight"));
+
+        // the `@index` fields of a body that wholly misses are not filled in the default object
+        let input = json!({"ids": [1, 2]});
+        let output = json!([{"[items]": {
+            "...id": "/ids",
+            "gift": {"@defaultObject": {"none": true}, "note": "/gift/note", "line": "@index"}
+        }}]);
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"items": [
+                {"id": 1, "gift": {"none": true}},
+                {"id": 2, "gift": {"none": true}}
+            ]}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_dsl() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let dsl_output = parse_dsl(
+            "
+            order.account_id = /retailer/id
+            order.po_number = /order/po_number
+            order.order_id = /order_id
+            order.ids = /ids
+            order.product_length = /product/length
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            transform(&input, &dsl_output).unwrap(),
+            transform(
+                &input,
+                &json!([{"order": {
+                    "account_id": "/retailer/id",
+                    "po_number": "/order/po_number",
+                    "order_id": "/order_id",
+                    "ids": "/ids",
+                    "product_length": "/product/length"
+                }}])
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn transform_ok_translate() {
+        let input = json!({"order": {"status": "S", "carrier": "UPS"}});
+        let output = json!([{"order": {
+            "status": "translate(/order/status, {'S': 'shipped', 'P': 'pending'})",
+            "carrier": "translate(/order/carrier, {'DHL': 'dhl'}) || 'other'",
+            "tracked": "translate(/order/carrier, {\"UPS\": true}, 'no')"
+        }}]);
+
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"order": {"status": "shipped", "carrier": "other", "tracked": true}}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_meta() {
+        let input = json!({"order_id": 1, "ids": [1, 2]});
+        let meta = json!({"version": "3", "owner": "/team/orders"});
+        let output = json!([
+            {"@meta": meta, "order": {"id": "/order_id"}, "customer": {"order": "/order_id"}},
+            {"@meta": {"version": "1"}, "[]": {"...id": "/ids"}},
+            {"[items]": {"...id": "/ids"}}
+        ]);
+
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([
+                {"order": {"id": 1}, "customer": {"order": 1}},
+                [{"id": 1}, {"id": 2}],
+                {"items": [{"id": 1}, {"id": 2}]}
+            ])
+        );
+        assert_eq!(
+            transform_named(&input, &output)
+                .unwrap()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<String>>(),
+            vec!["customer", "order", "[]", "items"]
+        );
+        assert_eq!(
+            template_meta(&output).unwrap(),
+            vec![
+                ("customer".to_string(), meta.clone()),
+                ("order".to_string(), meta),
+                ("[]".to_string(), json!({"version": "1"})),
+            ]
+        );
+        assert_eq!(
+            describe_transform(&input, &output).unwrap()[0],
+            ("/customer/order".to_string(), "number".to_string())
+        );
+        assert!(transform(&input, &json!([{"@meta": {"version": "3"}}])).is_err());
+
+        // the metadata is neither diffed nor explained
+        let old = json!([{"@meta": {"version": "2"}, "order": {"id": "/order_id"}}]);
+        let new = json!([{"@meta": {"version": "3"}, "order": {"id": "/order_id"}}]);
+        assert!(diff_templates(&old, &new).is_empty());
+        let output = json!([{"@meta": {"[x]": {"id": "/id"}}, "[order]": {"...id": "/ids"}}]);
+        assert_eq!(
+            explain_array_conversion(&output)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            vec!["/0/[order] is split on /0/[order]/...id"]
+        );
+    }
+
+    #[cfg(feature = "jsonpath")]
+    #[test]
+    fn transform_ok_jsonpath() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{"order": {
+            "trackings": "jsonpath:$.order.shipments[*].tracking_number",
+            "bulk_skus": "jsonpath:$..items[?(@.quantity > 1 || @.sku == 'SKU-543')].sku",
+            "account_id": "jsonpath:$.retailer.id",
+            "carrier": "jsonpath:$.order.carrier || 'unknown'",
+            "item_count": "size(jsonpath:$..items[*])",
+            "lines": {"@each": "/order/shipments", "first_sku": "jsonpath:$.order.shipments[0].items[0].sku"}
+        }}]);
+
+        let transformed_output = transform(&input, &output).unwrap();
+        assert_eq!(
+            transformed_output[0]["order"]["bulk_skus"],
+            json!(["SKU-123", "SKU-343", "SKU-543"])
+        );
+        assert_eq!(transformed_output[0]["order"]["account_id"], json!("12342"));
+        assert_eq!(transformed_output[0]["order"]["carrier"], json!("unknown"));
+        assert_eq!(transformed_output[0]["order"]["item_count"], json!(4));
+        assert_eq!(
+            transformed_output[0]["order"]["trackings"],
+            transform(
+                &input,
+                &json!([{"order": {"trackings": "/order/shipments/tracking_number"}}])
+            )
+            .unwrap()[0]["order"]["trackings"]
+        );
+        assert_eq!(
+            transformed_output[0]["order"]["lines"][1]["first_sku"],
+            json!("SKU-123")
+        );
+    }
+
+    #[test]
+    fn transform_ok_exists() {
+        let output = json!([{"order": {
+            "has_discount": "exists(/order/discount_code)",
+            "discount_code": "/order/discount_code || 'none'"
+        }}]);
+
+        let input = json!({"order": {"discount_code": "SUMMER"}});
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"order": {"has_discount": true, "discount_code": "SUMMER"}}])
+        );
+        let input = json!({"order": {"id": 1}});
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"order": {"has_discount": false, "discount_code": "none"}}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_coalesce_array() {
+        let input = json!({"customer": {"phone_home": "555-0100", "phone_mobile": null}});
+        let output = json!([{"customer": {
+            "phones": "coalesceArray(/customer/phone_home, /customer/phone_work, /customer/phone_mobile)",
+            "fax": "coalesceArray(/customer/fax)"
+        }}]);
+
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"customer": {"phones": ["555-0100"], "fax": []}}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_typeswitch() {
+        let output = json!([{"product": {
+            "price": {"@typeswitch": "/price", "number": "/price", "object": "/price/amount"},
+            "currency": {
+                "@typeswitch": "/price",
+                "number": "'USD'",
+                "object": "/price/currency"
+            }
+        }}]);
+
+        let input = json!({"price": 12.5});
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"product": {"price": 12.5, "currency": "USD"}}])
+        );
+        assert_eq!(
+            describe_transform(&input, &output).unwrap(),
+            vec![
+                ("/product/currency".to_string(), "string".to_string()),
+                ("/product/price".to_string(), "number".to_string()),
+            ]
+        );
+
+        let input = json!({"price": {"amount": 10, "currency": "EUR"}});
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"product": {"price": 10, "currency": "EUR"}}])
+        );
+
+        let input = json!({"price": "12.50"});
+        let err = transform_diagnostic(&input, &output).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Failed to resolve @typeswitch; /price resolves to a string, which has no branch: \"12.50\""
+        );
+        assert_eq!(err.pointer(), Some("/0/product/currency/@typeswitch"));
+    }
+
+    #[test]
+    fn describe_transform_bad_output_array_element_structure() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let types = describe_transform(&input, &json!([[]]));
+        assert!(types.is_err());
+        assert_eq!(
+            types.err().unwrap().to_string(),
+            "output array elements should be in object structure: []"
+        );
+    }
+
+    #[test]
+    fn transform_ok_default_on_missing() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{"order": {"id": "/order_id", "discount": "/order/discount"}}]);
+        let options = TransformOptions {
+            default_on_missing: Some(json!("N/A")),
+            ..Default::default()
+        };
+
+        let transformed_output = transform_with_options(&input, &output, &options);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order": {"id": "34554543", "discount": "N/A"}}])
+        );
+    }
+
+    #[test]
+    fn transform_diagnostic_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{"order": {"id": "/order_id"}}]);
+
+        let transformed_output = transform_diagnostic(&input, &output);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order": {"id": "34554543"}}])
+        );
+    }
+
+    #[test]
+    fn transform_diagnostic_err() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([
+            {"order": {"id": "/order_id"}},
+            {"product": {"name": "/product/details/name", "size": "/product/size"}}
+        ]);
+
+        let transformed_output = transform_diagnostic(&input, &output);
+        assert!(transformed_output.is_err());
+        let err = transformed_output.err().unwrap();
+        assert_eq!(err.pointer(), Some("/1/product/size"));
+        assert!(err
+            .message()
+            .starts_with("Failed to resolve mapping value; couldn't find field name size"));
+        assert!(err
+            .render()
+            .contains(">  10 |       \"size\": \"/product/size\""));
+    }
+
+    #[test]
+    fn transform_diagnostic_err_without_pointer() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+
+        let transformed_output = transform_diagnostic(&input, &json!({}));
+        assert!(transformed_output.is_err());
+        let err = transformed_output.err().unwrap();
+        assert_eq!(err.pointer(), None);
+        assert_eq!(
+            err.render(),
+            "error: output should be in an array of object structure"
+        );
+    }
+
+    #[test]
+    fn transform_value_ok() {
+        let output = fs::read_to_string(format!("{}/default.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+
+        let expected_transformed_output = fs::read_to_string(format!(
+            "{}/transformed/default.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect("Unable to read file");
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect("Unable to parse file");
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform_value(&input, &output);
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            expected_transformed_output
+        );
+
+        let output = json!([{"order": {"id": "/order_id", "discount": "/discount"}}]);
+        let options = TransformOptions {
+            default_on_missing: Some(json!(0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            transform_value_with_options(&input, &output, &options).unwrap(),
+            transform_with_options(&input, &output, &options).unwrap()
+        );
+        assert!(transform_value(&input, &output).is_err());
+    }
+
+    #[test]
+    fn transform_ok_typed_input_renamed_fields() {
+        #[derive(Serialize, Deserialize)]
+        struct Order {
+            #[serde(rename = "poNumber")]
+            po_number: String,
+        }
+
+        let input = Order {
+            po_number: "573832".to_string(),
+        };
+        let transformed_output = transform(&input, &json!([{"order": {"po": "/poNumber"}}]));
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order": {"po": "573832"}}])
+        );
+
+        // the rust field name is not part of the serialized input
+        let transformed_output = transform(&input, &json!([{"order": {"po": "/po_number"}}]));
+        assert!(transformed_output.is_err());
+    }
+
+    #[test]
+    fn transform_err_serialization() {
+        // maps with non string keys can't be serialized into JSON values
+        let unserializable: BTreeMap<(u8, u8), u8> = vec![((1, 2), 3)].into_iter().collect();
+
+        let transformed_output = transform(&unserializable, &json!([{"order": {"id": "/id"}}]));
+        assert!(transformed_output.is_err());
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "Failed to serialize input to JSON value"
+        );
+
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let transformed_output = transform(&input, &unserializable);
+        assert!(transformed_output.is_err());
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "Failed to serialize output template to JSON value"
+        );
+
+        let types = describe_transform(&unserializable, &json!([{"order": {"id": "/id"}}]));
+        assert!(types.is_err());
+        assert_eq!(
+            types.err().unwrap().to_string(),
+            "Failed to serialize input to JSON value"
+        );
+    }
+
+    #[test]
+    fn transform_each_err() {
+        let output = json!([{"order": {"id": "/order_id"}}]);
+        let inputs = vec![
+            json!({"order_id": 1}),
+            json!({"id": 2}),
+            json!({"order_id": 3}),
+        ];
+        let err = transform_each(&inputs, &output).unwrap_err();
+        assert_eq!(err.index(), 1);
+        assert_eq!(
+            err.error().to_string(),
+            "Failed to resolve mapping value; couldn't find field name order_id in the obj {\n  \"id\": 2\n}"
+        );
+
+        let unserializable: BTreeMap<(u8, u8), u8> = vec![((1, 2), 3)].into_iter().collect();
+        let err = transform_each(&inputs, &unserializable).unwrap_err();
+        assert_eq!(err.index(), 0);
+        assert_eq!(
+            err.error().to_string(),
+            "Failed to serialize output template to JSON value"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn transform_multi_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let outputs = std::collections::HashMap::from([
+            (
+                "order".to_string(),
+                json!([{"order": {"id": "/order_id", "ids": "/ids"}}]),
+            ),
+            (
+                "shipments".to_string(),
+                json!([{"[]": {"...tracking": "/order/shipments/tracking_number"}}]),
+            ),
+        ]);
+
+        let transformed_outputs = transform_multi(&input, &outputs).unwrap();
+        assert_eq!(transformed_outputs.len(), 2);
+        assert_eq!(
+            transformed_outputs["order"],
+            json!([{"order": {"id": "34554543", "ids": ["34554543", "7643534", "512342"]}}])
+        );
+        assert_eq!(
+            transformed_outputs["shipments"],
+            json!([[{"tracking": "1234567"}, {"tracking": "98776"}]])
+        );
+        assert!(transform_multi(&input, &Default::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn transform_ok_str_duplicate_keys() {
+        let input = r#"{"order": {"id": 1, "skus": ["a"], "id": 2}, "order": {"id": 3}}"#;
+        let output = r#"[{"order": {"id": "/order/id"}}]"#;
+        let options = TransformOptions {
+            warn_on_duplicate_input_keys: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            transform_str_with_duplicate_keys(input, output, &options).unwrap(),
+            (
+                json!([{"order": {"id": 3}}]),
+                vec!["/order/id".to_string(), "/order".to_string()]
+            )
+        );
+        assert_eq!(
+            transform_str_with_duplicate_keys(input, output, &Default::default()).unwrap(),
+            (json!([{"order": {"id": 3}}]), vec![])
+        );
+        let options = TransformOptions {
+            deny_duplicate_input_keys: true,
+            ..options
+        };
+        assert_eq!(
+            transform_str_with_options(input, output, &options)
+                .err()
+                .unwrap()
+                .downcast_ref::<DuplicateKeysError>()
+                .unwrap()
+                .pointers(),
+            ["/order/id", "/order"]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn transform_multi_err() {
+        let input = json!({"order_id": 1});
+        let outputs = std::collections::HashMap::from([
+            ("order".to_string(), json!([{"order": {"id": "/order_id"}}])),
+            (
+                "invoice".to_string(),
+                json!([{"invoice": {"id": "/invoice_id"}}]),
+            ),
+        ]);
+
+        let err = transform_multi(&input, &outputs).err().unwrap();
+        assert_eq!(err.to_string(), "Failed to transform the template invoice");
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/invoice/id"
+        );
+    }
+
+    #[test]
+    fn transform_ok_as_array() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{"order": {
+            "ids": "asArray(/order_id)",
+            "all_ids": "asArray(/ids)",
+            "discounts": "asArray(/order/discount?) || asArray('none')"
+        }}]);
+
+        let transformed_output = transform(&input, &output);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order": {
+                "ids": ["34554543"],
+                "all_ids": ["34554543", "7643534", "512342"],
+                "discounts": []
+            }}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_range() {
+        let input = json!({"ids": ["a", "b"], "skus": ["x", "y"]});
+        let output = json!([{"order": {
+            "slots": {"@range": 3, "slot": "@index"},
+            "lines": {
+                "@range": "count(/ids)",
+                "line": "@index",
+                "[items]": {"...sku": "/skus", "position": "@index"}
+            }
+        }}]);
+
+        let transformed_output = transform(&input, &output).unwrap();
+        let items = json!([{"sku": "x", "position": 0}, {"sku": "y", "position": 1}]);
+        assert_eq!(
+            transformed_output,
+            json!([{"order": {
+                "slots": [{"slot": 0}, {"slot": 1}, {"slot": 2}],
+                "lines": [{"line": 0, "items": items}, {"line": 1, "items": items}]
+            }}])
+        );
+
+        let output = json!([{"order": {"slots": {"@range": "/ids", "slot": "@index"}}}]);
+        let err = transform(&input, &output).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to resolve @range; \"/ids\" should resolve to a non-negative integer: [\"a\",\"b\"]"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/order/slots/@range"
+        );
+
+        let options = TransformOptions {
+            max_array_elements: Some(2),
+            ..Default::default()
+        };
+        let output = json!([{"order": {"slots": {"@range": 3, "slot": "@index"}}}]);
+        assert_eq!(
+            transform_with_options(&input, &output, &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to resolve @range; the array would have 3 elements, more than the max_array_elements cap of 2"
+        );
+    }
+
+    #[test]
+    fn transform_ok_index() {
+        let input = json!({"ids": ["a", "b"], "codes": ["@index"], "skus": [["x", "y"], ["z"]]});
+        let output = json!([{"[orders]": {
+            "...id": "/ids",
+            "position": "@index",
+            "code": "/codes",
+            "[items]": {"...sku": "/skus", "line": " @index "}
+        }}]);
+
+        let transformed_output = transform(&input, &output).unwrap();
+        assert_eq!(
+            transformed_output,
+            json!([{"orders": [
+                {
+                    "id": "a",
+                    "position": 0,
+                    "code": ["@index"],
+                    "items": [{"sku": "x", "line": 0}, {"sku": "y", "line": 1}, {"sku": "z", "line": 2}]
+                },
+                {
+                    "id": "b",
+                    "position": 1,
+                    "code": ["@index"],
+                    "items": [{"sku": "x", "line": 0}, {"sku": "y", "line": 1}, {"sku": "z", "line": 2}]
+                }
+            ]}])
+        );
+
+        // the index is filled in out of band, input strings are never taken for an index field,
+        // and the index fields of `@each` bodies and `@defaultObject` bodies are filled in as well
+        let input = json!({"ids": ["a", "b"], "note": "\u{0}@index\u{0}", "skus": [{"sku": "x"}]});
+        let output = json!([{"[orders]": {
+            "...id": "/ids",
+            "note": "/note",
+            "lines": {"@each": "/skus", "sku": "sku", "position": "@index"},
+            "gift": {"@defaultObject": {"none": true}, "note": "/gift?", "position": "@index"}
+        }}]);
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"orders": [
+                {
+                    "id": "a",
+                    "note": "\u{0}@index\u{0}",
+                    "lines": [{"sku": "x", "position": 0}],
+                    "gift": {"note": null, "position": 0}
+                },
+                {
+                    "id": "b",
+                    "note": "\u{0}@index\u{0}",
+                    "lines": [{"sku": "x", "position": 1}],
+                    "gift": {"note": null, "position": 1}
+                }
+            ]}])
+        );
+
+        let output = json!([{"order": {"position": "@index"}}]);
+        let err = transform(&input, &output).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @index format; it can only be used in an array convertible object or a @range body"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/order/position"
+        );
+    }
+
+    #[test]
+    fn transform_pointers_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let pointers = json!({
+            "/order/sub_order/account_id": "/retailer/id",
+            "/order/id": "/order_id",
+            "/[order_items]/...id": "/ids"
+        });
+
+        let transformed_output = transform_pointers(&input, pointers.as_object().unwrap()).unwrap();
+        assert_eq!(
+            transformed_output,
+            transform(
+                &input,
+                &json!([{
+                    "order": {"sub_order": {"account_id": "/retailer/id"}, "id": "/order_id"},
+                    "[order_items]": {"...id": "/ids"}
+                }])
+            )
+            .unwrap()
+        );
+
+        let pointers = json!({"/order": "/order", "/order/id": "/order_id"});
+        assert_eq!(
+            transform_pointers(&input, pointers.as_object().unwrap())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Bad pointer format; /order/id is under /order, which is already mapped to \"/order\""
+        );
+    }
+
+    #[test]
+    fn transform_layered_ok() {
+        let base = INPUT_JSON_FILE.lock().unwrap().clone();
+        let overrides = json!({"order_id": "1", "order": {"discount": {"code": "SUMMER"}}});
+        let output = json!([{"order": {
+            "id": "/order_id",
+            "ids": "/ids",
+            "discount": "/order/discount/code",
+            "items": {"@each": "/order/shipments", "tracking": "tracking_number"}
+        }}]);
+
+        let transformed_output = transform_layered(&[&overrides, &base], &output).unwrap();
+        assert_eq!(
+            transformed_output,
+            json!([{"order": {
+                "id": "1",
+                "ids": ["34554543", "7643534", "512342"],
+                "discount": "SUMMER",
+                "items": [{"tracking": "1234567"}, {"tracking": "98776"}]
+            }}])
+        );
+        assert!(transform_layered(&[&base], &output).is_err());
+
+        // an optional field missing from the overrides is resolved from the base
+        let overrides = json!({"order": {"status": "cancelled"}});
+        let base = json!({"order": {"status": "shipped", "gift": {"note": "Enjoy"}}});
+        let optional_output = json!([{"order": {
+            "status": "/order/status",
+            "note": "/order/gift?/note",
+            "coupon": "/order/coupon?"
+        }}]);
+        assert_eq!(
+            transform_layered(&[&overrides, &base], &optional_output).unwrap(),
+            json!([{"order": {"status": "cancelled", "note": "Enjoy", "coupon": null}}])
+        );
+        assert_eq!(
+            transform_layered::<Value, Value>(&[], &output)
+                .err()
+                .unwrap()
+                .to_string(),
+            "inputs should have at least one input to transform"
+        );
+    }
+
+    #[test]
+    fn transform_with_observer_ok() {
+        #[derive(Default)]
+        struct Recorder(core::cell::RefCell<Vec<String>>);
 
-        let expected_transformed_output = fs::read_to_string(&format!(
-            "{}/transformed/default.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect(&format!(
-            "Unable to read file {}/transformed/default.json",
-            OUTPUT_JSON_FILES_DIR
-        ));
-        let expected_transformed_output: Value =
-            from_str(&expected_transformed_output).expect(&format!(
-                "Unable to parse file {}/transformed/default.json",
-                OUTPUT_JSON_FILES_DIR
-            ));
+        impl TransformObserver for Recorder {
+            fn on_resolve(&self, output_path: &str, input_path: &str, result: &Value) {
+                self.0
+                    .borrow_mut()
+                    .push(format!("{} <- {} = {}", output_path, input_path, result));
+            }
+
+            fn on_missing(&self, output_path: &str, input_path: &str) {
+                self.0
+                    .borrow_mut()
+                    .push(format!("{} <- {} missing", output_path, input_path));
+            }
+        }
 
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_output = transform(&input, &output);
-        // println!(
-        //     "Output: {}",
-        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
-        // );
-        assert!(transformed_output.is_ok());
-        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+        let output = json!([{"order": {
+            "id": "/order_id",
+            "source": "'edi'",
+            "items": {"@each": "/order/shipments", "tracking": "tracking_number"},
+            "discount": "/order/discount || 'none'"
+        }}]);
+        let recorder = Recorder::default();
+
+        let transformed_output = transform_with_observer(&input, &output, &recorder).unwrap();
+        assert_eq!(transformed_output, transform(&input, &output).unwrap());
+        assert_eq!(
+            *recorder.0.borrow(),
+            vec![
+                "/order/discount <- /order/discount || 'none' = \"none\"",
+                "/order/id <- /order_id = \"34554543\"",
+                "/order/items/tracking <- tracking_number = \"1234567\"",
+                "/order/items/tracking <- tracking_number = \"98776\"",
+                "/order/source <- 'edi' = \"edi\"",
+            ]
+        );
+
+        let output = json!([{"order": {"id": "/order_id", "discount": "/order/discount"}}]);
+        let recorder = Recorder::default();
+        assert!(transform_with_observer(&input, &output, &recorder).is_err());
+        assert_eq!(
+            *recorder.0.borrow(),
+            vec!["/order/discount <- /order/discount missing"]
+        );
+
+        // the body of a `@defaultObject` object is resolved once
+        let output = json!([{"order": {
+            "id": "/order_id",
+            "discount": {"@defaultObject": {}, "code": "/order/discount"}
+        }}]);
+        let recorder = Recorder::default();
+        transform_with_observer(&input, &output, &recorder).unwrap();
+        assert_eq!(
+            *recorder.0.borrow(),
+            vec![
+                "/order/discount/code <- /order/discount missing",
+                "/order/id <- /order_id = \"34554543\"",
+            ]
+        );
     }
 
     #[test]
-    fn transform_ok_hard_coded_value() {
-        let output =
-            fs::read_to_string(&format!("{}/hard_coded_value.json", OUTPUT_JSON_FILES_DIR))
-                .expect("Unable to read file");
-        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+    fn transform_with_resolver_ok() {
+        struct Lookup;
 
-        let expected_transformed_output = fs::read_to_string(&format!(
-            "{}/transformed/hard_coded_value.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect(&format!(
-            "Unable to read file {}/transformed/hard_coded_value.json",
-            OUTPUT_JSON_FILES_DIR
-        ));
-        let expected_transformed_output: Value =
-            from_str(&expected_transformed_output).expect(&format!(
-                "Unable to parse file {}/transformed/hard_coded_value.json",
-                OUTPUT_JSON_FILES_DIR
-            ));
+        impl LeafResolver for Lookup {
+            fn resolve(
+                &self,
+                context: &LeafContext,
+                mapping: &str,
+                output_path: &str,
+            ) -> Result<Value> {
+                match mapping.strip_prefix("db:") {
+                    Some(table) => Ok(json!(format!(
+                        "{} {} {}",
+                        table,
+                        output_path,
+                        context.input()
+                    ))),
+                    None => DefaultLeafResolver.resolve(context, mapping, output_path),
+                }
+            }
+        }
 
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_output = transform(&input, &output);
-        // println!(
-        //     "Output: {}",
-        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
-        // );
-        assert!(transformed_output.is_ok());
-        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+        let output = json!([{"order": {
+            "id": "/order_id",
+            "carrier": "/order/carrier || 'unknown'",
+            "[items]": {"...id": "/ids"},
+            "shipments": {
+                "@each": "/order/shipments",
+                "tracking": "tracking_number",
+                "order_id": "/order_id",
+                "status": "db:shipments"
+            }
+        }}]);
+
+        let transformed_output = transform_with_resolver(&input, &output, &Lookup).unwrap();
+        let shipments = &input["order"]["shipments"];
+        assert_eq!(
+            transformed_output[0]["order"]["shipments"],
+            json!([
+                {
+                    "tracking": "1234567",
+                    "order_id": "34554543",
+                    "status": format!("shipments /order/shipments/status {}", shipments[0])
+                },
+                {
+                    "tracking": "98776",
+                    "order_id": "34554543",
+                    "status": format!("shipments /order/shipments/status {}", shipments[1])
+                }
+            ])
+        );
+
+        let output = json!([{"order": {
+            "id": "/order_id",
+            "carrier": "/order/carrier || 'unknown'",
+            "[items]": {"...id": "/ids"},
+            "shipments": {"@each": "/order/shipments", "tracking": "tracking_number"}
+        }}]);
+        assert_eq!(
+            transform_with_resolver(&input, &output, &DefaultLeafResolver).unwrap(),
+            transform(&input, &output).unwrap()
+        );
+
+        let output = json!([{"order": {"id": "/order_idd"}}]);
+        let err = transform_with_resolver(&input, &output, &DefaultLeafResolver).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/order/id"
+        );
     }
 
     #[test]
-    fn transform_ok_object_to_array() {
-        let output = fs::read_to_string(&format!("{}/array_obj.json", OUTPUT_JSON_FILES_DIR))
-            .expect("Unable to read file");
-        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+    fn transform_with_default_resolver_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let mut outputs = vec![];
+        for entry in fs::read_dir(OUTPUT_JSON_FILES_DIR).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_file() {
+                let output = fs::read_to_string(&path).expect("Unable to read output file");
+                outputs.push(from_str::<Value>(&output).expect("Unable to parse output file"));
+            }
+        }
+        outputs.extend([
+            json!([{"shipments": {
+                "@each": "/order/shipments",
+                "tracking": "tracking_number",
+                "order_id": "../order_id",
+                "next": "@sibling:tracking_number",
+                "missing": "../order_idd || 'none'"
+            }}]),
+            json!([{"[items]": {"...id": "/ids", "line": "@index", "count": "size(/ids)"}}]),
+            json!([{"slots": {"@range": 2, "slot": "@index"}}]),
+            json!([{"id": "/order_id", "bad": "nope(/ids)"}]),
+            json!([{"id": "/order_idd"}]),
+        ]);
 
-        let expected_transformed_output = fs::read_to_string(&format!(
-            "{}/transformed/array_obj.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect(&format!(
-            "Unable to read file {}/transformed/array_obj.json",
-            OUTPUT_JSON_FILES_DIR
-        ));
-        let expected_transformed_output: Value =
-            from_str(&expected_transformed_output).expect(&format!(
-                "Unable to parse file {}/transformed/array_obj.json",
-                OUTPUT_JSON_FILES_DIR
-            ));
+        for output in outputs {
+            let expected = transform(&input, &output).map_err(|err| err.to_string());
+            assert_eq!(
+                transform_with_resolver(&input, &output, &DefaultLeafResolver)
+                    .map_err(|err| err.to_string()),
+                expected,
+                "{}",
+                output
+            );
+        }
 
-        let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_output = transform(&input, &output);
-        // println!(
-        //     "Output: {}",
-        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
-        // );
-        assert!(transformed_output.is_ok());
-        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+        // a malformed mapping fails the transformation even if the resolver handles the error
+        struct Lenient;
+
+        impl LeafResolver for Lenient {
+            fn resolve(
+                &self,
+                context: &LeafContext,
+                mapping: &str,
+                output_path: &str,
+            ) -> Result<Value> {
+                DefaultLeafResolver
+                    .resolve(context, mapping, output_path)
+                    .or(Ok(Value::Null))
+            }
+        }
+
+        let output = json!([{"id": "/order_idd"}]);
+        assert_eq!(
+            transform_with_resolver(&input, &output, &Lenient).unwrap(),
+            json!([{"id": null}])
+        );
+        let output = json!([{"id": "nope(/ids)"}]);
+        assert_eq!(
+            transform_with_resolver(&input, &output, &Lenient)
+                .unwrap_err()
+                .to_string(),
+            "Bad function format; unknown function nope"
+        );
     }
 
     #[test]
-    fn transform_ok_object_to_array_2() {
-        let output = fs::read_to_string(&format!("{}/array_obj_2.json", OUTPUT_JSON_FILES_DIR))
-            .expect("Unable to read file");
-        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+    fn transform_with_report_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([
+            {"order": {"id": "/order_id"}},
+            {"refund": {"id": "/refund/id"}},
+            {"[]": {"...id": "/ids"}},
+            {"@when": "/refund", "invoice": {"id": "/order_id"}},
+            {"refund": {"reason": "/order/reason"}}
+        ]);
 
-        let expected_transformed_output = fs::read_to_string(&format!(
-            "{}/transformed/array_obj_2.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect(&format!(
-            "Unable to read file {}/transformed/array_obj_2.json",
-            OUTPUT_JSON_FILES_DIR
+        assert!(transform_with_report(&input, &output, &Default::default()).is_err());
+
+        let options = TransformOptions {
+            continue_on_element_error: true,
+            ..Default::default()
+        };
+        let (transformed_output, errors) =
+            transform_with_report(&input, &output, &options).unwrap();
+        assert_eq!(
+            transformed_output,
+            json!([
+                {"order": {"id": "34554543"}},
+                [{"id": "34554543"}, {"id": "7643534"}, {"id": "512342"}]
+            ])
+        );
+        assert_eq!(
+            errors.iter().map(ElementError::index).collect::<Vec<_>>(),
+            vec![1, 4]
+        );
+        assert!(errors[0].to_string().starts_with(
+            "Failed to transform the output object 1; Failed to resolve mapping value"
         ));
-        let expected_transformed_output: Value =
-            from_str(&expected_transformed_output).expect(&format!(
-                "Unable to parse file {}/transformed/array_obj_2.json",
-                OUTPUT_JSON_FILES_DIR
-            ));
+        assert_eq!(
+            errors[0]
+                .error()
+                .downcast_ref::<FieldError>()
+                .unwrap()
+                .pointer,
+            "/1/refund/id"
+        );
+        assert_eq!(
+            transform_with_options(&input, &output, &options).unwrap(),
+            transformed_output
+        );
+
+        // malformed output objects still fail the transform
+        let output = json!([{"order": {"id": "/order_id"}}, "refund"]);
+        assert!(transform_with_report(&input, &output, &options).is_err());
+    }
+
+    #[test]
+    fn transform_with_report_ok_failed_conversion() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let obj = json!({
+            "order": {"[a]": {"...x": "/ids"}, "[b]": {"...y": "/ids", "...z": "/order_id"}}
+        });
+        let output = json!([{"order": {"id": "/order_id"}}, obj]);
+        let options = TransformOptions {
+            continue_on_element_error: true,
+            ..Default::default()
+        };
+
+        let (transformed_output, errors) =
+            transform_with_report(&input, &output, &options).unwrap();
+        assert_eq!(transformed_output, json!([{"order": {"id": "34554543"}}]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].index(), 1);
+        assert!(errors[0]
+            .to_string()
+            .contains("Failed to process array convertible object"));
 
+        // `[a]` is converted before `[b]` fails, the element is left as it was before the conversion
+        let mut element = obj;
+        assert!(
+            transform_obj(&Contexts::new(&[&input]), &mut element, 1, false, &options).is_err()
+        );
+        assert_eq!(
+            element,
+            json!({
+                "order": {
+                    "[a]": {"...x": ["34554543", "7643534", "512342"]},
+                    "[b]": {"...y": ["34554543", "7643534", "512342"], "...z": "34554543"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn transform_merge_ok() {
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!([{"order": {"id": "/order_id", "ids": "/ids"}}]);
+        let base = json!([{"order": {"id": "", "source": "edi", "ids": ["1"]}}]);
+
+        let transformed_output = transform_merge(&input, &output, &base);
+        assert!(transformed_output.is_ok());
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"order": {
+                "id": "34554543",
+                "source": "edi",
+                "ids": ["34554543", "7643534", "512342"]
+            }}])
+        );
+    }
+
+    #[test]
+    fn transform_ok_root_conditions() {
+        let input = json!({"order": {"id": 1, "total": 150, "item_ids": [2, 3]}});
+        let output = json!([
+            {"@when": "/order/total >= #100", "large_order": {"id": "/order/id"}},
+            {"@when": "/order/total < #100", "small_order": {"id": "/order/id"}},
+            {"@when": "/order/id", "//note": "root array", "[]": {"...id": "/order/item_ids"}}
+        ]);
+
         let transformed_output = transform(&input, &output);
-        // println!(
-        //     "Output: {}",
-        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
-        // );
         assert!(transformed_output.is_ok());
-        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+        assert_eq!(
+            transformed_output.unwrap(),
+            json!([{"large_order": {"id": 1}}, [{"id": 2}, {"id": 3}]])
+        );
     }
 
     #[test]
-    fn transform_err_array_convertible_obj_no_spread_array_field() {
-        let output = fs::read_to_string(&format!(
-            "{}/bad_array_convertible_obj_structure.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect("Unable to read file");
-        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+    fn transform_err_duplicate_names() {
+        let input = json!({"order": {"id": 1, "total": 150, "ids": [1]}});
+        let output = json!([
+            {"order": {"id": "/order/id"}},
+            {"order": {"total": "/order/total"}},
+            {"[]": {"...id": "/order/ids"}},
+            {"[]": {"...id": "/order/ids"}}
+        ]);
 
-        let input = INPUT_JSON_FILE.lock().unwrap().clone();
         let transformed_output = transform(&input, &output);
-        // println!(
-        //     "Output: {}",
-        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
-        // );
         assert!(transformed_output.is_err());
         assert_eq!(
             transformed_output.err().unwrap().to_string(),
-            "Failed to process array convertible object; a array convertible object [order] is detected but no spread array field was found"
+            "output objects should have unique names, duplicated names: order"
+        );
+
+        let options = TransformOptions {
+            allow_duplicate_names: true,
+            ..Default::default()
+        };
+        let output = json!([{"order": {"id": "/order/id"}}, {"order": {"total": "/order/total"}}]);
+        assert_eq!(
+            transform_with_options(&input, &output, &options).unwrap(),
+            json!([{"order": {"id": 1}}, {"order": {"total": 150}}])
         );
     }
 
     #[test]
-    fn transform_err_no_array_convertible_obj_and_spread_array_field() {
-        let output = fs::read_to_string(&format!(
-            "{}/bad_array_convertible_obj_structure_2.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect("Unable to read file");
-        let output: Value = from_str(&output).expect("Unable to parse input json file to value");
+    fn transform_ok_multiple_named_roots() {
+        let input = json!({"order": {"id": 1, "total": 150}, "customer": {"id": 2}});
+        let output = json!([
+            {"order": {"id": "/order/id"}, "customer": {"id": "/customer/id"}},
+            {"totals": {"total": "/order/total"}}
+        ]);
+
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([
+                {"order": {"id": 1}, "customer": {"id": 2}},
+                {"totals": {"total": 150}}
+            ])
+        );
+
+        let output = json!([
+            {"order": {"id": "/order/id"}, "customer": {"id": "/customer/id"}},
+            {"customer": {"id": "/customer/id"}, "Order": {"id": "/order/id"}}
+        ]);
+        assert_eq!(
+            transform(&input, &output).err().unwrap().to_string(),
+            "output objects should have unique names, duplicated names: customer"
+        );
+    }
+
+    #[test]
+    fn transform_err_root_array_with_other_names() {
+        let input = json!({"ids": [1]});
+        let output = json!([{"Order": {"id": "/ids"}, "[]": {"...id": "/ids"}}]);
+
+        assert!(transform(&input, &output)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("output object marked as root array [] should have no other fields"));
+    }
+
+    #[test]
+    fn transform_ok_duplicate_names_excluded_by_condition() {
+        let input = json!({"order": {"id": 1, "total": 150}});
+        let output = json!([
+            {"@when": "/order/total > #100", "order": {"id": "/order/id", "large": "'yes'"}},
+            {"@when": "/order/total <= #100", "order": {"id": "/order/id"}}
+        ]);
+
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"order": {"id": 1, "large": "yes"}}])
+        );
+    }
 
+    #[test]
+    fn transform_err_max_array_elements() {
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_output = transform(&input, &output);
-        // println!(
-        //     "Output: {}",
-        //     to_string_pretty(&transformed_output.unwrap()).unwrap()
-        // );
-        assert!(transformed_output.is_ok());
+        let output = json!([{"[]": {"...id": "/ids"}}]);
+        let options = TransformOptions {
+            max_array_elements: Some(2),
+            ..Default::default()
+        };
+
+        let transformed_output = transform_with_options(&input, &output, &options);
+        assert!(transformed_output.is_err());
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "Failed to split object to array; the array would have 3 elements, more than the max_array_elements cap of 2"
+        );
     }
 
     #[test]
-    fn transformer_bad_output_structure() {
+    fn transform_ok_empty_as_null() {
+        let input = json!({"order": {"id": 1, "total": 50, "ids": []}});
+        let output = json!([{
+            "order": {
+                "id": "/order/id",
+                "large": {"total": {"@when": "/order/total > #100", "value": "/order/total"}},
+                "[items]": {"...id": "/order/ids"}
+            }
+        }]);
+        let options = TransformOptions {
+            empty_as_null: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            transform(&input, &output).unwrap(),
+            json!([{"order": {"id": 1, "large": {}, "items": []}}])
+        );
+        assert_eq!(
+            transform_with_options(&input, &output, &options).unwrap(),
+            json!([{"order": {"id": 1, "large": null, "items": null}}])
+        );
+    }
+
+    #[test]
+    fn transform_diagnostic_err_root_condition() {
+        let input = json!({"order": {"total": 150}});
+        let output = json!([{"@when": "/order/total ~ #100", "order": {"id": "/order/id"}}]);
+
+        let err = transform_diagnostic(&input, &output).err().unwrap();
+        assert_eq!(
+            err.message(),
+            "Bad condition format; unsupported operator ~, supported operators are == != >= <= > <"
+        );
+        assert_eq!(err.pointer(), Some("/0/@when"));
+    }
+
+    #[test]
+    fn transform_merge_ok_append_arrays() {
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_result = transform(&input, &json!({}));
-        assert!(transformed_result.is_err());
+        let output = json!([{"order": {"ids": "/ids"}}]);
+        let base = json!([{"order": {"ids": ["1"]}}]);
+        let options = TransformOptions {
+            array_merge: ArrayMergePolicy::Append,
+            ..Default::default()
+        };
 
+        let transformed_output = transform_merge_with_options(&input, &output, &base, &options);
+        assert!(transformed_output.is_ok());
         assert_eq!(
-            transformed_result.err().unwrap().to_string(),
-            "output should be in an array of object structure"
+            transformed_output.unwrap(),
+            json!([{"order": {"ids": ["1", "34554543", "7643534", "512342"]}}])
         );
     }
 
     #[test]
-    fn transformer_bad_output_array_element_structure() {
+    fn transform_merge_bad_base_structure() {
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_output = transform(&input, &json!([[]]));
+        let output = json!([{"order": {"id": "/order_id"}}]);
+        let transformed_output = transform_merge(&input, &output, &json!({}));
         assert!(transformed_output.is_err());
-
         assert_eq!(
             transformed_output.err().unwrap().to_string(),
-            "output array elements should be in object structure: []"
+            "base should be in an array of object structure"
         );
     }
 
+    #[cfg(feature = "fs")]
     #[test]
-    fn transformer_output_array_obj_element_without_name() {
-        let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let transformed_output = transform(&input, &json!([{}]));
+    fn transform_files_ok() {
+        let expected_transformed_output = fs::read_to_string(format!(
+            "{}/transformed/default.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect("Unable to read file");
+        let expected_transformed_output: Value =
+            from_str(&expected_transformed_output).expect("Unable to parse file");
+
+        let transformed_output = transform_files(
+            Path::new("./test/input.json"),
+            Path::new(&format!("{}/default.json", OUTPUT_JSON_FILES_DIR)),
+        );
+        assert!(transformed_output.is_ok());
+        assert_eq!(transformed_output.unwrap(), expected_transformed_output);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn transform_files_err_missing_file() {
+        let transformed_output = transform_files(
+            Path::new("./test/input.json"),
+            Path::new("./test/output/missing.json"),
+        );
         assert!(transformed_output.is_err());
+        assert_eq!(
+            transformed_output.err().unwrap().to_string(),
+            "Failed to read json file ./test/output/missing.json"
+        );
+    }
 
+    #[cfg(feature = "fs")]
+    #[test]
+    fn transform_files_err_bad_json() {
+        let transformed_output =
+            transform_files(Path::new("./README.md"), Path::new("./test/input.json"));
+        assert!(transformed_output.is_err());
         assert_eq!(
             transformed_output.err().unwrap().to_string(),
-            "failed to get the name of the output: {}"
+            "Failed to parse json file ./README.md"
         );
     }
 }
@@ -0,0 +1,60 @@
+use crate::options::ArrayMergePolicy;
+use serde_json::Value;
+
+// Deep merges `value` onto `base`. Objects are merged key by key, arrays are merged based on the
+// passed policy and any other value in `value` overrides the one in `base`.
+pub fn merge_values(base: &mut Value, value: Value, array_merge: ArrayMergePolicy) {
+    match (base, value) {
+        (Value::Object(base_obj), Value::Object(obj)) => {
+            for (key, v) in obj {
+                match base_obj.get_mut(&key) {
+                    Some(base_v) => merge_values(base_v, v, array_merge),
+                    None => {
+                        base_obj.insert(key, v);
+                    }
+                }
+            }
+        }
+        (Value::Array(base_array), Value::Array(array))
+            if array_merge == ArrayMergePolicy::Append =>
+        {
+            base_array.extend(array);
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_values_objects() {
+        let mut base = json!({"a": 1, "b": {"c": 2, "d": 3}});
+        merge_values(
+            &mut base,
+            json!({"b": {"c": 4}, "e": 5}),
+            ArrayMergePolicy::Replace,
+        );
+        assert_eq!(base, json!({"a": 1, "b": {"c": 4, "d": 3}, "e": 5}));
+    }
+
+    #[test]
+    fn test_merge_values_arrays() {
+        let mut base = json!({"a": [1, 2]});
+        merge_values(&mut base, json!({"a": [3]}), ArrayMergePolicy::Replace);
+        assert_eq!(base, json!({"a": [3]}));
+
+        let mut base = json!({"a": [1, 2]});
+        merge_values(&mut base, json!({"a": [3]}), ArrayMergePolicy::Append);
+        assert_eq!(base, json!({"a": [1, 2, 3]}));
+    }
+
+    #[test]
+    fn test_merge_values_type_mismatch() {
+        let mut base = json!({"a": {"b": 1}});
+        merge_values(&mut base, json!({"a": "value"}), ArrayMergePolicy::Append);
+        assert_eq!(base, json!({"a": "value"}));
+    }
+}
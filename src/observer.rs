@@ -0,0 +1,19 @@
+use serde_json::Value;
+
+/// Hooks called while an output template is transformed, e.g. for logging or metrics, see
+/// [`transform_with_observer`](crate::transform_with_observer). The output paths are the JSON
+/// pointers of the fields in their output object, example `/order/id`, and the input paths are the
+/// mappings of the fields as written, fallbacks included, example `/order/carrier || 'unknown'`.
+/// Both hooks do nothing by default.
+pub trait TransformObserver {
+    /// Called when the mapping of an output field resolves, with the resolved value.
+    fn on_resolve(&self, output_path: &str, input_path: &str, result: &Value) {
+        let _ = (output_path, input_path, result);
+    }
+
+    /// Called when the mapping of an output field can't be resolved from the input, before the
+    /// `default_on_missing` option value is used if it is set.
+    fn on_missing(&self, output_path: &str, input_path: &str) {
+        let _ = (output_path, input_path);
+    }
+}
@@ -0,0 +1,127 @@
+use serde_json::Value;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+/// Options that tweak how the output template is transformed.
+#[derive(Debug, Clone, Default)]
+pub struct TransformOptions {
+    /// Allows several output objects with the same name, otherwise duplicated names fail the
+    /// transform since they are usually copy paste mistakes.
+    pub allow_duplicate_names: bool,
+    /// How arrays are merged when the transformed output is merged onto a base object,
+    /// see [`transform_merge_with_options`](crate::transform_merge_with_options).
+    pub array_merge: ArrayMergePolicy,
+    /// Skips the output objects of the template that fail to transform instead of failing the
+    /// whole transform, so the other objects are still transformed, e.g. for templates whose roots
+    /// are optional. The errors of the skipped objects can be inspected with
+    /// [`transform_with_report`](crate::transform_with_report). Malformed output objects, e.g. with
+    /// no name, still fail the transform. The array convertible objects of an output object are
+    /// converted on a copy of it, so an object whose conversion fails partway is left untouched.
+    pub continue_on_element_error: bool,
+    /// Value used for any mapping that can't be resolved from the input, instead of failing the
+    /// transform. The field is kept in the output with this value.
+    pub default_on_missing: Option<Value>,
+    /// Fails the input parsed by [`transform_str_with_options`](crate::transform_str_with_options)
+    /// with a [`DuplicateKeysError`](crate::DuplicateKeysError) if it has duplicated keys, see
+    /// `warn_on_duplicate_input_keys` to report them without failing.
+    pub deny_duplicate_input_keys: bool,
+    /// Replaces the empty objects and empty arrays of the transformed output with null, example the
+    /// objects whose fields were all left out or the arrays of zero length spread arrays.
+    pub empty_as_null: bool,
+    /// Enables the `@now` and `@now:unix` directives, which resolve to the current time. It is
+    /// disabled by default so that transforms are deterministic. The system clock requires the
+    /// `std` feature.
+    pub enable_time: bool,
+    /// Maximum number of characters of the input objects printed in error messages, example the
+    /// object a missing field was looked up in, the longer ones are truncated with an ellipsis so
+    /// that huge inputs don't flood the logs. They are printed whole by default.
+    pub error_context_limit: Option<usize>,
+    /// Resolves mappings against pre-flattened input keys, i.g `/order/po_number` resolves
+    /// `{"order.po_number": "573832"}`. When the nested lookup fails under an object, the field name
+    /// and the rest of the mapping path are joined with `.` and looked up in that object as a single
+    /// key. The nested lookup always takes precedence over the flattened one.
+    pub flattened_input: bool,
+    /// Maximum number of objects an array convertible object can be split into, the transform fails
+    /// before allocating a larger array. It is a safety valve for untrusted inputs with huge spread
+    /// arrays.
+    pub max_array_elements: Option<usize>,
+    /// Fixed time the time directives resolve to instead of the system clock, example for
+    /// reproducible tests. Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub now: Option<SystemTime>,
+    /// How the paths that don't start with `/` are resolved, see [`RelativePathPolicy`].
+    pub relative_paths: RelativePathPolicy,
+    /// How the spread arrays of an array convertible object are combined into the objects of the
+    /// array.
+    pub spread_policy: SpreadPolicy,
+    /// Fails the mappings that look up a field in an array, e.g. `/order/shipments/tracking_number`
+    /// where `shipments` is an array, instead of implicitly collecting the field of every element.
+    /// The elements are iterated explicitly with the `*` wildcard, e.g.
+    /// `/order/shipments/*/tracking_number`, so templates tell where the arrays are.
+    pub strict_array_access: bool,
+    /// Fails the `not` function when its value is not a boolean or null, instead of negating its
+    /// truthiness.
+    pub strict_not: bool,
+    /// Fails the `pick` function when one of the picked fields is missing from the object, instead
+    /// of leaving it out.
+    pub strict_pick: bool,
+    /// Trims the trailing nulls of every spread array, so that its field is left out of the objects
+    /// past its last non-null element instead of being set to null. With [`SpreadPolicy::Pad`] the
+    /// padded tail of the shorter spread arrays is trimmed, and the array ends at the last object
+    /// with a non-null spread field. It has no effect with [`SpreadPolicy::Product`].
+    pub trim_trailing_nulls: bool,
+    /// Detects the duplicated keys of the input parsed by
+    /// [`transform_str_with_duplicate_keys`](crate::transform_str_with_duplicate_keys), which
+    /// returns their JSON pointers along with the transformed output. JSON parsers keep the last
+    /// value of a duplicated key, which silently drops the other values.
+    pub warn_on_duplicate_input_keys: bool,
+    /// Resolves the `ratio` function to `0.0` when its denominator is zero, instead of failing.
+    pub zero_ratio_on_zero_denominator: bool,
+}
+
+/// Merge semantics for arrays found at the same path in both the base object and the
+/// transformed output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayMergePolicy {
+    /// The transformed array replaces the base array.
+    #[default]
+    Replace,
+    /// The transformed array elements are appended to the base array elements.
+    Append,
+}
+
+/// How the mapping paths that don't start with `/` are resolved. A path starting with `/` is always
+/// resolved against the input root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RelativePathPolicy {
+    /// A relative path is resolved against the current `@each` element, or against the input root
+    /// outside of `@each` bodies.
+    #[default]
+    Context,
+    /// Same as `Context`, but a relative path outside of an `@each` body fails the transform
+    /// instead of being resolved against the input root, so that a missing leading `/` can't go
+    /// unnoticed. It applies to the mapping values and to the array paths of `@each`, `@groupBy`,
+    /// `@indexed`, `@filter` and `@zip`.
+    Strict,
+}
+
+/// How the spread arrays (`...`) of an array convertible object (`[]`) are combined into the objects
+/// of the array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpreadPolicy {
+    /// The spread arrays are zipped, the i-th object takes the i-th element of every spread array.
+    /// The spread arrays are expected to have the same length.
+    #[default]
+    Zip,
+    /// Same as `Zip`, but the array has the length of the longest spread array, the shorter ones are
+    /// padded with null.
+    Pad,
+    /// Same as `Zip`, but no object is generated at the positions where a spread array has a null
+    /// element, or no element. With several spread arrays, a single null at a position is enough to
+    /// skip it, even if the other spread arrays have values there.
+    SkipNull,
+    /// The cartesian product of the spread arrays, one object is generated for every combination of
+    /// their elements. The first spread array in the template key order varies the slowest, like
+    /// the outer loop of nested loops.
+    Product,
+}
@@ -0,0 +1,412 @@
+//! Path utilities shared by the transformer, they implement the output template key decorations:
+//! `[order]` marks an object to be converted into an array, `...ids` marks the array to be spread
+//! and `["a/b"]` quotes a key that contains slashes.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use anyhow::{anyhow, Error, Result};
+
+/// Cleans a key from the array conversion decorations `...` and `[]`, example `...items` -> `items`,
+/// `[order]` -> `order`. Combined decorations are all removed, example `[...items]` -> `items`, so
+/// cleaning a clean key leaves it untouched.
+/// ```
+/// use transformer_rs::path::clean_key;
+///
+/// assert_eq!(clean_key("...items").unwrap(), "items");
+/// assert_eq!(clean_key("[order]").unwrap(), "order");
+/// assert_eq!(clean_key("[...items]").unwrap(), "items");
+/// ```
+pub fn clean_key(key: &str) -> Result<&str> {
+    let mut clean_key = key;
+    loop {
+        if let Some(inner) = clean_key
+            .strip_prefix('[')
+            .and_then(|key| key.strip_suffix(']'))
+        {
+            clean_key = inner;
+        } else if let Some(spread) = clean_key.strip_prefix("...") {
+            clean_key = spread;
+        } else {
+            return Ok(clean_key);
+        }
+    }
+}
+
+/// Cleans every key of the path from the array conversion decorations `...` and `[]`, example
+/// `[order]/...items/id` -> `/order/items/id`. The cleaned keys are escaped so that the result can
+/// be used as JSON pointer, example `["a/b"]` -> `/a~1b`.
+///
+/// The empty keys of doubled, leading or trailing slashes are dropped, example `//a` -> `/a` and
+/// `/a/` -> `/a`, so `/` cleans to the empty path of the root like `""`. An empty key is written
+/// quoted, example `/a/[""]` -> `/a/`.
+/// ```
+/// use transformer_rs::path::clean_path;
+///
+/// assert_eq!(clean_path("/[order]/...items/id").unwrap(), "/order/items/id");
+/// assert_eq!(clean_path("/order/[\"a/b\"]").unwrap(), "/order/a~1b");
+/// assert_eq!(clean_path("/order//items/").unwrap(), "/order/items");
+/// ```
+pub fn clean_path(path: &str) -> Result<String> {
+    split_path(path)?
+        .into_iter()
+        .filter(|key| !key.is_empty())
+        .try_fold(String::new(), |xpath, key| {
+            let key = unquote_key(key).unwrap_or(key);
+            Ok::<String, Error>(format!("{}/{}", xpath, escape_pointer_key(clean_key(key)?)))
+        })
+}
+
+// splits path into keys, a key quoted with `["` and `"]` is kept whole so it can contain slashes
+// and dots, example `/order/["a/b"]/value` -> [`order`, `["a/b"]`, `value`], and so is a key with a
+// predicate, example `/items[sku == 'a/b']/id` -> [`items[sku == 'a/b']`, `id`]. Quoted keys are
+// returned with their quotes so that they can be told apart from keys with path markers, see
+// `unquote_key`
+pub(crate) fn split_path(path: &str) -> Result<Vec<&str>> {
+    let mut keys = vec![];
+    if path.is_empty() {
+        return Ok(keys);
+    }
+    let mut rest = path.strip_prefix('/').unwrap_or(path);
+    loop {
+        if let Some(quoted) = rest.strip_prefix("[\"") {
+            let end = quoted.find("\"]").ok_or_else(|| {
+                anyhow!(
+                    "Bad path format; quoted key should be closed with \"]: {}",
+                    path
+                )
+            })?;
+            keys.push(&rest[..end + 4]);
+            rest = &quoted[end + 2..];
+            if rest.is_empty() {
+                break;
+            }
+            rest = rest.strip_prefix('/').ok_or_else(|| {
+                anyhow!(
+                    "Bad path format; quoted key should be followed by /: {}",
+                    path
+                )
+            })?;
+        } else {
+            match find_key_end(rest) {
+                Some(end) => {
+                    keys.push(&rest[..end]);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    keys.push(rest);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(keys)
+}
+
+// returns the index of the slash that ends the first key of the path, slashes in the predicate of
+// a key are skipped, example `items[sku == 'a/b']/id` -> 19
+fn find_key_end(path: &str) -> Option<usize> {
+    scan_key(path).ok()
+}
+
+// Scans the first key of the path up to the slash that ends it, its index is returned, or the depth
+// of the brackets still open at the end of the path if no slash ends the key
+fn scan_key(path: &str) -> core::result::Result<usize, usize> {
+    let mut depth = 0;
+    let mut in_literal = false;
+    let mut escaped = false;
+    for (i, c) in path.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_literal => escaped = true,
+            '\'' if depth > 0 => in_literal = !in_literal,
+            _ if in_literal => {}
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            '/' if depth == 0 => return Ok(i),
+            _ => {}
+        }
+    }
+    Err(depth)
+}
+
+// Returns true if the path is resolved against the input root, that is if it starts with `/`, any
+// other path is relative to the current context, example `/order/id` is absolute while `id` and
+// `../id` are relative
+pub(crate) fn is_absolute_path(path: &str) -> bool {
+    path.starts_with('/')
+}
+
+// converts a path into JSON pointer without cleaning its keys, example `/[order]/["a/b"]` ->
+// `/[order]/a~1b`
+pub(crate) fn to_pointer(path: &str) -> Result<String> {
+    Ok(split_path(path)?
+        .into_iter()
+        .map(|key| format!("/{}", escape_pointer_key(unquote_key(key).unwrap_or(key))))
+        .collect())
+}
+
+// returns the literal key of a quoted key, example `["a/b"]` -> `a/b`, or None if the key is
+// not quoted
+pub(crate) fn unquote_key(key: &str) -> Option<&str> {
+    key.strip_prefix("[\"")
+        .and_then(|key| key.strip_suffix("\"]"))
+}
+
+// escapes a key to be used as JSON pointer token, example `a/b` -> `a~1b`
+pub(crate) fn escape_pointer_key(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// the key of a JSON pointer token, example `a~1b` -> `a/b`
+pub(crate) fn unescape_pointer_key(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// Returns true if the object name is wrapped in square brackets, example `[order]`.
+/// ```
+/// use transformer_rs::path::is_obj_to_be_converted_to_array;
+///
+/// assert!(is_obj_to_be_converted_to_array("[order]"));
+/// assert!(!is_obj_to_be_converted_to_array("order"));
+/// ```
+pub fn is_obj_to_be_converted_to_array(obj_name: &str) -> bool {
+    obj_name.starts_with('[') && obj_name.ends_with(']')
+}
+
+/// Returns true if the array name starts with 3 dots, example `...ids`.
+/// ```
+/// use transformer_rs::path::is_to_be_spread_array;
+///
+/// assert!(is_to_be_spread_array("...ids"));
+/// assert!(!is_to_be_spread_array("ids"));
+/// ```
+pub fn is_to_be_spread_array(array_name: &str) -> bool {
+    array_name.contains("...")
+}
+
+// Returns true if the key is a template comment that starts with 2 slashes, example `//note`
+pub(crate) fn is_comment(key: &str) -> bool {
+    key.starts_with("//")
+}
+
+/// Formats a key by concatenating the xpath and the key in the right format, example:
+/// xpath: `/order/items`, key: `id` -> `/order/items/id`. Keys containing slashes are quoted,
+/// example: xpath: `/order`, key: `a/b` -> `/order/["a/b"]`, and so are the keys that wouldn't be
+/// read back whole from the path otherwise, the ones starting like a quoted key or with an unclosed
+/// bracket, example `a[b`. A key containing `"]` can't be quoted.
+/// ```
+/// use transformer_rs::path::format_key;
+///
+/// assert_eq!(format_key("/order/items", "id"), "/order/items/id");
+/// assert_eq!(format_key("", "order"), "/order");
+/// assert_eq!(format_key("/order", "a[b"), "/order/[\"a[b\"]");
+/// ```
+pub fn format_key(xpath: &str, key: &str) -> String {
+    if needs_quotes(key) {
+        return format!("{}/[\"{}\"]", xpath, key);
+    }
+    match (xpath, key) {
+        (x, "") => x.to_string(),
+        ("", k) => {
+            format!("/{}", k)
+        }
+
+        (x, k) => {
+            format!("{}/{}", x, k)
+        }
+    }
+}
+
+// returns true if the key should be quoted to be split back whole from a path, see `split_path`
+fn needs_quotes(key: &str) -> bool {
+    key.contains('/')
+        || (!key.contains("\"]")
+            && (key.starts_with("[\"") || matches!(scan_key(key), Err(depth) if depth > 0)))
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // keys mixing plain characters with the characters of the decorations, quoted keys and
+    // predicates, so that they hit the edge cases of the path utilities
+    fn decorated_key() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop_oneof![
+                "[a-z0-9]{1,3}",
+                Just("...".to_string()),
+                Just("[".to_string()),
+                Just("]".to_string()),
+                Just("[\"".to_string()),
+                Just("\"]".to_string()),
+                Just("/".to_string()),
+                Just("'".to_string()),
+                Just("\\".to_string()),
+                Just("~".to_string()),
+                Just(".".to_string()),
+            ],
+            0..5,
+        )
+        .prop_map(|parts| parts.concat())
+    }
+
+    #[test]
+    fn test_clean_key() {
+        assert_eq!(clean_key("").unwrap(), "");
+        assert_eq!(clean_key("key").unwrap(), "key");
+        assert_eq!(clean_key("...key").unwrap(), "key");
+    }
+
+    #[test]
+    fn test_clean_path() {
+        assert_eq!(clean_path("").unwrap(), "");
+        assert_eq!(clean_path("/obj").unwrap(), "/obj");
+        assert_eq!(clean_path("/obj/obj").unwrap(), "/obj/obj");
+        assert_eq!(clean_path("obj/obj").unwrap(), "/obj/obj");
+        assert_eq!(clean_path("/[obj]/...array").unwrap(), "/obj/array");
+        assert_eq!(clean_path("/[obj]/obj/...array").unwrap(), "/obj/obj/array");
+        assert_eq!(clean_path("/[obj]/[\"a/b\"]").unwrap(), "/obj/a~1b");
+        assert_eq!(
+            clean_path("/[\"[a/b]\"]/[\"...c/d\"]").unwrap(),
+            "/a~1b/c~1d"
+        );
+        assert_eq!(clean_path("/[\"a~b\"]").unwrap(), "/a~0b");
+        assert_eq!(clean_path("/").unwrap(), "");
+        assert_eq!(clean_path("//a").unwrap(), "/a");
+        assert_eq!(clean_path("/a/").unwrap(), "/a");
+        assert_eq!(clean_path("/[a]//...b//").unwrap(), "/a/b");
+        assert_eq!(clean_path("/[\"a\"]/").unwrap(), "/a");
+        assert_eq!(clean_path("/a/[\"\"]").unwrap(), "/a/");
+        assert_eq!(clean_path("/[]").unwrap(), "/");
+    }
+
+    #[test]
+    fn test_to_pointer() {
+        assert_eq!(to_pointer("").unwrap(), "");
+        assert_eq!(to_pointer("/[obj]/...array").unwrap(), "/[obj]/...array");
+        assert_eq!(to_pointer("/obj/[\"a/b\"]").unwrap(), "/obj/a~1b");
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert!(split_path("").unwrap().is_empty());
+        assert_eq!(split_path("/").unwrap(), vec![""]);
+        assert_eq!(split_path("/a/b").unwrap(), vec!["a", "b"]);
+        assert_eq!(split_path("a/b").unwrap(), vec!["a", "b"]);
+        assert_eq!(
+            split_path("/order/[\"a/b\"]/value").unwrap(),
+            vec!["order", "[\"a/b\"]", "value"]
+        );
+        assert_eq!(split_path("/[\"a.b\"]").unwrap(), vec!["[\"a.b\"]"]);
+        assert_eq!(
+            split_path("/items[sku == 'a/]b']/id").unwrap(),
+            vec!["items[sku == 'a/]b']", "id"]
+        );
+        assert_eq!(
+            split_path("/[\"a/b\"/c").err().unwrap().to_string(),
+            "Bad path format; quoted key should be closed with \"]: /[\"a/b\"/c"
+        );
+        assert_eq!(
+            split_path("/[\"a/b\"]c").err().unwrap().to_string(),
+            "Bad path format; quoted key should be followed by /: /[\"a/b\"]c"
+        );
+    }
+
+    #[test]
+    fn test_unquote_key() {
+        assert_eq!(unquote_key("[\"a/b\"]"), Some("a/b"));
+        assert_eq!(unquote_key("[\"\"]"), Some(""));
+        assert_eq!(unquote_key("a/b"), None);
+        assert_eq!(unquote_key("[a/b]"), None);
+    }
+
+    #[test]
+    fn test_is_obj_to_be_converted_to_array() {
+        assert_eq!(is_obj_to_be_converted_to_array("[obj]"), true);
+        assert_eq!(is_obj_to_be_converted_to_array("obj"), false);
+        assert_eq!(is_obj_to_be_converted_to_array("[obj"), false);
+        assert_eq!(is_obj_to_be_converted_to_array("obj]"), false);
+    }
+
+    #[test]
+    fn test_is_to_be_spread_array() {
+        assert_eq!(is_to_be_spread_array("...array"), true);
+        assert_eq!(is_to_be_spread_array("array"), false);
+    }
+
+    #[test]
+    fn test_is_absolute_path() {
+        assert!(is_absolute_path("/order/id"));
+        assert!(is_absolute_path("/"));
+        assert!(!is_absolute_path("id"));
+        assert!(!is_absolute_path("../id"));
+        assert!(!is_absolute_path(""));
+    }
+
+    #[test]
+    fn test_is_comment() {
+        assert!(is_comment("//note"));
+        assert!(!is_comment("/note"));
+        assert!(!is_comment("note"));
+    }
+
+    #[test]
+    fn test_format_key() {
+        assert_eq!(format_key("", ""), "");
+        assert_eq!(format_key("", "key"), "/key");
+        assert_eq!(format_key("/xpath/xpath", ""), "/xpath/xpath");
+        assert_eq!(format_key("/xpath", "key"), "/xpath/key");
+        assert_eq!(format_key("/xpath", "a/b"), "/xpath/[\"a/b\"]");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_path_utils_dont_panic(path in "\\PC{0,24}", key in decorated_key()) {
+            let _ = clean_path(&path);
+            let _ = to_pointer(&path);
+            let _ = split_path(&path);
+            let _ = clean_key(&key);
+            let _ = clean_path(&format_key(&path, &key));
+        }
+
+        #[test]
+        fn prop_clean_key_is_idempotent(key in decorated_key()) {
+            let clean = clean_key(&key).unwrap();
+            prop_assert_eq!(clean_key(clean).unwrap(), clean);
+        }
+
+        #[test]
+        fn prop_format_key_then_clean_path_round_trips(
+            keys in prop::collection::vec(decorated_key(), 0..5)
+        ) {
+            // a key with `"]` and a slash can't be quoted
+            prop_assume!(keys.iter().all(|key| !key.contains("\"]")));
+            let path = keys.iter().fold(String::new(), |xpath, key| format_key(&xpath, key));
+            let keys: Vec<&String> = keys.iter().filter(|key| !key.is_empty()).collect();
+
+            let pointer: String = keys
+                .iter()
+                .map(|key| format!("/{}", escape_pointer_key(key)))
+                .collect();
+            prop_assert_eq!(to_pointer(&path).unwrap(), pointer);
+            let clean: String = keys
+                .iter()
+                .map(|key| format!("/{}", escape_pointer_key(clean_key(key).unwrap())))
+                .collect();
+            prop_assert_eq!(clean_path(&path).unwrap(), clean);
+        }
+
+        #[test]
+        fn prop_clean_path_ignores_empty_keys(keys in prop::collection::vec("[a-z.\\[\\]]{1,6}", 0..5)) {
+            let path = keys.iter().fold(String::new(), |xpath, key| format_key(&xpath, key));
+            let doubled = format!("/{}//", path.replace('/', "//"));
+            prop_assert_eq!(clean_path(&doubled).unwrap(), clean_path(&path).unwrap());
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::options::TransformOptions;
+use crate::transformer::{resolve_alternatives, Contexts};
+use anyhow::{anyhow, Result};
+use core::cell::RefCell;
+use serde_json::Value;
+
+/// Resolves the mappings of the output fields in place of the built-in resolution, e.g. to look up
+/// some of the fields in a database, see [`transform_with_resolver`](crate::transform_with_resolver).
+/// The directives, the conditions and the array paths of the template are still handled by the
+/// engine, only the string leaves are resolved with it.
+///
+/// The mapping is resolved against a [`LeafContext`] rather than the bare input value, since a
+/// mapping isn't always resolved against the input root alone: in `@each` bodies the relative
+/// mappings resolve against the current element and `../` mappings reach out to the enclosing
+/// elements, layered inputs are tried in order, and the options of the transformation, e.g.
+/// `flattened_input` or `strict_array_access`, change how a path is looked up. The context carries
+/// the input roots and the elements of the enclosing `@each` iterations, the options, and the value
+/// the mapping is resolved against, [`LeafContext::input`], which is all a custom resolver usually
+/// needs. The rest is there for [`DefaultLeafResolver`], so that falling back on it resolves a
+/// mapping the same as [`transform`](crate::transform) does, and fails the transformation on a
+/// malformed mapping the same way.
+pub trait LeafResolver {
+    /// Resolves the `mapping` of the output field at `output_path`, the JSON pointer of the field in
+    /// its output object, example `/order/id`. The `context` holds the values the mapping is
+    /// resolved against, see [`LeafContext::input`]. An error is handled as a mapping that can't be
+    /// resolved from the input.
+    fn resolve(&self, context: &LeafContext, mapping: &str, output_path: &str) -> Result<Value>;
+}
+
+/// The values an output field mapping is resolved against, the input roots and the elements of the
+/// enclosing `@each` iterations, along with the options of the transformation.
+pub struct LeafContext<'a> {
+    contexts: &'a Contexts<'a>,
+    options: &'a TransformOptions,
+    input: &'a Value,
+    // the error of a malformed mapping met by the default resolution, it fails the transformation
+    // whatever the resolver returns, the same as with the built-in resolution
+    template_error: RefCell<Option<anyhow::Error>>,
+}
+
+impl<'a> LeafContext<'a> {
+    pub(crate) fn new(
+        contexts: &'a Contexts<'a>,
+        options: &'a TransformOptions,
+        input: &'a Value,
+    ) -> Self {
+        LeafContext {
+            contexts,
+            options,
+            input,
+            template_error: RefCell::new(None),
+        }
+    }
+
+    pub(crate) fn take_template_error(&self) -> Option<anyhow::Error> {
+        self.template_error.borrow_mut().take()
+    }
+
+    /// The value the mapping is resolved against: the current `@each` element for relative mappings
+    /// in `@each` bodies, the input root otherwise.
+    pub fn input(&self) -> &'a Value {
+        self.input
+    }
+}
+
+/// The built-in resolution of the mappings against the input, for custom resolvers to fall back on.
+/// It resolves a mapping the same as [`transform`](crate::transform) does, including the mappings
+/// reaching out of the current `@each` element, e.g. `../id`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultLeafResolver;
+
+impl LeafResolver for DefaultLeafResolver {
+    fn resolve(&self, context: &LeafContext, mapping: &str, _output_path: &str) -> Result<Value> {
+        resolve_alternatives(context.contexts, mapping, context.options).unwrap_or_else(|err| {
+            let message = anyhow!("{}", err);
+            *context.template_error.borrow_mut() = Some(err);
+            Err(message)
+        })
+    }
+}
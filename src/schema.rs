@@ -0,0 +1,62 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+// Validates the transformed output against a JSON Schema, every validation error is reported along
+// with the JSON pointer of the failing value in the transformed output
+pub fn validate(value: &Value, schema: &Value) -> Result<()> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|err| anyhow!("Bad schema format; {}", err))?;
+    let errors: Vec<String> = validator
+        .iter_errors(value)
+        .map(|err| {
+            let pointer = err.instance_path.to_string();
+            let pointer = if pointer.is_empty() { "/" } else { &pointer };
+            format!("{}: {}", pointer, err)
+        })
+        .collect();
+    if !errors.is_empty() {
+        bail!(
+            "Failed to validate the transformed output against the schema; {}",
+            errors.join("; ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        let schema = json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {"order": {"type": "object", "required": ["id"]}}
+            }
+        });
+
+        assert!(validate(&json!([{"order": {"id": "1"}}]), &schema).is_ok());
+        assert_eq!(
+            validate(&json!([{"order": {}}, {"order": 1}]), &schema)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to validate the transformed output against the schema; /0/order: \"id\" is a required property; /1/order: 1 is not of type \"object\""
+        );
+        assert_eq!(
+            validate(&json!({}), &json!({"type": "array"}))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to validate the transformed output against the schema; /: {} is not of type \"array\""
+        );
+        assert!(validate(&json!([]), &json!({"type": 1}))
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Bad schema format; "));
+    }
+}
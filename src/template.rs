@@ -0,0 +1,563 @@
+use crate::condition::{is_included, WHEN_KEY};
+use crate::diagnostic::{prefix_field_error, BatchError, FieldError};
+use crate::merge::merge_values;
+use crate::options::ArrayMergePolicy;
+use crate::options::TransformOptions;
+use crate::path::{
+    clean_key, clean_path, format_key, is_obj_to_be_converted_to_array, split_path, to_pointer,
+};
+use crate::transformer::{
+    convert_array_convertible_objs, index_pointers, process_array_convertible_objs,
+    take_spread_fields, traverse_mut, traverse_mut_in, Contexts, IndexFields, SplitObjects,
+};
+use crate::{strip_meta, transform_values};
+use alloc::collections::LinkedList;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{to_value, Value};
+
+// smallest batch that is transformed in parallel, smaller ones aren't worth the threads overhead
+#[cfg(feature = "rayon")]
+const PARALLEL_BATCH_MIN_LEN: usize = 1024;
+
+/// An output template serialized once and reused for transforming many inputs.
+/// # Example
+/// ```
+/// use serde_json::json;
+/// use transformer_rs::Template;
+///
+/// let template = Template::new(&json!([{"order": {"id": "/order_id"}}])).unwrap();
+///
+/// let transformed_output = template.transform(&json!({"order_id": 1})).unwrap();
+/// assert_eq!(transformed_output, json!([{"order": {"id": 1}}]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Template {
+    output: Value,
+    options: TransformOptions,
+}
+
+impl Template {
+    /// Serializes the output template, it should be in an array of object structure.
+    pub fn new<O: Serialize>(output: &O) -> Result<Self> {
+        Self::with_options(output, TransformOptions::default())
+    }
+
+    /// Same as [`Template::new`], the template transforms with the passed options.
+    pub fn with_options<O: Serialize>(output: &O, options: TransformOptions) -> Result<Self> {
+        let output =
+            to_value(output).context("Failed to serialize output template to JSON value")?;
+        let elements = output
+            .as_array()
+            .ok_or_else(|| anyhow!("output should be in an array of object structure"))?;
+        if let Some(element) = elements.iter().find(|element| !element.is_object()) {
+            bail!(
+                "output array elements should be in object structure: {}",
+                element
+            );
+        }
+        Ok(Template { output, options })
+    }
+
+    /// The output template.
+    pub fn output(&self) -> &Value {
+        &self.output
+    }
+
+    /// The options the template transforms with.
+    pub fn options(&self) -> &TransformOptions {
+        &self.options
+    }
+
+    /// Transforms the input the same way as [`transform_with_options`](crate::transform_with_options).
+    pub fn transform<I: Serialize>(&self, input: &I) -> Result<Value> {
+        let input = to_value(input).context("Failed to serialize input to JSON value")?;
+        transform_values(&input, self.output.clone(), &self.options)
+    }
+
+    /// Transforms each of the inputs, the transformed outputs are in the inputs order. With the
+    /// `rayon` feature, batches of at least 1024 inputs are transformed in parallel. Fails with a
+    /// [`BatchError`](crate::BatchError) that carries the index of the first input that failed to
+    /// transform.
+    /// # Example
+    /// ```
+    /// use serde_json::json;
+    /// use transformer_rs::Template;
+    ///
+    /// let template = Template::new(&json!([{"order": {"id": "/order_id"}}])).unwrap();
+    ///
+    /// let transformed_outputs = template
+    ///     .transform_each(&[json!({"order_id": 1}), json!({"order_id": 2})])
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     transformed_outputs,
+    ///     vec![json!([{"order": {"id": 1}}]), json!([{"order": {"id": 2}}])]
+    /// );
+    /// ```
+    pub fn transform_each<I: Serialize + Sync>(
+        &self,
+        inputs: &[I],
+    ) -> core::result::Result<Vec<Value>, BatchError> {
+        #[cfg(feature = "rayon")]
+        if inputs.len() >= PARALLEL_BATCH_MIN_LEN {
+            use rayon::prelude::*;
+            // every input is transformed so that the reported failure is the first one, as when
+            // the batch is transformed sequentially
+            let results: Vec<Result<Value>> = inputs
+                .par_iter()
+                .map(|input| self.transform(input))
+                .collect();
+            return results
+                .into_iter()
+                .enumerate()
+                .map(|(index, result)| result.map_err(|err| BatchError::new(index, err)))
+                .collect();
+        }
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                self.transform(input)
+                    .map_err(|err| BatchError::new(index, err))
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the objects the array convertible object at `pointer` is split into,
+    /// instead of materializing the whole array. `pointer` is the JSON pointer of the array
+    /// convertible object in the output template, example `/0/order/[sub_order]`. The spread arrays
+    /// are resolved upfront, then each object is transformed on demand, with the i-th element of
+    /// the spread arrays, so the iterator yields a result per object. The iterator is empty when
+    /// the object is left out by a `@when` condition.
+    /// # Example
+    /// ```
+    /// use serde_json::json;
+    /// use transformer_rs::Template;
+    ///
+    /// let input = json!({"order": {"id": 1, "item_ids": [10, 20, 30]}});
+    /// let template = Template::new(&json!([{
+    ///     "[items]": {"order_id": "/order/id", "...item_id": "/order/item_ids"}
+    /// }]))
+    /// .unwrap();
+    ///
+    /// let mut items = template.split_iter(&input, "/0/[items]").unwrap();
+    /// assert_eq!(
+    ///     items.next().unwrap().unwrap(),
+    ///     json!({"order_id": 1, "item_id": 10})
+    /// );
+    /// assert_eq!(items.len(), 2);
+    /// ```
+    pub fn split_iter<I: Serialize>(&self, input: &I, pointer: &str) -> Result<SplitIter> {
+        let input = to_value(input).context("Failed to serialize input to JSON value")?;
+        let keys: Vec<String> = pointer
+            .strip_prefix('/')
+            .ok_or_else(|| {
+                anyhow!(
+                    "Bad pointer format; pointer should start with /: {}",
+                    pointer
+                )
+            })?
+            .split('/')
+            .map(|key| key.replace("~1", "/").replace("~0", "~"))
+            .collect();
+        let (element_index, keys) = keys
+            .split_first()
+            .ok_or_else(|| anyhow!("Bad pointer format; missing output element: {}", pointer))?;
+        let element_index: usize = element_index.parse().map_err(|_| {
+            anyhow!(
+                "Bad pointer format; invalid output element index: {}",
+                pointer
+            )
+        })?;
+        let (key, parent_keys) = keys.split_last().ok_or_else(|| {
+            anyhow!(
+                "Bad pointer format; pointer should point to an array convertible object: {}",
+                pointer
+            )
+        })?;
+        if !is_obj_to_be_converted_to_array(key) {
+            bail!("{} is not an array convertible object", pointer);
+        }
+        if parent_keys
+            .iter()
+            .any(|key| is_obj_to_be_converted_to_array(key))
+        {
+            bail!(
+                "{} is nested in an array convertible object, only the outer one can be iterated",
+                pointer
+            );
+        }
+        let mut element = self
+            .output
+            .get(element_index)
+            .ok_or_else(|| anyhow!("couldn't find output element {}", element_index))?
+            .clone();
+        let parent_path = parent_keys
+            .iter()
+            .fold(String::new(), |xpath, key| format_key(&xpath, key));
+        // the spread fields are resolved upfront with the rest of the output element, the other
+        // fields of the object are the template of each object it is split into
+        let template = match element.pointer_mut(&to_pointer(&format_key(&parent_path, key))?) {
+            Some(Value::Object(template)) => template,
+            Some(_) => bail!("{} is not an array convertible object", pointer),
+            None => bail!("couldn't find {} in the output template", pointer),
+        };
+        let skeleton = take_spread_fields(template);
+        let template = Value::Object(core::mem::replace(template, skeleton));
+
+        strip_meta(&mut element);
+        let split = self.split_objects(&input, element, element_index, &parent_path, key)?;
+        Ok(SplitIter {
+            input,
+            template,
+            xpath: parent_path,
+            key: key.to_string(),
+            element_index,
+            options: self.options.clone(),
+            split,
+        })
+    }
+
+    // Resolves the spread arrays of the array convertible object in the output element, the other
+    // fields of the object having been taken out of it, and takes them out of the object to be split
+    // lazily.
+    fn split_objects(
+        &self,
+        input: &Value,
+        mut element: Value,
+        element_index: usize,
+        parent_path: &str,
+        key: &str,
+    ) -> Result<SplitObjects> {
+        if !is_included(&Contexts::new(&[input]), &element, &self.options)
+            .map_err(|err| FieldError::new(format!("/{}/{}", element_index, WHEN_KEY), err))?
+        {
+            return Ok(SplitObjects::empty());
+        }
+        traverse_mut(input, &mut element, "", "", &self.options)
+            .map_err(|err| prefix_field_error(err, &format!("/{}", element_index)))?;
+
+        // the object is processed the same way as `process_array_convertible_objs` does, except
+        // for the final split, so its spread arrays are left on the stack to be split lazily
+        let parent_obj = match element.pointer_mut(&clean_path(parent_path)?) {
+            Some(Value::Object(parent_obj)) => parent_obj,
+            // the object or one of its parents is left out by a `@when` condition
+            _ => return Ok(SplitObjects::empty()),
+        };
+        let obj = match parent_obj.remove(key) {
+            Some(obj) => obj,
+            None => return Ok(SplitObjects::empty()),
+        };
+        parent_obj.insert(clean_key(key)?.to_string(), obj.clone());
+        let obj_template_path = format_key(parent_path, key);
+        let mut visited = LinkedList::new();
+        let mut array_lens = LinkedList::new();
+        for (sub_key, v) in obj.as_object().into_iter().flatten() {
+            process_array_convertible_objs(
+                v,
+                &mut element,
+                &obj_template_path,
+                sub_key,
+                &mut visited,
+                &mut array_lens,
+                &[],
+                &self.options,
+            )?;
+        }
+
+        let obj_path = clean_path(&obj_template_path)?;
+        let paths_to_spread_arrays: Vec<String> = visited
+            .iter()
+            .filter_map(|path| path.strip_prefix(&format!("{}/", obj_path)))
+            .map(|path| format!("/{}", path))
+            .collect();
+        // the number of objects is the length of the last spread array, as for `transform`
+        let array_len = paths_to_spread_arrays
+            .iter()
+            .rev()
+            .find_map(|path| element.pointer(&format!("{}{}", obj_path, path))?.as_array())
+            .map(Vec::len)
+            .ok_or_else(|| anyhow!("Failed to process array convertible object; a array convertible object {} is detected but no spread array field was found", key))?;
+        let obj = element
+            .pointer_mut(&obj_path)
+            .map(Value::take)
+            .unwrap_or_default();
+        SplitObjects::new(obj, paths_to_spread_arrays, array_len, &self.options)
+    }
+}
+
+/// Iterator over the objects an array convertible object is split into, returned by
+/// [`Template::split_iter`]. Each object is transformed when it is iterated, with the elements of
+/// the spread arrays at its position.
+#[derive(Debug, Clone)]
+pub struct SplitIter {
+    input: Value,
+    // the array convertible object template without its spread fields
+    template: Value,
+    xpath: String,
+    key: String,
+    element_index: usize,
+    options: TransformOptions,
+    split: SplitObjects,
+}
+
+impl SplitIter {
+    // transforms the object template, its nested array convertible objects included, and fills in
+    // the spread fields taken out of it and the `@index` fields of the object
+    fn transform_next(&self) -> Result<Value> {
+        let mut obj = self.template.clone();
+        let index_fields = IndexFields::new(split_path(&format_key(&self.xpath, &self.key))?.len());
+        let roots = [&self.input];
+        traverse_mut_in(
+            &Contexts::new(&roots).with_index_fields(&index_fields),
+            &mut obj,
+            &self.xpath,
+            &self.key,
+            &self.options,
+        )?;
+        let index_fields = index_fields.fields();
+        convert_array_convertible_objs(&mut obj, &index_fields, &self.options)?;
+        for pointer in index_pointers(&index_fields, &[])? {
+            if let Some(field) = obj.pointer_mut(&pointer) {
+                *field = Value::from(self.split.position());
+            }
+        }
+        merge_values(
+            &mut obj,
+            self.split.parent_obj().clone(),
+            ArrayMergePolicy::Replace,
+        );
+        Ok(obj)
+    }
+}
+
+impl Iterator for SplitIter {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Result<Value>> {
+        if self.split.len() == 0 {
+            return None;
+        }
+        match self.transform_next() {
+            Ok(obj) => self.split.split_next(obj).map(Ok),
+            Err(err) => {
+                self.split.next();
+                Some(Err(prefix_field_error(
+                    err,
+                    &format!("/{}", self.element_index),
+                )))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.split.size_hint()
+    }
+}
+
+impl ExactSizeIterator for SplitIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpreadPolicy;
+    use serde_json::json;
+
+    #[test]
+    fn test_new_err() {
+        assert_eq!(
+            Template::new(&json!({})).err().unwrap().to_string(),
+            "output should be in an array of object structure"
+        );
+        assert_eq!(
+            Template::new(&json!([{}, 1])).err().unwrap().to_string(),
+            "output array elements should be in object structure: 1"
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let template = Template::new(&json!([{"[]": {"...id": "/ids"}}])).unwrap();
+
+        assert_eq!(
+            template.transform(&json!({"ids": [1, 2]})).unwrap(),
+            json!([[{"id": 1}, {"id": 2}]])
+        );
+        assert_eq!(
+            template.transform(&json!({"ids": [3]})).unwrap(),
+            json!([[{"id": 3}]])
+        );
+    }
+
+    #[test]
+    fn test_transform_each() {
+        let template = Template::new(&json!([{"order": {"id": "/order_id"}}])).unwrap();
+        // large enough to be transformed in parallel with the rayon feature
+        let inputs: Vec<Value> = (0..2000).map(|i| json!({ "order_id": i })).collect();
+
+        let transformed_outputs = template.transform_each(&inputs).unwrap();
+        assert_eq!(transformed_outputs.len(), 2000);
+        for (i, transformed_output) in transformed_outputs.iter().enumerate() {
+            assert_eq!(transformed_output, &json!([{"order": {"id": i}}]));
+        }
+
+        assert!(template.transform_each::<Value>(&[]).unwrap().is_empty());
+
+        let mut inputs = inputs;
+        inputs[1200] = json!({});
+        inputs[1500] = json!({});
+        let err = template.transform_each(&inputs).err().unwrap();
+        assert_eq!(err.index(), 1200);
+        assert_eq!(
+            err.to_string(),
+            "Failed to transform the input 1200 of the batch; Failed to resolve mapping value; couldn't find field name order_id in the obj {}"
+        );
+        assert_eq!(
+            err.error().downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/order/id"
+        );
+        assert_eq!(
+            template
+                .transform_each(&[json!({"order_id": 1}), json!({}), json!({"order_id": 3})])
+                .err()
+                .unwrap()
+                .index(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_split_iter() {
+        let input = json!({
+            "order": {
+                "id": 1,
+                "shipments": [
+                    {"tracking": "a", "sku": ["x", "y"]},
+                    {"tracking": "b", "sku": ["z"]}
+                ]
+            }
+        });
+        let output = json!([{
+            "order": {
+                "id": "/order/id",
+                "[shipments]": {
+                    "order_id": "/order/id",
+                    "...tracking": "/order/shipments/tracking",
+                    "[items]": {"...sku": "/order/shipments/sku"}
+                }
+            }
+        }]);
+        let template = Template::new(&output).unwrap();
+
+        let shipments = template
+            .split_iter(&input, "/0/order/[shipments]")
+            .unwrap()
+            .collect::<Result<Vec<Value>>>()
+            .unwrap();
+        let transformed_output = template.transform(&input).unwrap();
+        assert_eq!(
+            Value::Array(shipments),
+            transformed_output[0]["order"]["shipments"]
+        );
+
+        assert_eq!(
+            template
+                .split_iter(&input, "/0/order/[shipments]/[items]")
+                .err()
+                .unwrap()
+                .to_string(),
+            "/0/order/[shipments]/[items] is nested in an array convertible object, only the outer one can be iterated"
+        );
+        assert_eq!(
+            template
+                .split_iter(&input, "/0/order")
+                .err()
+                .unwrap()
+                .to_string(),
+            "/0/order is not an array convertible object"
+        );
+        assert_eq!(
+            template
+                .split_iter(&input, "/1/[order]")
+                .err()
+                .unwrap()
+                .to_string(),
+            "couldn't find output element 1"
+        );
+    }
+
+    #[test]
+    fn test_split_iter_product() {
+        let input = json!({"ids": [1, 2], "skus": ["a", "b"]});
+        let options = TransformOptions {
+            spread_policy: SpreadPolicy::Product,
+            ..Default::default()
+        };
+        let template = Template::with_options(
+            &json!([{"[]": {"...id": "/ids", "...sku": "/skus"}}]),
+            options,
+        )
+        .unwrap();
+
+        let objs = template.split_iter(&input, "/0/[]").unwrap();
+        assert_eq!(objs.len(), 4);
+        assert_eq!(
+            objs.collect::<Result<Vec<Value>>>().unwrap(),
+            vec![
+                json!({"id": 1, "sku": "a"}),
+                json!({"id": 1, "sku": "b"}),
+                json!({"id": 2, "sku": "a"}),
+                json!({"id": 2, "sku": "b"})
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_iter_excluded() {
+        let template = Template::new(&json!([{
+            "order": {"[items]": {"@when": "/ids", "...id": "/ids"}}
+        }]))
+        .unwrap();
+
+        let mut objs = template
+            .split_iter(&json!({"ids": []}), "/0/order/[items]")
+            .unwrap();
+        assert!(objs.next().is_none());
+    }
+
+    #[test]
+    fn test_split_iter_transforms_each_object() {
+        let template = Template::new(&json!([{
+            "[items]": {"...id": "/ids", "position": "@index", "name": "/name"}
+        }]))
+        .unwrap();
+
+        let objs = template
+            .split_iter(&json!({"ids": [1, 2], "name": "a"}), "/0/[items]")
+            .unwrap();
+        assert_eq!(
+            objs.collect::<Result<Vec<Value>>>().unwrap(),
+            vec![
+                json!({"id": 1, "position": 0, "name": "a"}),
+                json!({"id": 2, "position": 1, "name": "a"})
+            ]
+        );
+
+        // the fields other than the spread ones are only resolved when an object is iterated
+        let mut objs = template
+            .split_iter(&json!({"ids": [1, 2]}), "/0/[items]")
+            .unwrap();
+        assert_eq!(objs.len(), 2);
+        let err = objs.next().unwrap().err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/0/[items]/name"
+        );
+        assert_eq!(objs.len(), 1);
+    }
+}
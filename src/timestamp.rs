@@ -0,0 +1,160 @@
+use crate::options::TransformOptions;
+use alloc::{format, string::String};
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+/// Directive that resolves to the current time as RFC3339 UTC timestamp, example
+/// `2024-01-02T03:04:05Z`.
+pub const NOW_DIRECTIVE: &str = "@now";
+/// Directive that resolves to the current time as seconds since the Unix epoch.
+pub const NOW_UNIX_DIRECTIVE: &str = "@now:unix";
+
+// Resolves a time directive, `None` is returned if the value is not a time directive. The current
+// time is taken from the `now` option if set, the system clock otherwise.
+pub fn resolve_time_directive(value: &str, options: &TransformOptions) -> Result<Option<Value>> {
+    if value != NOW_DIRECTIVE && value != NOW_UNIX_DIRECTIVE {
+        return Ok(None);
+    }
+    if !options.enable_time {
+        bail!(
+            "Failed to resolve {}; time directives require the enable_time option",
+            value
+        );
+    }
+    let secs = current_secs(value, options)?;
+    if value == NOW_UNIX_DIRECTIVE {
+        Ok(Some(Value::from(secs)))
+    } else {
+        Ok(Some(Value::from(format_rfc3339(secs))))
+    }
+}
+
+// returns the seconds since the Unix epoch of the `now` option if set, of the system clock otherwise
+#[cfg(feature = "std")]
+fn current_secs(value: &str, options: &TransformOptions) -> Result<u64> {
+    use anyhow::anyhow;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    Ok(options
+        .now
+        .unwrap_or_else(SystemTime::now)
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| {
+            anyhow!(
+                "Failed to resolve {}; the time is before the Unix epoch",
+                value
+            )
+        })?
+        .as_secs())
+}
+
+// there is no clock without std
+#[cfg(not(feature = "std"))]
+fn current_secs(value: &str, _: &TransformOptions) -> Result<u64> {
+    bail!(
+        "Failed to resolve {}; time directives require the std feature",
+        value
+    )
+}
+
+// formats seconds since the Unix epoch as RFC3339 UTC timestamp, example 0 -> `1970-01-01T00:00:00Z`
+fn format_rfc3339(secs: u64) -> String {
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // converts days since the epoch into a civil date, the year starts in March so the leap day
+    // is the last day of the year
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_rfc3339(1_704_164_645), "2024-01-02T03:04:05Z");
+        assert_eq!(format_rfc3339(4_102_444_799), "2099-12-31T23:59:59Z");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_resolve_time_directive() {
+        use serde_json::json;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let options = TransformOptions {
+            enable_time: true,
+            now: Some(UNIX_EPOCH + Duration::from_secs(1_704_164_645)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve_time_directive("@now", &options).unwrap(),
+            Some(json!("2024-01-02T03:04:05Z"))
+        );
+        assert_eq!(
+            resolve_time_directive("@now:unix", &options).unwrap(),
+            Some(json!(1_704_164_645))
+        );
+        assert_eq!(
+            resolve_time_directive("/order/created_at", &options).unwrap(),
+            None
+        );
+        assert!(resolve_time_directive(
+            "@now",
+            &TransformOptions {
+                enable_time: true,
+                ..Default::default()
+            }
+        )
+        .unwrap()
+        .is_some());
+        assert_eq!(
+            resolve_time_directive("@now", &Default::default())
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to resolve @now; time directives require the enable_time option"
+        );
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn test_resolve_time_directive_no_std() {
+        let options = TransformOptions {
+            enable_time: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve_time_directive("@now", &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to resolve @now; time directives require the std feature"
+        );
+    }
+}
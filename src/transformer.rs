@@ -1,502 +1,5025 @@
-use anyhow::{anyhow, bail, Error, Result};
-use serde_json::{to_string_pretty, to_value, Value};
-use std::collections::LinkedList;
-
-// cleans key string from `...` or `[]`, example `...items` -> `item, `[order]` ->  `order`
-fn clean_key(key: &str) -> Result<&str> {
-    let mut clean_key = key;
-
-    if is_obj_to_be_converted_to_array(key) {
-        clean_key = key
-            .strip_prefix('[')
-            .map(|s| {
-                s.strip_suffix(']').unwrap_or(key)
-            })
-            .ok_or_else(|| anyhow!(
-                "Bad key format; array convertible objects notation should like \"[example_key]\": {}",
-                key
-            ))?;
+use crate::arithmetic::{apply, Operator};
+use crate::condition::{evaluate_condition, is_comparison, is_equality, is_included, WHEN_KEY};
+use crate::diagnostic::FieldError;
+use crate::function::{parse_function_call, resolve_function};
+use crate::jsonpath::resolve_jsonpath;
+use crate::observer::TransformObserver;
+use crate::options::{RelativePathPolicy, SpreadPolicy, TransformOptions};
+use crate::path::{
+    clean_key, clean_path, escape_pointer_key, format_key, is_absolute_path, is_comment,
+    is_obj_to_be_converted_to_array, is_to_be_spread_array, split_path, to_pointer,
+    unescape_pointer_key, unquote_key,
+};
+use crate::resolver::{LeafContext, LeafResolver};
+use crate::timestamp::{resolve_time_directive, NOW_DIRECTIVE, NOW_UNIX_DIRECTIVE};
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, LinkedList};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use anyhow::{anyhow, bail, Result};
+use core::cell::{Cell, RefCell};
+use core::convert::TryFrom;
+use serde_json::{from_str, to_string_pretty, to_value, Map, Value};
+
+// trailing path token that copies the whole input subtree into the output, example `/product/**`
+const PASSTHROUGH_TOKEN: &str = "**";
+// path token that resolves the rest of the path against every element of an array, or every value
+// of an object, example `/order/shipments/*/tracking_number`
+const WILDCARD_TOKEN: &str = "*";
+// leading path token that moves one context out, example `../id` inside an `@each` body
+const PARENT_CONTEXT_TOKEN: &str = "..";
+// prefix of a mapping that reads a field of the current `@each` element, example `@sibling:id`
+const SIBLING_PREFIX: &str = "@sibling:";
+// mapping of a field of an array convertible object that resolves to the index of each object the
+// array convertible object is split into
+const INDEX_DIRECTIVE: &str = "@index";
+// leaf directive resolving to how deeply nested its field is in the transformed output object
+const DEPTH_DIRECTIVE: &str = "@depth";
+// start of a mapping interpolated in an output key, example `order_${/order/id}`, it ends with `}`
+const INTERPOLATION_START: &str = "${";
+/// Key of an output object that groups the elements of the array the key maps to by the value of
+/// its `key` field into an object of arrays.
+pub const GROUP_BY_KEY: &str = "@groupBy";
+/// Key of an output object that merges the objects of the array the key maps to by the value of
+/// their `key` field, the numeric fields listed in `sum` are added up.
+pub const MERGE_BY_KEY: &str = "@mergeBy";
+/// Key of an output object that resolves to an object with one key per element of the array the
+/// key maps to, the keys are the `prefix` field followed by the element index.
+pub const INDEXED_KEY: &str = "@indexed";
+/// Key of an output object that resolves to the elements of the array the key maps to that satisfy
+/// its `where` condition, projected on its `select` mapping.
+pub const FILTER_KEY: &str = "@filter";
+/// Key of an output object whose value maps field names to input arrays, the object resolves to the
+/// array of the objects built from the elements of the arrays at the same position.
+pub const ZIP_KEY: &str = "@zip";
+/// Key of an output object holding the object it resolves to when none of the mappings of its other
+/// fields can be found in the input, example when the input subtree they map to is missing.
+pub const DEFAULT_OBJECT_KEY: &str = "@defaultObject";
+/// Key of an output object whose other fields are resolved once per element of the array the
+/// key maps to, the object resolves to the array of the results.
+pub const EACH_KEY: &str = "@each";
+/// Key of an output object whose other fields are resolved as many times as the count the key maps
+/// to, with `@index` resolving to the iteration index, the object resolves to the array of the
+/// results.
+pub const RANGE_KEY: &str = "@range";
+/// Key of an output object that resolves to one of its other fields depending on the JSON type of
+/// the value the key maps to, the fields are named after the types: `null`, `boolean`, `number`,
+/// `string`, `array` and `object`.
+pub const TYPESWITCH_KEY: &str = "@typeswitch";
+// the branch names of a `@typeswitch` object, as returned by `type_name`
+const TYPESWITCH_BRANCHES: [&str; 6] = ["null", "boolean", "number", "string", "array", "object"];
+/// Key of an output object that would order its keys, it is not supported: the keys of the
+/// transformed objects are always sorted.
+pub const ORDER_KEY: &str = "@order";
+// the keys of the output objects that resolve to another value than an object with their fields
+const DIRECTIVE_KEYS: [&str; 9] = [
+    DEFAULT_OBJECT_KEY,
+    EACH_KEY,
+    RANGE_KEY,
+    GROUP_BY_KEY,
+    MERGE_BY_KEY,
+    INDEXED_KEY,
+    FILTER_KEY,
+    ZIP_KEY,
+    TYPESWITCH_KEY,
+];
+
+// The values the mappings are resolved against: the input roots, tried in order of precedence,
+// followed by the elements of the enclosing `@each` iterations, the innermost last. The observer, if
+// any, is notified of the resolution of every output field, and the resolver, if any, resolves the
+// mappings of the output fields in place of the built-in resolution.
+#[derive(Clone)]
+pub struct Contexts<'a> {
+    roots: &'a [&'a Value],
+    elements: Vec<&'a Value>,
+    observer: Option<&'a dyn TransformObserver>,
+    resolver: Option<&'a dyn LeafResolver>,
+    // inside a `@range` body, the number of template path tokens of the body and the iteration
+    // index the `@index` fields of the body resolve to
+    range_index: Option<(usize, usize)>,
+    // the `@index` fields of the array convertible objects, filled in once the objects are split
+    index_fields: Option<&'a IndexFields>,
+    // the enclosing `@each` iterations since the output the index fields are relative to, as the
+    // number of template path tokens of the `@each` object and the iteration index
+    each_indexes: Vec<(usize, usize)>,
+    // inside an `@each` body, where the innermost element is the current `@each` element that
+    // `@sibling:` reads
+    in_each: bool,
+    // the number of enclosing `@each` and `@range` iterations, each one nests its body one array
+    // deeper in the output
+    iterations: usize,
+    // inside a `@defaultObject` body, the mappings of the body that missed
+    misses: Option<&'a Misses>,
+}
+
+impl<'a> Contexts<'a> {
+    pub fn new(roots: &'a [&'a Value]) -> Self {
+        Contexts {
+            roots,
+            elements: vec![],
+            observer: None,
+            resolver: None,
+            range_index: None,
+            index_fields: None,
+            each_indexes: vec![],
+            in_each: false,
+            iterations: 0,
+            misses: None,
+        }
+    }
+
+    pub fn with_observer(mut self, observer: &'a dyn TransformObserver) -> Self {
+        self.observer = Some(observer);
+        self
     }
-    if is_to_be_spread_array(key) {
-        clean_key = key.strip_prefix("...").unwrap_or(key);
+
+    pub fn with_resolver(mut self, resolver: &'a dyn LeafResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
     }
-    Ok(clean_key)
-}
 
-// cleans path from `...` or `[]`, example `[order]/...items/id` -> `order/items/id`
-fn clean_path(path: &str) -> Result<String> {
-    if path.is_empty() {
-        return Ok(String::default());
+    pub fn with_index_fields(mut self, index_fields: &'a IndexFields) -> Self {
+        self.index_fields = Some(index_fields);
+        self.each_indexes.clear();
+        self
     }
-    let mut result = path.split('/').into_iter().collect::<Vec<&str>>();
-    if result[0].is_empty() {
-        result = result.drain(1..).collect();
+
+    pub fn with_misses(mut self, misses: &'a Misses) -> Self {
+        self.misses = Some(misses);
+        self
     }
-    let mut result = result.into_iter().try_fold("".to_string(), |xpath, key| {
-        let mut cleaned_key = key;
-        if is_obj_to_be_converted_to_array(key) || is_to_be_spread_array(key) {
-            cleaned_key = clean_key(key)?
-        }
 
-        Ok::<String, Error>(format!("{}/{}", xpath, cleaned_key))
-    })?;
-    if result.is_empty() {
-        result = clean_key(path)?.to_string();
+    // enters the body of an `@each` iteration over the element
+    pub fn push(&mut self, element: &'a Value) {
+        self.elements.push(element);
     }
-    Ok(result)
-}
 
-// Returns true if the object name wrapped in square brackets, example `[order]`
-fn is_obj_to_be_converted_to_array(obj_name: &str) -> bool {
-    obj_name.starts_with('[') && obj_name.ends_with(']')
+    pub fn pop(&mut self) {
+        self.elements.pop();
+    }
+
+    // the depth of the innermost context, 0 for the input roots
+    pub fn depth(&self) -> usize {
+        self.elements.len()
+    }
+
+    // the current `@each` element, the first input root outside of `@each` bodies
+    pub fn innermost(&self) -> Option<&'a Value> {
+        self.elements.last().or_else(|| self.roots.first()).copied()
+    }
+
+    // the value a mapping is resolved against by the resolver, the current `@each` element for a
+    // relative mapping, the first input root otherwise
+    fn resolver_input(&self, mapping: &str) -> &'a Value {
+        let input = if is_absolute_path(mapping) {
+            self.roots.first()
+        } else {
+            self.elements.last().or_else(|| self.roots.first())
+        };
+        input.copied().unwrap_or(&Value::Null)
+    }
+
+    // the values of the context at the depth, the input roots at depth 0
+    pub fn get(&self, depth: usize) -> Option<&[&'a Value]> {
+        match depth {
+            0 => Some(self.roots),
+            depth => self.elements.get(depth - 1).map(core::slice::from_ref),
+        }
+    }
 }
 
-// Returns true if the array name starts with 3 dots, example `...ids`
-fn is_to_be_spread_array(array_name: &str) -> bool {
-    array_name.contains("...")
+// The `@index` fields of the array convertible objects of an output, recorded while the output is
+// traversed so that they are filled in with the index of their object once it is split, rather than
+// resolving to a placeholder value that an input value could be equal to. A field is recorded as
+// the tokens of its output path, relative to the output traversed from the template path of `base`
+// tokens.
+pub struct IndexFields {
+    base: usize,
+    fields: RefCell<Vec<Vec<String>>>,
 }
 
-// format keys by concatenating xpath and the key in the right format, example:
-// xpath: "/order/items", key = "id" -> "/order/items/id"
-fn format_key(xpath: &str, key: &str) -> String {
-    match (xpath, key) {
-        (x, "") => x.to_string(),
-        ("", k) => {
-            format!("/{}", k)
+impl IndexFields {
+    pub fn new(base: usize) -> Self {
+        IndexFields {
+            base,
+            fields: RefCell::new(vec![]),
         }
+    }
+
+    // the number of recorded fields
+    pub fn len(&self) -> usize {
+        self.fields.borrow().len()
+    }
+
+    // forgets the fields recorded past the first `len` ones, the ones of a discarded traversal
+    pub fn truncate(&self, len: usize) {
+        self.fields.borrow_mut().truncate(len);
+    }
+
+    // the recorded fields, as the tokens of their output path
+    pub fn fields(&self) -> Vec<Vec<String>> {
+        self.fields.borrow().clone()
+    }
+}
+
+// The mappings of a `@defaultObject` body that read the input, counted while the body is traversed
+// along with how many of them missed, to tell whether the whole body missed without traversing it
+// twice. Without the `default_on_missing` option the error of the first miss is kept rather than
+// failing the traversal, the body fails with it unless the whole body missed.
+#[derive(Default)]
+pub struct Misses {
+    total: Cell<usize>,
+    missing: Cell<usize>,
+    first_error: RefCell<Option<anyhow::Error>>,
+}
 
-        (x, k) => {
-            format!("{}/{}", x, k)
+impl Misses {
+    // counts a mapping of the body, along with whether it missed
+    fn record(&self, missed: bool) {
+        self.total.set(self.total.get() + 1);
+        if missed {
+            self.missing.set(self.missing.get() + 1);
         }
     }
+
+    // keeps the error of a miss, unless an earlier one is kept
+    fn defer(&self, err: anyhow::Error) {
+        self.first_error.borrow_mut().get_or_insert(err);
+    }
+
+    // returns true if the body has mappings and they all missed
+    fn all_missed(&self) -> bool {
+        self.total.get() > 0 && self.missing.get() == self.total.get()
+    }
+}
+
+// The contexts a mapping is resolved against, either the `Contexts` themselves or the input roots
+// alone, example `&[&input]`, which are the contexts of a single input outside of `@each` bodies.
+pub trait AsContexts {
+    fn as_contexts(&self) -> Cow<'_, Contexts<'_>>;
+}
+
+impl AsContexts for Contexts<'_> {
+    fn as_contexts(&self) -> Cow<'_, Contexts<'_>> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<const N: usize> AsContexts for [&Value; N] {
+    fn as_contexts(&self) -> Cow<'_, Contexts<'_>> {
+        Cow::Owned(Contexts::new(self))
+    }
 }
 
 // Treats input which is type of serde Value as tree. It uses depth first search algorithm for traversal
 // It resolve the mapping value of each of the nodes and modifies it in place.
-pub fn traverse_mut(input: &Value, output: &mut Value, xpath: &str, key: &str) -> Result<()> {
-    match output {
-        Value::Object(ref mut tree) => {
-            for (sub_key, mut v) in tree.iter_mut() {
-                traverse_mut(input, &mut v, &format_key(xpath, key), sub_key)?;
+pub fn traverse_mut(
+    input: &Value,
+    output: &mut Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<()> {
+    traverse_mut_in(&Contexts::new(&[input]), output, xpath, key, options)
+}
+
+// Same as `traverse_mut`, the mappings are resolved against the passed contexts, the input roots
+// followed by the elements of the enclosing `@each` iterations, the innermost last. The objects are
+// walked depth first with an explicit stack instead of recursion, so that deeply nested templates
+// can't overflow the call stack; the fields are visited in the same order as a recursive walk.
+pub fn traverse_mut_in(
+    contexts: &Contexts,
+    output: &mut Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<()> {
+    let mut stack = vec![(output, xpath.to_string(), key.to_string())];
+    while let Some((output, xpath, key)) = stack.pop() {
+        // each element of an array is a mapping of its own, example `["/geo/lat", "/geo/lng"]`
+        if let Value::Array(elements) = output {
+            let array_xpath = format_key(&xpath, &key);
+            for (i, v) in elements.iter_mut().enumerate().rev() {
+                stack.push((v, array_xpath.clone(), i.to_string()));
             }
-            Ok(())
+            continue;
         }
-        _ => {
-            let output_field_value = output
-                .as_str()
-                .ok_or_else(|| {
-                    anyhow!(
-                        "Traversing output object failed; output object field should be string: {}",
-                        output
-                    )
-                })?
-                .to_owned();
-            // check for hard coded values
-            if output_field_value.starts_with('\'') && output_field_value.ends_with('\'') {
-                *output = to_value(output_field_value.replace('\'', ""))?;
-                return Ok(());
+        if !output.is_object() {
+            resolve_field(contexts, output, &xpath, &key, options)?;
+            continue;
+        }
+        if let Some(resolved) = resolve_directive(contexts, output, &xpath, &key, options)? {
+            *output = resolved;
+            continue;
+        }
+        if let Value::Object(tree) = output {
+            let obj_xpath = format_key(&xpath, &key);
+            interpolate_keys(contexts, tree, &obj_xpath, options)?;
+            // pushed in reverse so that the first field is popped first
+            for (sub_key, v) in tree.iter_mut().rev() {
+                stack.push((v, obj_xpath.clone(), sub_key.clone()));
             }
-            let mut path_tokens: LinkedList<&str> = output_field_value
-                .split('/')
-                .into_iter()
-                .collect::<Vec<&str>>()
-                .drain(1..) // gets rid of the unwanted "" from split
-                .into_iter()
-                .collect();
-            *output = resolve_output_field_value(&mut path_tokens, input)?;
-            Ok(())
         }
     }
+    Ok(())
 }
 
-// Takes mapping value. i.g "/order/shipments/items/quantity" and resolves it from the input object
-// and returns the value.
-pub fn resolve_output_field_value(
-    path_tokens: &mut LinkedList<&str>,
-    input: &Value,
-) -> Result<Value> {
-    let field_name = match path_tokens.pop_front() {
-        None => {
-            return Ok(input.clone());
-        }
-        Some(field_name) => field_name,
+// Prepares an output object for the traversal: comments are removed, so their values are never
+// treated as mappings, and so are the fields whose `@when` condition is false. If the object has an
+// `@each`, `@range`, `@groupBy`, `@mergeBy` or `@indexed` key, the value it resolves to is returned since its
+// fields are templates of the array elements rather than fields to traverse, and so is the
+// traversed branch of a `@typeswitch` object, or the traversed body of a `@defaultObject` object.
+fn resolve_directive(
+    contexts: &Contexts,
+    output: &mut Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Option<Value>> {
+    let tree = match output {
+        Value::Object(tree) => tree,
+        _ => return Ok(None),
     };
-
-    match input {
-        Value::Array(array_values) => {
-            let mut result_array = vec![];
-            for element in array_values.iter() {
-                let value = element.get(&field_name).ok_or(anyhow!(
-                    "Failed to resolve mapping value; couldn't find field name {} in the obj {}",
-                    &field_name,
-                    to_string_pretty(&element)?
-                ))?;
-                if value.is_array() {
-                    result_array.extend(value.as_array().unwrap());
-                } else {
-                    result_array.push(value);
-                }
+    tree.retain(|sub_key, _| !is_comment(sub_key));
+    // objects whose `@when` condition is false are removed along with their key, the condition of
+    // the current object was already evaluated by its parent
+    tree.remove(WHEN_KEY);
+    if tree.contains_key(ORDER_KEY) {
+        return Err(field_error(
+            &format_key(xpath, key),
+            ORDER_KEY,
+            anyhow!(
+                "Bad {} format; ordering the keys of an object is not supported, the keys of the \
+                 transformed objects are always sorted",
+                ORDER_KEY
+            ),
+        ));
+    }
+    // the rest of a `@defaultObject` object is traversed first, the directives it has included
+    if let Some(default) = tree.remove(DEFAULT_OBJECT_KEY) {
+        let body = Value::Object(core::mem::take(tree));
+        return resolve_default_object(contexts, &default, body, xpath, key, options).map(Some);
+    }
+    // the rest of an `@each` object is the template of each element of the iterated array
+    if let Some(each) = tree.remove(EACH_KEY) {
+        let body = Value::Object(core::mem::take(tree));
+        return iterate_each(contexts, &each, &body, xpath, key, options).map(Some);
+    }
+    if let Some(count) = tree.remove(RANGE_KEY) {
+        let body = Value::Object(core::mem::take(tree));
+        return iterate_range(contexts, &count, &body, xpath, key, options).map(Some);
+    }
+    if let Some(group_by) = tree.remove(GROUP_BY_KEY) {
+        return group_elements(contexts, &group_by, tree, xpath, key, options).map(Some);
+    }
+    if let Some(merge_by) = tree.remove(MERGE_BY_KEY) {
+        return merge_elements(contexts, &merge_by, tree, xpath, key, options).map(Some);
+    }
+    if let Some(indexed) = tree.remove(INDEXED_KEY) {
+        return index_elements(contexts, &indexed, tree, xpath, key, options).map(Some);
+    }
+    if let Some(filter) = tree.remove(FILTER_KEY) {
+        return filter_elements(contexts, &filter, tree, xpath, key, options).map(Some);
+    }
+    if let Some(zip) = tree.remove(ZIP_KEY) {
+        return zip_arrays(contexts, &zip, tree, xpath, key, options).map(Some);
+    }
+    // the selected branch of a `@typeswitch` object is traversed in place of the object
+    if let Some(discriminator) = tree.remove(TYPESWITCH_KEY) {
+        let branch = type_branch(contexts, &discriminator, tree, xpath, key, options)?;
+        let mut branch = tree.remove(branch).unwrap_or_default();
+        traverse_mut_in(contexts, &mut branch, xpath, key, options)?;
+        return Ok(Some(branch));
+    }
+    let mut excluded = vec![];
+    for (sub_key, v) in tree.iter() {
+        match is_included(contexts, v, options) {
+            Ok(true) => {}
+            Ok(false) => excluded.push(sub_key.clone()),
+            Err(err) => {
+                let sub_xpath = format_key(&format_key(xpath, key), sub_key);
+                return Err(field_error(&sub_xpath, WHEN_KEY, err));
             }
-            resolve_output_field_value(path_tokens, &to_value(result_array)?)
         }
-        Value::Object(obj_value) => {
-            return match obj_value.get(&field_name.to_owned()) {
-                None => bail!(
-                    "Failed to resolve mapping value; couldn't find field name {} in the obj {}",
-                    &field_name,
-                    to_string_pretty(&obj_value)?
+    }
+    for sub_key in excluded {
+        tree.remove(&sub_key);
+    }
+    Ok(None)
+}
+
+// Replaces the keys of an output object that interpolate mappings with `${...}` with their
+// resolved names, example `order_${/order/id}` -> `order_1`, before the fields are traversed. A
+// key resolved to the name of another key of the object is an error, whether the other key is
+// interpolated or not.
+fn interpolate_keys(
+    contexts: &Contexts,
+    tree: &mut Map<String, Value>,
+    obj_xpath: &str,
+    options: &TransformOptions,
+) -> Result<()> {
+    let keys: Vec<String> = tree
+        .keys()
+        .filter(|key| key.contains(INTERPOLATION_START))
+        .cloned()
+        .collect();
+    let mut interpolated = vec![];
+    for key in keys {
+        let name = interpolate_key(contexts, &key, options)
+            .map_err(|err| field_error(obj_xpath, &key, err))?;
+        let value = tree.remove(&key).unwrap_or_default();
+        interpolated.push((key, name, value));
+    }
+    for (key, name, value) in interpolated {
+        if tree.contains_key(&name) {
+            return Err(field_error(
+                obj_xpath,
+                &key,
+                anyhow!(
+                    "Bad key format; the interpolated key {} collides with another key {} of the object",
+                    key,
+                    name
                 ),
-                Some(field_value) => resolve_output_field_value(path_tokens, field_value),
-            };
+            ));
         }
-        _ => Ok(input.clone()),
+        tree.insert(name, value);
     }
+    Ok(())
 }
 
-// it traverse the transformed output and convert objects into arrays wherever found.
-pub fn process_array_convertible_objs(
-    input: &Value,
+// resolves every `${...}` mapping of an output key, the values should be strings, numbers or bools
+fn interpolate_key(contexts: &Contexts, key: &str, options: &TransformOptions) -> Result<String> {
+    let mut name = String::with_capacity(key.len());
+    let mut rest = key;
+    while let Some(start) = rest.find(INTERPOLATION_START) {
+        name.push_str(&rest[..start]);
+        rest = &rest[start + INTERPOLATION_START.len()..];
+        let end = find_interpolation_end(rest).ok_or_else(|| {
+            anyhow!(
+                "Bad key format; {} is not closed with }} in the key {}",
+                INTERPOLATION_START,
+                key
+            )
+        })?;
+        match resolve_alternative(contexts, rest[..end].trim(), options)?? {
+            Value::String(value) => name.push_str(&value),
+            value @ (Value::Number(_) | Value::Bool(_)) => name.push_str(&value.to_string()),
+            value => bail!(
+                "Failed to resolve the key {}; {} should resolve to a string, a number or a bool: {}",
+                key,
+                rest[..end].trim(),
+                value
+            ),
+        }
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+// returns the index of the `}` closing an interpolated mapping, except the ones in hard coded values
+fn find_interpolation_end(mapping: &str) -> Option<usize> {
+    let mut in_literal = false;
+    let mut escaped = false;
+    for (i, c) in mapping.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_literal => escaped = true,
+            '\'' => in_literal = !in_literal,
+            '}' if !in_literal => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Resolves the body of a `@defaultObject` object, the default object is returned when every mapping
+// of the body that reads the input misses, the hard coded values and the directives aside. When
+// only some of them miss, they fail as usual or take the `default_on_missing` value. The misses of
+// a nested body count for the enclosing body as well. The malformed mappings fail either way.
+fn resolve_default_object(
+    contexts: &Contexts,
+    default: &Value,
+    mut body: Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    if !default.is_object() {
+        return Err(field_error(
+            &format_key(xpath, key),
+            DEFAULT_OBJECT_KEY,
+            anyhow!(
+                "Bad {} format; it should be an object: {}",
+                DEFAULT_OBJECT_KEY,
+                default
+            ),
+        ));
+    }
+    let misses = Misses::default();
+    // the `@index` fields recorded by the body are forgotten if the default object is used
+    let recorded = contexts.index_fields.map_or(0, IndexFields::len);
+    let body_contexts = contexts.clone().with_misses(&misses);
+    traverse_mut_in(&body_contexts, &mut body, xpath, key, options)?;
+    let first_error = misses.first_error.borrow_mut().take();
+    if let Some(enclosing) = contexts.misses {
+        enclosing
+            .total
+            .set(enclosing.total.get() + misses.total.get());
+        enclosing
+            .missing
+            .set(enclosing.missing.get() + misses.missing.get());
+    }
+    if misses.all_missed() {
+        if let Some(index_fields) = contexts.index_fields {
+            index_fields.truncate(recorded);
+        }
+        return Ok(default.clone());
+    }
+    match (first_error, contexts.misses) {
+        (Some(err), Some(enclosing)) => {
+            enclosing.defer(err);
+            Ok(body)
+        }
+        (Some(err), None) => Err(err),
+        (None, _) => Ok(body),
+    }
+}
+
+// returns true if the output field value has an alternative read from the input, rather than only
+// hard coded values and time directives
+fn reads_input(output_field_value: &str) -> bool {
+    split_alternatives(output_field_value)
+        .into_iter()
+        .any(|alternative| {
+            unquote_literal(alternative).is_none()
+                && ![NOW_DIRECTIVE, NOW_UNIX_DIRECTIVE].contains(&alternative)
+        })
+}
+
+// resolves the mapping of an output field in place, errors carry the JSON pointer of the field
+fn resolve_field(
+    contexts: &Contexts,
     output: &mut Value,
     xpath: &str,
     key: &str,
-    visited: &mut LinkedList<String>,
-    array_lens: &mut LinkedList<usize>,
+    options: &TransformOptions,
 ) -> Result<()> {
-    match input {
-        Value::Object(ref tree) => {
-            if is_obj_to_be_converted_to_array(key) {
-                visited.push_back(format_key(&clean_path(xpath)?, clean_key(key)?));
-                let parent_obj = if xpath.is_empty() {
-                    output.as_object_mut().ok_or_else(|| {
-                        anyhow!(
-                        "Failed to process array convertible object; output expected to be object"
-                    )
-                    })?
-                } else {
-                    output
-                        .pointer_mut(&clean_path(xpath)?)
-                        .ok_or_else(||anyhow!("Failed to process array convertible object; failed for find parent object"))?
-                        .as_object_mut()
-                        .ok_or_else(|| anyhow!("Failed to process array convertible object; output expected to be object"))?
-                };
+    let resolve = || {
+        let output_field_value = output.as_str().ok_or_else(|| {
+            anyhow!(
+                "Traversing output object failed; output object field should be string: {}",
+                output
+            )
+        })?;
+        if output_field_value.trim() == INDEX_DIRECTIVE {
+            check_index_directive(xpath, contexts.range_index.is_some())?;
+            return resolve_index(contexts, xpath, key);
+        }
+        if output_field_value.trim() == DEPTH_DIRECTIVE {
+            return output_depth(xpath, key, contexts.iterations).map(Value::from);
+        }
+        resolve_mapping(contexts, output_field_value, xpath, key, options)
+    };
+    match resolve() {
+        Ok(value) => {
+            *output = value;
+            Ok(())
+        }
+        Err(err) => Err(FieldError::new(to_pointer(&format_key(xpath, key))?, err).into()),
+    }
+}
 
-                parent_obj.insert(
-                    clean_key(key)?.to_string(),
-                    parent_obj.get(key).ok_or_else(||anyhow!("Failed to process array convertible object; couldn't find field name {} in {:#?}", &key, &parent_obj))?.clone(),
-                );
-                parent_obj.remove(key).ok_or_else(|| anyhow!("Failed to process array convertible object; couldn't find field name {} in {:#?}", &key, &parent_obj))?;
-            }
+// fails an `@index` field outside of an array convertible object or a `@range` body, where there is
+// no index to fill
+fn check_index_directive(xpath: &str, in_range: bool) -> Result<()> {
+    if !in_range && !is_in_array_convertible_obj(xpath)? {
+        bail!(
+            "Bad {} format; it can only be used in an array convertible object or a {} body",
+            INDEX_DIRECTIVE,
+            RANGE_KEY
+        );
+    }
+    Ok(())
+}
 
-            for (sub_key, v) in tree.iter() {
-                process_array_convertible_objs(
-                    v,
-                    output,
-                    &format_key(xpath, key),
-                    sub_key,
-                    visited,
-                    array_lens,
-                )?;
-            }
+// Resolves an `@index` field. In a `@range` body, outside of the array convertible objects of the
+// body, it is the iteration index. Otherwise the index of the array convertible object isn't known
+// until the object is split, so the field is recorded in the index fields of the contexts and is
+// null until then.
+fn resolve_index(contexts: &Contexts, xpath: &str, key: &str) -> Result<Value> {
+    let path = format_key(xpath, key);
+    let tokens = split_path(&path)?;
+    if let Some((body, index)) = contexts.range_index {
+        if !tokens
+            .iter()
+            .skip(body)
+            .any(|token| is_obj_to_be_converted_to_array(token))
+        {
+            return Ok(Value::from(index));
+        }
+    }
+    if let Some(index_fields) = contexts.index_fields {
+        let mut tokens: Vec<String> = tokens.into_iter().map(String::from).collect();
+        // the inner iterations are inserted first, so the positions of the outer ones still hold
+        for &(position, index) in contexts.each_indexes.iter().rev() {
+            tokens.insert(position.min(tokens.len()), index.to_string());
+        }
+        let field = tokens.split_off(index_fields.base.min(tokens.len()));
+        index_fields.fields.borrow_mut().push(field);
+    }
+    Ok(Value::Null)
+}
 
-            // start array splitting
-            if is_obj_to_be_converted_to_array(key) {
-                split_obj_to_array(
-                    output,
-                    array_lens.pop_back().ok_or_else(|| anyhow!("Failed to process array convertible object; a array convertible object {} is detected but no spread array field was found", &key))?,
-                    visited,
-                    &clean_path(&format_key(xpath, key))?,
-                    clean_key(key)?,
-                )?
-            }
+// The JSON pointers of the `@index` fields of an array convertible object relative to the object,
+// whose output path tokens are `obj_tokens`. The fields of its nested array convertible objects are
+// left out, they are filled in when the nested objects are split.
+pub(crate) fn index_pointers(
+    index_fields: &[Vec<String>],
+    obj_tokens: &[&str],
+) -> Result<Vec<String>> {
+    index_fields
+        .iter()
+        .filter(|field| {
+            field.len() > obj_tokens.len()
+                && field
+                    .iter()
+                    .zip(obj_tokens)
+                    .all(|(token, obj_token)| token == obj_token)
+        })
+        .map(|field| &field[obj_tokens.len()..])
+        .filter(|tokens| {
+            !tokens
+                .iter()
+                .any(|token| is_obj_to_be_converted_to_array(token))
+        })
+        .map(|tokens| clean_path(&format!("/{}", tokens.join("/"))))
+        .collect()
+}
+
+// Resolves the array of an `@each` object and resolves the body template once per element, with
+// the element as the innermost context. A missing optional array, resolved to null, has no elements.
+fn iterate_each(
+    contexts: &Contexts,
+    each: &Value,
+    body: &Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let resolve = || {
+        let path = each.as_str().ok_or_else(|| {
+            anyhow!(
+                "Bad {} format; it should be a mapping path: {}",
+                EACH_KEY,
+                each
+            )
+        })?;
+        check_relative_path(contexts, path, options)?;
+        match resolve_path(contexts, path, options)?? {
+            Value::Array(elements) => Ok(elements),
+            Value::Null => Ok(vec![]),
+            value => bail!(
+                "Failed to resolve {}; {} should resolve to an array: {}",
+                EACH_KEY,
+                path,
+                truncate_context(value.to_string(), options)
+            ),
         }
-        _ => {
-            if is_to_be_spread_array(key) {
-                let parent_obj = output
-                    .pointer_mut(&clean_path(xpath)?)
-                    .ok_or_else(|| anyhow!("Failed to process array convertible object; unable to find the parent obj path of the array {}", &key))?
-                    .as_object_mut()
-                    .ok_or_else(|| anyhow!("Failed to process array convertible object; the parent obj of the spread array {} is not an object type", &key))?;
-                parent_obj.insert(
-                    clean_key(key)?.to_string(),
-                    parent_obj.get(key).ok_or_else(||anyhow!("Failed to process array convertible object; couldn't find {} in {:#?} ", &key, &parent_obj))?.clone(),
-                );
-                parent_obj.remove(key).ok_or_else(|| {
-                    anyhow!(
-                    "Failed to process array convertible object; failed to remove {} from {:#?}",
-                    &key,
-                    &parent_obj
-                )
-                })?;
-                if input.is_array() {
-                    array_lens.push_back(
-                        input
-                            .as_array()
-                            .ok_or_else(|| {
-                                anyhow!(
-                    "Failed to process array convertible object; failed to remove {} from {:#?}",
-                    &key,
-                    &parent_obj
+    };
+    let elements = resolve().map_err(|err| field_error(&format_key(xpath, key), EACH_KEY, err))?;
+
+    let each_tokens = split_path(&format_key(xpath, key))?.len();
+    let mut element_contexts = contexts.clone();
+    element_contexts.iterations += 1;
+    element_contexts.in_each = true;
+    let mut results = vec![];
+    for (i, element) in elements.iter().enumerate() {
+        element_contexts.push(element);
+        element_contexts.each_indexes.push((each_tokens, i));
+        let mut result = body.clone();
+        traverse_mut_in(&element_contexts, &mut result, xpath, key, options)?;
+        element_contexts.each_indexes.pop();
+        element_contexts.pop();
+        results.push(result);
+    }
+    Ok(Value::Array(results))
+}
+
+// Returns the depth of an output field in its transformed output object, the number of keys and
+// array indexes from the object to the field, example 4 for `/order/items/0/depth`. The keys are the
+// ones of the field template path, an array convertible object adds the index of its split, and so
+// does each of the enclosing `@each` and `@range` iterations. The name of a root array marker is not
+// a key of the output.
+fn output_depth(xpath: &str, key: &str, iterations: usize) -> Result<usize> {
+    let path = format_key(xpath, key);
+    let keys = split_path(&path)?;
+    let split_objs = keys
+        .iter()
+        .filter(|key| is_obj_to_be_converted_to_array(key))
+        .count();
+    let root_array_marker = usize::from(keys.first() == Some(&"[]"));
+    Ok(keys.len() + split_objs + iterations - root_array_marker)
+}
+
+// Resolves the count of a `@range` object, a number or a mapping such as `count(/ids)`, and resolves
+// the body template once per index from 0 to the count, the `@index` fields of the body resolve to
+// the index. The count is capped by the `max_array_elements` option.
+fn iterate_range(
+    contexts: &Contexts,
+    count: &Value,
+    body: &Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let resolve = || {
+        let value = match count {
+            Value::String(mapping) => resolve_alternative(contexts, mapping, options)??,
+            count => count.clone(),
+        };
+        let count = value
+            .as_u64()
+            .and_then(|count| usize::try_from(count).ok())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Failed to resolve {}; {} should resolve to a non-negative integer: {}",
+                    RANGE_KEY,
+                    count,
+                    value
                 )
-                            })?
-                            .len(),
-                    );
+            })?;
+        match options.max_array_elements {
+            Some(max) if count > max => bail!(
+                "Failed to resolve {}; the array would have {} elements, more than the max_array_elements cap of {}",
+                RANGE_KEY,
+                count,
+                max
+            ),
+            _ => Ok(count),
+        }
+    };
+    let count = resolve().map_err(|err| field_error(&format_key(xpath, key), RANGE_KEY, err))?;
+
+    // the index fields of the array convertible objects of the body are relative to the body, which
+    // is converted on its own
+    let body_tokens = split_path(&format_key(xpath, key))?.len();
+    let index_fields = IndexFields::new(body_tokens);
+    let mut range_contexts = contexts.clone().with_index_fields(&index_fields);
+    range_contexts.iterations += 1;
+    let mut results = vec![];
+    for index in 0..count {
+        range_contexts.range_index = Some((body_tokens, index));
+        index_fields.truncate(0);
+        let mut result = body.clone();
+        traverse_mut_in(&range_contexts, &mut result, xpath, key, options)?;
+        convert_array_convertible_objs(&mut result, &index_fields.fields(), options)
+            .map_err(|err| field_error(xpath, key, err))?;
+        results.push(result);
+    }
+    Ok(Value::Array(results))
+}
+
+// Resolves the array of a `@groupBy` object and groups its elements by the value of the `key`
+// mapping into an object of arrays. The `value` mapping picks what is collected of each element,
+// the whole element if it is not set. Keys of different types with the same name, such as `10`
+// and `"10"`, are an error rather than one group. Both mappings are resolved with the element as
+// the innermost context, example `{"@groupBy": "/orders", "key": "region", "value": "amount"}` ->
+// `{"eu": [5], "us": [10, 20]}`
+fn group_elements(
+    contexts: &Contexts,
+    group_by: &Value,
+    fields: &Map<String, Value>,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let obj_xpath = format_key(xpath, key);
+    check_fields(GROUP_BY_KEY, fields, &["key", "value"], &obj_xpath)?;
+    let key_path = mapping_field(GROUP_BY_KEY, fields, "key", &obj_xpath)?.ok_or_else(|| {
+        field_error(
+            xpath,
+            key,
+            anyhow!(
+                "Bad {} format; missing the key field to group by",
+                GROUP_BY_KEY
+            ),
+        )
+    })?;
+    let value_path = mapping_field(GROUP_BY_KEY, fields, "value", &obj_xpath)?;
+    let elements = resolve_array(contexts, GROUP_BY_KEY, group_by, &obj_xpath, options)?
+        .ok_or_else(|| {
+            field_error(
+                &obj_xpath,
+                GROUP_BY_KEY,
+                anyhow!(
+                    "Failed to resolve {}; {} should resolve to an array: null",
+                    GROUP_BY_KEY,
+                    group_by.as_str().unwrap_or_default()
+                ),
+            )
+        })?;
+
+    let mut groups = BTreeMap::new();
+    let mut element_contexts = contexts.clone();
+    element_contexts.in_each = false;
+    for element in elements.iter() {
+        element_contexts.push(element);
+        let group = resolve_group_key(
+            &element_contexts,
+            GROUP_BY_KEY,
+            key_path,
+            &obj_xpath,
+            options,
+        )?;
+        let value = resolve_element_field(
+            &element_contexts,
+            GROUP_BY_KEY,
+            "value",
+            value_path,
+            &obj_xpath,
+            options,
+        )?;
+        element_contexts.pop();
+        let (group_key, values) = groups
+            .entry(group_name(&group))
+            .or_insert_with(|| (group.clone(), Vec::new()));
+        if *group_key != group {
+            return Err(field_error(
+                &obj_xpath,
+                "key",
+                anyhow!(
+                    "Failed to resolve {}; the keys {} and {} name the same group",
+                    GROUP_BY_KEY,
+                    group_key,
+                    group
+                ),
+            ));
+        }
+        values.push(value);
+    }
+    Ok(Value::Object(
+        groups
+            .into_iter()
+            .map(|(name, (_, values))| (name, Value::Array(values)))
+            .collect(),
+    ))
+}
+
+// Resolves the array of a `@mergeBy` object, whose elements should be objects, and merges the
+// objects with the same value of the `key` mapping into one object. The numeric fields listed in
+// `sum` are added up, every other field keeps the value of the first object of the key. Nested
+// arrays are flattened, so that the objects of several input arrays can be merged, and the merged
+// objects are in the order of the first object of their key. A missing optional array, resolved to
+// null, has no elements. Example
+// `{"@mergeBy": "/order/shipments/items", "key": "sku", "sum": ["quantity"]}` ->
+// `[{"sku": "SKU-123", "quantity": 5}, {"sku": "SKU-343", "quantity": 3}]`
+fn merge_elements(
+    contexts: &Contexts,
+    merge_by: &Value,
+    fields: &Map<String, Value>,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let obj_xpath = format_key(xpath, key);
+    check_fields(MERGE_BY_KEY, fields, &["key", "sum"], &obj_xpath)?;
+    let key_path = mapping_field(MERGE_BY_KEY, fields, "key", &obj_xpath)?.ok_or_else(|| {
+        field_error(
+            xpath,
+            key,
+            anyhow!(
+                "Bad {} format; missing the key field to merge by",
+                MERGE_BY_KEY
+            ),
+        )
+    })?;
+    let sum_error = |sum: &Value| {
+        field_error(
+            &obj_xpath,
+            "sum",
+            anyhow!(
+                "Bad {} format; sum should be an array of field names: {}",
+                MERGE_BY_KEY,
+                sum
+            ),
+        )
+    };
+    let sum_fields = match fields.get("sum") {
+        None => vec![],
+        Some(Value::Array(sum_fields)) => sum_fields
+            .iter()
+            .map(|field| field.as_str().ok_or_else(|| sum_error(&fields["sum"])))
+            .collect::<Result<Vec<&str>>>()?,
+        Some(sum) => return Err(sum_error(sum)),
+    };
+    let elements = flatten_array(
+        resolve_array(contexts, MERGE_BY_KEY, merge_by, &obj_xpath, options)?.unwrap_or_default(),
+    );
+
+    let mut merged: Vec<Map<String, Value>> = vec![];
+    let mut merged_indexes = BTreeMap::new();
+    let mut element_contexts = contexts.clone();
+    element_contexts.in_each = false;
+    for element in elements.iter() {
+        let merge_error = |field: &str, message: String| {
+            field_error(
+                &obj_xpath,
+                field,
+                anyhow!("Failed to resolve {}; {}", MERGE_BY_KEY, message),
+            )
+        };
+        let obj = element.as_object().ok_or_else(|| {
+            merge_error(
+                MERGE_BY_KEY,
+                format!(
+                    "the element {} should be an object",
+                    truncate_context(element.to_string(), options)
+                ),
+            )
+        })?;
+        element_contexts.push(element);
+        let group = resolve_group_key(
+            &element_contexts,
+            MERGE_BY_KEY,
+            key_path,
+            &obj_xpath,
+            options,
+        )?;
+        element_contexts.pop();
+        let sum_value = |field: &str| match obj.get(field) {
+            Some(value @ Value::Number(_)) => Ok(value),
+            Some(value) => Err(merge_error(
+                "sum",
+                format!(
+                    "the sum field {} of the element {} should be a number: {}",
+                    field,
+                    truncate_context(element.to_string(), options),
+                    value
+                ),
+            )),
+            None => Err(merge_error(
+                "sum",
+                format!(
+                    "couldn't find the sum field {} in the element {}",
+                    field,
+                    truncate_context(element.to_string(), options)
+                ),
+            )),
+        };
+        // the JSON text of the key keeps its type, so `10` and `"10"` are merged apart
+        let group = group.to_string();
+        match merged_indexes.get(&group) {
+            None => {
+                for field in &sum_fields {
+                    sum_value(field)?;
+                }
+                merged_indexes.insert(group, merged.len());
+                merged.push(obj.clone());
+            }
+            Some(&i) => {
+                for field in &sum_fields {
+                    let value = sum_value(field)?;
+                    if let Some(total) = merged[i].get_mut(*field) {
+                        *total = add_numbers(total, value);
+                    }
+                }
+            }
+        }
+    }
+    Ok(Value::Array(
+        merged.into_iter().map(Value::Object).collect(),
+    ))
+}
+
+// adds two JSON numbers with the numeric typing rules of the `add` function, integers stay integers
+// unless the sum overflows
+fn add_numbers(a: &Value, b: &Value) -> Value {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => apply(Operator::Add, a, b)
+            .map(Value::Number)
+            .unwrap_or_default(),
+        _ => Value::Null,
+    }
+}
+
+// resolves the key mapping of the innermost context element into the key of its group, keys
+// should be strings, numbers or bools and keep their type, so `10` and `"10"` are different keys
+fn resolve_group_key(
+    element_contexts: &Contexts,
+    directive: &str,
+    key_path: &str,
+    obj_xpath: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let element = element_contexts
+        .innermost()
+        .ok_or_else(|| anyhow!("Failed to resolve {}; no element to resolve", directive))?;
+    let key_error = |message: String| {
+        field_error(
+            obj_xpath,
+            "key",
+            anyhow!("Failed to resolve {}; {}", directive, message),
+        )
+    };
+    match resolve_path(element_contexts, key_path, options)? {
+        Ok(group @ (Value::String(_) | Value::Number(_) | Value::Bool(_))) => Ok(group),
+        Ok(group) => Err(key_error(format!(
+            "the key {} of the element {} should be a string, a number or a bool: {}",
+            key_path,
+            truncate_context(element.to_string(), options),
+            group
+        ))),
+        Err(_) => Err(key_error(format!(
+            "couldn't find the key {} in the element {}",
+            key_path,
+            truncate_context(element.to_string(), options)
+        ))),
+    }
+}
+
+// the name of the group of a key, the string itself or the JSON text of a number or a bool
+fn group_name(group: &Value) -> String {
+    match group {
+        Value::String(name) => name.clone(),
+        group => group.to_string(),
+    }
+}
+
+// Resolves the array of an `@indexed` object into an object with one key per element, the key is
+// the `prefix` followed by the element index. The `field` mapping picks the value of each element,
+// the whole element if it is not set, it is resolved with the element as the innermost context.
+// A missing optional array, resolved to null, has no elements. Example
+// `{"@indexed": "/shipments", "prefix": "shipment_", "field": "tracking_number"}` ->
+// `{"shipment_0": "1234567", "shipment_1": "98776"}`
+fn index_elements(
+    contexts: &Contexts,
+    indexed: &Value,
+    fields: &Map<String, Value>,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let obj_xpath = format_key(xpath, key);
+    check_fields(INDEXED_KEY, fields, &["field", "prefix"], &obj_xpath)?;
+    let prefix = match fields.get("prefix") {
+        None => "",
+        Some(Value::String(prefix)) => prefix,
+        Some(prefix) => {
+            return Err(field_error(
+                &obj_xpath,
+                "prefix",
+                anyhow!(
+                    "Bad {} format; prefix should be a string: {}",
+                    INDEXED_KEY,
+                    prefix
+                ),
+            ))
+        }
+    };
+    let field_path = mapping_field(INDEXED_KEY, fields, "field", &obj_xpath)?;
+    let elements =
+        resolve_array(contexts, INDEXED_KEY, indexed, &obj_xpath, options)?.unwrap_or_default();
+
+    let mut result = Map::new();
+    let mut element_contexts = contexts.clone();
+    element_contexts.in_each = false;
+    for (i, element) in elements.iter().enumerate() {
+        element_contexts.push(element);
+        let value = resolve_element_field(
+            &element_contexts,
+            INDEXED_KEY,
+            "field",
+            field_path,
+            &obj_xpath,
+            options,
+        )?;
+        element_contexts.pop();
+        result.insert(format!("{}{}", prefix, i), value);
+    }
+    Ok(Value::Object(result))
+}
+
+// Resolves the array of a `@filter` object into the elements that satisfy its `where` condition,
+// all of them if it is not set, each projected on its `select` mapping, the whole element if it is
+// not set. Like the predicates of the mapping paths, the condition and the mapping are resolved
+// against the element, so `/quantity` and `quantity` are the same field of the element. A missing
+// optional array, resolved to null, has no elements. Example
+// `{"@filter": "/order/shipments/items", "where": "/quantity > #1", "select": "sku"}` ->
+// `["SKU-123", "SKU-343"]`
+fn filter_elements(
+    contexts: &Contexts,
+    filter: &Value,
+    fields: &Map<String, Value>,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let obj_xpath = format_key(xpath, key);
+    check_fields(FILTER_KEY, fields, &["select", "where"], &obj_xpath)?;
+    let condition = match fields.get("where") {
+        None => None,
+        Some(Value::String(condition)) => Some(condition),
+        Some(condition) => {
+            return Err(field_error(
+                &obj_xpath,
+                "where",
+                anyhow!(
+                    "Bad {} format; where should be a condition: {}",
+                    FILTER_KEY,
+                    condition
+                ),
+            ))
+        }
+    };
+    let select = mapping_field(FILTER_KEY, fields, "select", &obj_xpath)?;
+    let elements =
+        resolve_array(contexts, FILTER_KEY, filter, &obj_xpath, options)?.unwrap_or_default();
+
+    let mut result = vec![];
+    for element in elements.iter() {
+        let roots = [element];
+        let element_contexts = Contexts::new(&roots);
+        if let Some(condition) = condition {
+            if !evaluate_condition(&element_contexts, condition, options)
+                .map_err(|err| field_error(&obj_xpath, "where", err))?
+            {
+                continue;
+            }
+        }
+        let value = match select {
+            None => element.clone(),
+            Some(select) => resolve_alternatives(&element_contexts, select, options)
+                .map_err(|err| field_error(&obj_xpath, "select", err))?
+                .map_err(|_| {
+                    field_error(
+                        &obj_xpath,
+                        "select",
+                        anyhow!(
+                            "Failed to resolve {}; couldn't find the select {} in the element {}",
+                            FILTER_KEY,
+                            select,
+                            truncate_context(element.to_string(), options)
+                        ),
+                    )
+                })?,
+        };
+        result.push(value);
+    }
+    Ok(Value::Array(result))
+}
+
+// Resolves the arrays the fields of a `@zip` object map to and zips them into an array of objects
+// with the same fields, the i-th object takes the i-th element of every array. The arrays are
+// combined with the `spread_policy` option like spread arrays, except that arrays of different
+// lengths are an error with `SpreadPolicy::Zip`. A missing optional array, resolved to null, has no
+// elements. Example `{"@zip": {"sku": "/skus", "qty": "/quantities"}}` ->
+// `[{"sku": "SKU-123", "qty": 4}, {"sku": "SKU-343", "qty": 3}]`
+fn zip_arrays(
+    contexts: &Contexts,
+    zip: &Value,
+    fields: &Map<String, Value>,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let obj_xpath = format_key(xpath, key);
+    if let Some(field) = fields.keys().find(|field| !is_comment(field)) {
+        return Err(field_error(
+            &obj_xpath,
+            field,
+            anyhow!(
+                "Bad {} format; unsupported field {}, the object should only have the {} field",
+                ZIP_KEY,
+                field,
+                ZIP_KEY
+            ),
+        ));
+    }
+    let paths = match zip {
+        Value::Object(paths) if paths.keys().any(|field| !is_comment(field)) => paths,
+        zip => {
+            return Err(field_error(
+                &obj_xpath,
+                ZIP_KEY,
+                anyhow!(
+                    "Bad {} format; it should be an object mapping field names to array paths: {}",
+                    ZIP_KEY,
+                    zip
+                ),
+            ))
+        }
+    };
+
+    let mut arrays = Map::new();
+    let mut pointers = vec![];
+    for (field, path) in paths.iter().filter(|(field, _)| !is_comment(field)) {
+        let elements =
+            resolve_array(contexts, ZIP_KEY, path, &obj_xpath, options)?.unwrap_or_default();
+        pointers.push(format!("/{}", escape_pointer_key(field)));
+        arrays.insert(field.clone(), Value::Array(elements));
+    }
+    let lens: Vec<(&String, usize)> = arrays
+        .iter()
+        .map(|(field, elements)| (field, elements.as_array().map_or(0, Vec::len)))
+        .collect();
+    let array_len = lens.iter().map(|(_, len)| *len).max().unwrap_or_default();
+    if options.spread_policy == SpreadPolicy::Zip && lens.iter().any(|(_, len)| *len != array_len) {
+        return Err(field_error(
+            &obj_xpath,
+            ZIP_KEY,
+            anyhow!(
+                "Failed to resolve {}; the arrays should have the same length: {}",
+                ZIP_KEY,
+                lens.iter()
+                    .map(|(field, len)| format!("{} has {}", field, len))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        ));
+    }
+    let objs = SplitObjects::new(Value::Object(arrays), pointers, array_len, options)
+        .map_err(|err| field_error(&obj_xpath, ZIP_KEY, err))?;
+    Ok(Value::Array(objs.collect()))
+}
+
+// Resolves the mapping of a `@typeswitch` object and returns the name of the branch of its JSON
+// type. The branches are validated first, and a type without branch is an error.
+fn type_branch(
+    contexts: &Contexts,
+    discriminator: &Value,
+    branches: &Map<String, Value>,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<&'static str> {
+    let obj_xpath = format_key(xpath, key);
+    check_fields(TYPESWITCH_KEY, branches, &TYPESWITCH_BRANCHES, &obj_xpath)?;
+    let resolve = || {
+        let path = discriminator.as_str().ok_or_else(|| {
+            anyhow!(
+                "Bad {} format; it should be a mapping path: {}",
+                TYPESWITCH_KEY,
+                discriminator
+            )
+        })?;
+        let value = resolve_alternatives(contexts, path, options)??;
+        let branch = type_name(&value);
+        if !branches.contains_key(branch) {
+            bail!(
+                "Failed to resolve {}; {} resolves to a {}, which has no branch: {}",
+                TYPESWITCH_KEY,
+                path,
+                branch,
+                value
+            );
+        }
+        Ok(branch)
+    };
+    resolve().map_err(|err| field_error(&obj_xpath, TYPESWITCH_KEY, err))
+}
+
+// fails if the object of the directive has a field other than the supported ones, comments aside
+fn check_fields(
+    directive: &str,
+    fields: &Map<String, Value>,
+    supported: &[&str],
+    obj_xpath: &str,
+) -> Result<()> {
+    match fields
+        .keys()
+        .find(|field| !is_comment(field) && !supported.contains(&field.as_str()))
+    {
+        Some(field) => Err(field_error(
+            obj_xpath,
+            field,
+            anyhow!(
+                "Bad {} format; unsupported field {}, the supported fields are {}",
+                directive,
+                field,
+                supported.join(" and ")
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+// returns the mapping path of a field of the directive object, None if the field is not set
+fn mapping_field<'a>(
+    directive: &str,
+    fields: &'a Map<String, Value>,
+    field: &str,
+    obj_xpath: &str,
+) -> Result<Option<&'a str>> {
+    match fields.get(field) {
+        None => Ok(None),
+        Some(Value::String(path)) => Ok(Some(path)),
+        Some(value) => Err(field_error(
+            obj_xpath,
+            field,
+            anyhow!(
+                "Bad {} format; {} should be a mapping path: {}",
+                directive,
+                field,
+                value
+            ),
+        )),
+    }
+}
+
+// resolves the array the directive maps to, None if it resolves to null
+fn resolve_array(
+    contexts: &Contexts,
+    directive: &str,
+    path: &Value,
+    obj_xpath: &str,
+    options: &TransformOptions,
+) -> Result<Option<Vec<Value>>> {
+    let resolve = || {
+        let path = path.as_str().ok_or_else(|| {
+            anyhow!(
+                "Bad {} format; it should be a mapping path: {}",
+                directive,
+                path
+            )
+        })?;
+        check_relative_path(contexts, path, options)?;
+        match resolve_path(contexts, path, options)?? {
+            Value::Array(elements) => Ok(Some(elements)),
+            Value::Null => Ok(None),
+            value => bail!(
+                "Failed to resolve {}; {} should resolve to an array: {}",
+                directive,
+                path,
+                truncate_context(value.to_string(), options)
+            ),
+        }
+    };
+    resolve().map_err(|err| field_error(obj_xpath, directive, err))
+}
+
+// resolves the field mapping of the innermost context element, the whole element if the mapping
+// is not set
+fn resolve_element_field(
+    element_contexts: &Contexts,
+    directive: &str,
+    field: &str,
+    path: Option<&str>,
+    obj_xpath: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let element = element_contexts
+        .innermost()
+        .ok_or_else(|| anyhow!("Failed to resolve {}; no element to resolve", directive))?;
+    match path {
+        None => Ok(element.clone()),
+        Some(path) => resolve_path(element_contexts, path, options)?.map_err(|_| {
+            field_error(
+                obj_xpath,
+                field,
+                anyhow!(
+                    "Failed to resolve {}; couldn't find the {} {} in the element {}",
+                    directive,
+                    field,
+                    path,
+                    truncate_context(element.to_string(), options)
+                ),
+            )
+        }),
+    }
+}
+
+// wraps an error with the JSON pointer of the failing field in the output template
+fn field_error(xpath: &str, key: &str, err: anyhow::Error) -> anyhow::Error {
+    match to_pointer(&format_key(xpath, key)) {
+        Ok(pointer) => FieldError::new(pointer, err).into(),
+        Err(err) => err,
+    }
+}
+
+// Walks the output object the same way as `traverse_mut`, but instead of modifying it in place it
+// records the JSON type each mapping resolves to along with the output path of the field.
+pub fn describe_types(
+    input: &Value,
+    output: &Value,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+    types: &mut Vec<(String, String)>,
+) -> Result<()> {
+    match output {
+        // the body of an `@each` or `@range` object and the fields of a `@mergeBy` or `@filter`
+        // object are resolved against the elements, only the array is described, and so is the
+        // array of a `@zip` object
+        Value::Object(tree)
+            if [EACH_KEY, RANGE_KEY, MERGE_BY_KEY, FILTER_KEY, ZIP_KEY]
+                .iter()
+                .any(|directive| tree.contains_key(*directive)) =>
+        {
+            types.push((
+                format_key(xpath, key),
+                type_name(&Value::Array(vec![])).to_string(),
+            ));
+            Ok(())
+        }
+        // only the branch of the type the mapping resolves to is described
+        Value::Object(tree) if tree.contains_key(TYPESWITCH_KEY) => {
+            let mut branches = tree.clone();
+            let discriminator = branches.remove(TYPESWITCH_KEY).unwrap_or_default();
+            let branch = type_branch(
+                &Contexts::new(&[input]),
+                &discriminator,
+                &branches,
+                xpath,
+                key,
+                options,
+            )?;
+            describe_types(input, &branches[branch], xpath, key, options, types)
+        }
+        // the fields of `@groupBy` and `@indexed` objects are resolved against the elements
+        Value::Object(tree)
+            if tree.contains_key(GROUP_BY_KEY) || tree.contains_key(INDEXED_KEY) =>
+        {
+            types.push((
+                format_key(xpath, key),
+                type_name(&Value::Object(Map::new())).to_string(),
+            ));
+            Ok(())
+        }
+        Value::Object(tree) => {
+            for (sub_key, v) in tree.iter().filter(|(sub_key, _)| {
+                !is_comment(sub_key) && ![WHEN_KEY, DEFAULT_OBJECT_KEY].contains(&sub_key.as_str())
+            }) {
+                describe_types(input, v, &format_key(xpath, key), sub_key, options, types)?;
+            }
+            Ok(())
+        }
+        Value::Array(elements) => {
+            for (i, v) in elements.iter().enumerate() {
+                describe_types(
+                    input,
+                    v,
+                    &format_key(xpath, key),
+                    &i.to_string(),
+                    options,
+                    types,
+                )?;
+            }
+            Ok(())
+        }
+        _ => {
+            let output_field_value = output.as_str().ok_or_else(|| {
+                anyhow!(
+                    "Traversing output object failed; output object field should be string: {}",
+                    output
+                )
+            })?;
+            if output_field_value.trim() == INDEX_DIRECTIVE {
+                check_index_directive(xpath, false)?;
+                types.push((format_key(xpath, key), "number".to_string()));
+                return Ok(());
+            }
+            if output_field_value.trim() == DEPTH_DIRECTIVE {
+                types.push((format_key(xpath, key), "number".to_string()));
+                return Ok(());
+            }
+            let value = resolve_mapping(
+                &Contexts::new(&[input]),
+                output_field_value,
+                xpath,
+                key,
+                options,
+            )?;
+            types.push((format_key(xpath, key), type_name(&value).to_string()));
+            Ok(())
+        }
+    }
+}
+
+// Resolves a single output field value, which is either a hard coded value or a mapping path, or
+// several of them separated by `||` that are tried in order until one resolves, example
+// `/a/primary || /a/secondary || 'unknown'`. Mapping paths that can't be resolved from the input
+// take the `default_on_missing` option value if it is set. The resolver of the contexts, if any,
+// resolves the whole output field value instead, and the observer, if any, is notified with the JSON
+// pointer of the output field. Inside a `@defaultObject` body the misses are counted, see `Misses`.
+fn resolve_mapping(
+    contexts: &Contexts,
+    output_field_value: &str,
+    xpath: &str,
+    key: &str,
+    options: &TransformOptions,
+) -> Result<Value> {
+    let result = match contexts.resolver {
+        Some(resolver) => {
+            let input = contexts.resolver_input(output_field_value);
+            let context = LeafContext::new(contexts, options, input);
+            let result = resolver.resolve(
+                &context,
+                output_field_value,
+                &to_pointer(&format_key(xpath, key))?,
+            );
+            if let Some(err) = context.take_template_error() {
+                return Err(err);
+            }
+            result
+        }
+        None => resolve_alternatives(contexts, output_field_value, options)?,
+    };
+    if let Some(observer) = contexts.observer {
+        let output_path = to_pointer(&format_key(xpath, key))?;
+        match &result {
+            Ok(value) => observer.on_resolve(&output_path, output_field_value, value),
+            Err(_) => observer.on_missing(&output_path, output_field_value),
+        }
+    }
+    if let Some(misses) = contexts.misses {
+        if reads_input(output_field_value) {
+            misses.record(result.is_err());
+        }
+    }
+    match (result, &options.default_on_missing, contexts.misses) {
+        (Err(_), Some(default), _) => Ok(default.clone()),
+        // the miss fails the `@defaultObject` body it is in, unless the whole body misses
+        (Err(err), None, Some(misses)) => {
+            misses.defer(FieldError::new(to_pointer(&format_key(xpath, key))?, err).into());
+            Ok(Value::Null)
+        }
+        (result, _, _) => result,
+    }
+}
+
+// Resolves the `||` separated alternatives of an output field value in order until one resolves, the
+// error of the last one is returned if none does
+pub fn resolve_alternatives(
+    contexts: &Contexts,
+    output_field_value: &str,
+    options: &TransformOptions,
+) -> Result<Result<Value>> {
+    let mut result = Ok(Value::Null);
+    for alternative in split_alternatives(output_field_value) {
+        result = resolve_alternative(contexts, alternative, options)?;
+        if result.is_ok() {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+// Resolves a hard coded value, a time directive, a function call or a mapping path. The outer result
+// is an error if the template is malformed, the inner one if the mapping path can't be resolved from
+// the input.
+pub fn resolve_alternative(
+    contexts: &Contexts,
+    output_field_value: &str,
+    options: &TransformOptions,
+) -> Result<Result<Value>> {
+    // check for hard coded values
+    if let Some(literal) = unquote_literal(output_field_value) {
+        return Ok(Ok(to_value(literal)?));
+    }
+    if let Some(time) = resolve_time_directive(output_field_value, options)? {
+        return Ok(Ok(time));
+    }
+    if let Some(field) = output_field_value.strip_prefix(SIBLING_PREFIX) {
+        return resolve_sibling(contexts, field, options);
+    }
+    if let Some(result) = resolve_jsonpath(contexts, output_field_value)? {
+        return Ok(result);
+    }
+    if let Some((name, args)) = parse_function_call(output_field_value)? {
+        return resolve_function(contexts, name, &args, options);
+    }
+    check_relative_path(contexts, output_field_value, options)?;
+    resolve_path(contexts, output_field_value, options)
+}
+
+// Fails a relative mapping path outside of an `@each` body with `RelativePathPolicy::Strict`, where
+// it would otherwise be resolved against the input root
+fn check_relative_path(contexts: &Contexts, path: &str, options: &TransformOptions) -> Result<()> {
+    if options.relative_paths == RelativePathPolicy::Strict
+        && contexts.depth() == 0
+        && !is_absolute_path(path)
+    {
+        bail!(
+            "Bad path format; the relative path {} is outside of an {} body, it should start with /",
+            path,
+            EACH_KEY
+        );
+    }
+    Ok(())
+}
+
+// Resolves a `@sibling:field` mapping, the field of the current `@each` element. It is the same as
+// the relative path `field` but it can't reach out of the element, and it is an error outside of an
+// `@each` body where there is no current element.
+fn resolve_sibling(
+    contexts: &Contexts,
+    field: &str,
+    options: &TransformOptions,
+) -> Result<Result<Value>> {
+    if !contexts.in_each {
+        bail!(
+            "Bad {} format; it can only be used in an {} body: {}{}",
+            SIBLING_PREFIX.trim_end_matches(':'),
+            EACH_KEY,
+            SIBLING_PREFIX,
+            field
+        );
+    }
+    if field.is_empty() || field.starts_with('/') || field.starts_with(PARENT_CONTEXT_TOKEN) {
+        bail!(
+            "Bad {} format; it should be followed by a field of the current element: {}{}",
+            SIBLING_PREFIX.trim_end_matches(':'),
+            SIBLING_PREFIX,
+            field
+        );
+    }
+    resolve_path(contexts, field, options)
+}
+
+// Resolves a mapping path, a path starting with `/` is resolved against the input root, any other
+// path against the innermost context, the current `@each` element or the input root outside of
+// `@each` bodies. Each leading `..` token moves one context out, example `../id` resolves the id of
+// the enclosing `@each` element. The outer result is an error if the path is malformed, the inner
+// one if the path can't be resolved from the input.
+pub fn resolve_path(
+    contexts: &impl AsContexts,
+    path: &str,
+    options: &TransformOptions,
+) -> Result<Result<Value>> {
+    let contexts = &*contexts.as_contexts();
+    let mut path_tokens: LinkedList<&str> = split_path(path)?.into_iter().collect();
+    let mut depth = if is_absolute_path(path) {
+        0
+    } else {
+        contexts.depth()
+    };
+    while path_tokens.front() == Some(&PARENT_CONTEXT_TOKEN) {
+        path_tokens.pop_front();
+        depth = depth.checked_sub(1).ok_or_else(|| {
+            anyhow!(
+                "Bad path format; {} goes above the input root: {}",
+                PARENT_CONTEXT_TOKEN,
+                path
+            )
+        })?;
+    }
+    if path_tokens.contains(&PARENT_CONTEXT_TOKEN) {
+        bail!(
+            "Bad path format; {} should only lead relative paths: {}",
+            PARENT_CONTEXT_TOKEN,
+            path
+        );
+    }
+    // the input roots are tried in order, the error of the first one is kept if none resolves. An
+    // optional path resolved to null moves on to the next root as well, it is only null if no root
+    // has the field
+    let optional = path_tokens
+        .iter()
+        .any(|token| parse_path_token(token).optional);
+    let mut optional_null = false;
+    let mut first_err = None;
+    for input in contexts.get(depth).unwrap_or_default() {
+        match resolve_output_field_value(&mut path_tokens.clone(), input, options) {
+            Ok(value) if optional && value.is_null() => optional_null = true,
+            Ok(value) => return Ok(Ok(value.into_owned())),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    if optional_null {
+        return Ok(Ok(Value::Null));
+    }
+    Ok(Err(first_err.unwrap_or_else(|| {
+        anyhow!(
+            "Failed to resolve mapping value; no input to resolve {}",
+            path
+        )
+    })))
+}
+
+// splits an output field value on `||` into its trimmed alternatives, except the ones in hard coded
+// values, quoted keys and function arguments, example `/a || 'x||y'` -> [`/a`, `'x||y'`]
+fn split_alternatives(output_field_value: &str) -> Vec<&str> {
+    let mut alternatives = vec![];
+    let mut alternative_start = 0;
+    let mut in_literal = false;
+    let mut escaped = false;
+    let mut in_quoted_key = false;
+    let mut depth = 0;
+    let mut chars = output_field_value.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_literal => escaped = true,
+            '\'' if !in_quoted_key => in_literal = !in_literal,
+            '"' if !in_literal => in_quoted_key = !in_quoted_key,
+            _ if in_literal || in_quoted_key => {}
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '|' if depth == 0 && chars.peek().map(|(_, c)| *c) == Some('|') => {
+                chars.next();
+                alternatives.push(output_field_value[alternative_start..i].trim());
+                alternative_start = i + 2;
+            }
+            _ => {}
+        }
+    }
+    if alternatives.is_empty() {
+        return vec![output_field_value];
+    }
+    alternatives.push(output_field_value[alternative_start..].trim());
+    alternatives
+}
+
+// Returns the value of a hard coded literal quoted with single quotes. Inside the literal `\'` and
+// a doubled quote are escaped quotes and `\\` is an escaped backslash, example `'O\'Brien'` ->
+// `O'Brien`; any other backslash is kept as is, so that `'PO-(\d+)'` -> `PO-(\d+)`. `None` is
+// returned if the value is not a literal, or if its closing quote is escaped.
+pub fn unquote_literal(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut literal = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped @ ('\'' | '\\')) => literal.push(escaped),
+                Some(c) => {
+                    literal.push('\\');
+                    literal.push(c);
+                }
+                None => return None,
+            },
+            '\'' => {
+                literal.push('\'');
+                if chars.as_str().starts_with('\'') {
+                    chars.next();
+                }
+            }
+            c => literal.push(c),
+        }
+    }
+    Some(literal)
+}
+
+// Truncates the JSON text of a value printed in an error message to the `error_context_limit`
+// option characters followed by an ellipsis, so that a huge input object doesn't flood the logs
+pub fn truncate_context(context: String, options: &TransformOptions) -> String {
+    match options.error_context_limit {
+        Some(limit) if context.chars().count() > limit => {
+            let mut truncated: String = context.chars().take(limit).collect();
+            truncated.push_str("...");
+            truncated
+        }
+        _ => context,
+    }
+}
+
+// Returns the JSON type name of the value, example `[]` -> `array`
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Takes mapping value. i.g "/order/shipments/items/quantity" and resolves it from the input object
+// and returns the value. A field name marked with `?`, i.g "/order/discount?/code", is optional; if
+// it is missing or null the whole mapping resolves to null, and array elements missing it are
+// skipped. The value of a field name marked with `~`, i.g "/payload~/id", is a JSON string that is
+// parsed before resolving the rest of the path. A `*` token resolves the rest of the path against
+// every element of an array, see `resolve_wildcard`. A field that is present but null resolves to null,
+// and so do the fields under it, while an absent field, missing from its object or looked up in a
+// string, a number or a boolean, fails to resolve unless it is optional. With the
+// `flattened_input` option, when the nested lookup fails under an object, the field name and the
+// remaining path tokens are joined with `.` and looked up in that object as a
+// single flattened key, i.g "order.po_number". The nested lookup always takes precedence.
+// The resolved value is borrowed from the input when the path only walks down objects, it is only
+// owned when it is built while resolving, example the array of the field values of array elements.
+pub fn resolve_output_field_value<'a>(
+    path_tokens: &mut LinkedList<&str>,
+    input: &'a Value,
+    options: &TransformOptions,
+) -> Result<Cow<'a, Value>> {
+    let path_token = match path_tokens.pop_front() {
+        None => {
+            return Ok(Cow::Borrowed(input));
+        }
+        // the whole subtree is passed through as is
+        Some(PASSTHROUGH_TOKEN) => {
+            if !path_tokens.is_empty() {
+                bail!(
+                    "Bad path format; {} should be the last token of the path",
+                    PASSTHROUGH_TOKEN
+                );
+            }
+            return Ok(Cow::Borrowed(input));
+        }
+        Some(WILDCARD_TOKEN) => return resolve_wildcard(path_tokens, input, options),
+        Some(path_token) => parse_path_token(path_token),
+    };
+    let field_name = path_token.field_name;
+
+    match input {
+        Value::Array(_) if options.strict_array_access => bail!(
+            "Failed to resolve mapping value; couldn't find field name {} in an array with the strict_array_access option, its elements are iterated with the {} wildcard, example {}/{}",
+            field_name,
+            WILDCARD_TOKEN,
+            WILDCARD_TOKEN,
+            field_name
+        ),
+        Value::Array(array_values) => {
+            let mut result_array = vec![];
+            for element in array_values.iter() {
+                let value = match element.get(field_name) {
+                    None | Some(Value::Null) if path_token.optional => continue,
+                    None => bail!(
+                        "Failed to resolve mapping value; couldn't find field name {} in the obj {}",
+                        &field_name,
+                        truncate_context(to_string_pretty(&element)?, options)
+                    ),
+                    Some(value) if path_token.embedded_json => parse_embedded_json(field_name, value)?,
+                    Some(value) => value.clone(),
+                };
+                match value {
+                    Value::Array(values) => {
+                        for value in values {
+                            if matches_predicate(&path_token, &value, options)? {
+                                result_array.push(value);
+                            }
+                        }
+                    }
+                    value => {
+                        if matches_predicate(&path_token, &value, options)? {
+                            result_array.push(value);
+                        }
+                    }
+                }
+            }
+            let result = select_matches(&path_token, result_array);
+            resolve_cow_field_value(path_tokens, Cow::Owned(result), options)
+        }
+        Value::Object(obj_value) => {
+            // the flattened key is built before the nested lookup consumes the path tokens
+            let flattened_key = if options.flattened_input {
+                core::iter::once(field_name)
+                    .chain(
+                        path_tokens
+                            .iter()
+                            .map(|token| parse_path_token(token).field_name),
+                    )
+                    .collect::<Vec<&str>>()
+                    .join(".")
+            } else {
+                String::default()
+            };
+            let result = match obj_value.get(field_name) {
+                None | Some(Value::Null) if path_token.optional => {
+                    path_tokens.clear();
+                    Ok(Cow::Owned(Value::Null))
+                }
+                None => Err(anyhow!(
+                    "Failed to resolve mapping value; couldn't find field name {} in the obj {}",
+                    &field_name,
+                    truncate_context(to_string_pretty(&obj_value)?, options)
+                )),
+                Some(field_value) if path_token.embedded_json => {
+                    parse_embedded_json(field_name, field_value)
+                        .and_then(|field_value| {
+                            filter_by_predicate(&path_token, &field_value, options)
+                                .map(|field_value| Cow::Owned(field_value.into_owned()))
+                        })
+                        .and_then(|field_value| {
+                            resolve_cow_field_value(path_tokens, field_value, options)
+                        })
+                }
+                Some(field_value) if path_token.predicate.is_some() => {
+                    filter_by_predicate(&path_token, field_value, options).and_then(|field_value| {
+                        resolve_cow_field_value(path_tokens, field_value, options)
+                    })
+                }
+                Some(field_value) => resolve_output_field_value(path_tokens, field_value, options),
+            };
+            match (result, obj_value.get(&flattened_key)) {
+                (Err(_), Some(field_value)) if options.flattened_input => {
+                    path_tokens.clear();
+                    Ok(Cow::Borrowed(field_value))
+                }
+                (result, _) => result,
+            }
+        }
+        // a field under a present but null value is null as well
+        Value::Null => Ok(Cow::Borrowed(input)),
+        // a scalar has no fields, the field is absent
+        _ if path_token.optional => {
+            path_tokens.clear();
+            Ok(Cow::Owned(Value::Null))
+        }
+        _ => Err(anyhow!(
+            "Failed to resolve mapping value; couldn't find field name {} in the {} {}",
+            &field_name,
+            type_name(input),
+            input
+        )),
+    }
+}
+
+// Resolves the rest of the path against every element of an array, or every value of an object, in
+// order, into the array of the results. The results are not flattened, so that the wildcard keeps
+// one array level per element, example `/shipments/*/items` -> `[[item, item], [item]]`, except for
+// the arrays of a wildcard further down the path, which are concatenated so that every wildcard
+// iterates a level, example `/shipments/*/items/*/sku` -> `[sku, sku, sku]`. A wildcard under a
+// present but null value is null, like the fields under it.
+fn resolve_wildcard<'a>(
+    path_tokens: &mut LinkedList<&str>,
+    input: &'a Value,
+    options: &TransformOptions,
+) -> Result<Cow<'a, Value>> {
+    let elements: Vec<&Value> = match input {
+        Value::Array(values) => values.iter().collect(),
+        Value::Object(obj) => obj.values().collect(),
+        Value::Null => {
+            path_tokens.clear();
+            return Ok(Cow::Borrowed(input));
+        }
+        value => bail!(
+            "Failed to resolve mapping value; {} should iterate an array or an object, not a {}: {}",
+            WILDCARD_TOKEN,
+            type_name(value),
+            truncate_context(value.to_string(), options)
+        ),
+    };
+    let nested_wildcard = path_tokens.contains(&WILDCARD_TOKEN);
+    let mut results = vec![];
+    for element in elements {
+        match resolve_output_field_value(&mut path_tokens.clone(), element, options)?.into_owned() {
+            Value::Array(values) if nested_wildcard => results.extend(values),
+            value => results.push(value),
+        }
+    }
+    path_tokens.clear();
+    Ok(Cow::Owned(Value::Array(results)))
+}
+
+// resolves the rest of the path from a value that is either borrowed from the input or built while
+// resolving, a built value is moved rather than cloned when the path ends there
+fn resolve_cow_field_value<'a>(
+    path_tokens: &mut LinkedList<&str>,
+    value: Cow<'a, Value>,
+    options: &TransformOptions,
+) -> Result<Cow<'a, Value>> {
+    match value {
+        Cow::Borrowed(value) => resolve_output_field_value(path_tokens, value, options),
+        Cow::Owned(value) if path_tokens.is_empty() => Ok(Cow::Owned(value)),
+        Cow::Owned(value) => resolve_output_field_value(path_tokens, &value, options)
+            .map(|value| Cow::Owned(value.into_owned())),
+    }
+}
+
+// A mapping path token, the field name along with the markers that change how it is resolved
+#[derive(Debug, PartialEq)]
+struct PathToken<'a> {
+    field_name: &'a str,
+    // marked with `?`, a missing or null field resolves to null
+    optional: bool,
+    // marked with `~`, the field value is a JSON string that is parsed before resolving the rest of
+    // the path
+    embedded_json: bool,
+    // condition in square brackets after the field name, the elements of the field value that don't
+    // satisfy it are filtered out before resolving the rest of the path
+    predicate: Option<&'a str>,
+}
+
+// returns true if the token has no predicate or the value satisfies it, the predicate paths are
+// resolved against the value
+fn matches_predicate(
+    path_token: &PathToken,
+    value: &Value,
+    options: &TransformOptions,
+) -> Result<bool> {
+    match path_token.predicate {
+        Some(predicate) => evaluate_condition(&Contexts::new(&[value]), predicate, options),
+        None => Ok(true),
+    }
+}
+
+// filters out the array elements that don't satisfy the predicate of the token, a value that is
+// not an array should satisfy it. Only the matching elements are cloned.
+fn filter_by_predicate<'a>(
+    path_token: &PathToken,
+    value: &'a Value,
+    options: &TransformOptions,
+) -> Result<Cow<'a, Value>> {
+    match value {
+        Value::Array(values) => {
+            let mut filtered = vec![];
+            for value in values {
+                if matches_predicate(path_token, value, options)? {
+                    filtered.push(value.clone());
+                }
+            }
+            Ok(Cow::Owned(select_matches(path_token, filtered)))
+        }
+        value if matches_predicate(path_token, value, options)? => Ok(Cow::Borrowed(value)),
+        value => bail!(
+            "Failed to resolve mapping value; field {} doesn't satisfy the predicate {}: {}",
+            path_token.field_name,
+            path_token.predicate.unwrap_or_default(),
+            value
+        ),
+    }
+}
+
+// Returns the elements that matched the predicate of the token as an array, except for an equality
+// predicate matched by exactly one element, example `items[sku == 'SKU-343']`, which selects that
+// element so that the rest of the path resolves to a scalar
+fn select_matches(path_token: &PathToken, mut matches: Vec<Value>) -> Value {
+    match path_token.predicate {
+        Some(predicate) if matches.len() == 1 && is_equality(predicate) => matches.remove(0),
+        _ => Value::Array(matches),
+    }
+}
+
+// parses a path token into the field name, its predicate and its markers, which can be combined in
+// any order, example `discount?` -> `discount` optional, `payload~?` -> `payload` optional embedded
+// JSON, `items[quantity > #1]?` -> `items` optional with the predicate `quantity > #1`. Quoted keys
+// are taken literally, example `["a?"]` -> `a?`
+fn parse_path_token(path_token: &str) -> PathToken<'_> {
+    let mut token = PathToken {
+        field_name: path_token,
+        optional: false,
+        embedded_json: false,
+        predicate: None,
+    };
+    if let Some(field_name) = unquote_key(path_token) {
+        token.field_name = field_name;
+        return token;
+    }
+    loop {
+        if let Some(field_name) = token.field_name.strip_suffix('?') {
+            token.optional = true;
+            token.field_name = field_name;
+        } else if let Some(field_name) = token.field_name.strip_suffix('~') {
+            token.embedded_json = true;
+            token.field_name = field_name;
+        } else {
+            break;
+        }
+    }
+    // only a comparison or a `?` prefixed condition is a predicate, other brackets are part of the
+    // field name, example `tags[0]`
+    if let Some(field_name) = token.field_name.strip_suffix(']') {
+        if let Some((field_name, predicate)) = field_name.split_once('[') {
+            let predicate = predicate.trim();
+            let predicate = match predicate.strip_prefix('?') {
+                Some(condition) => Some(condition.trim()),
+                None if is_comparison(predicate) => Some(predicate),
+                None => None,
+            };
+            if !field_name.is_empty() && predicate.is_some() {
+                token.field_name = field_name;
+                token.predicate = predicate;
+            }
+        }
+    }
+    token
+}
+
+// parses the value of a field marked with `~`, which should be a JSON string
+fn parse_embedded_json(field_name: &str, value: &Value) -> Result<Value> {
+    let json = value.as_str().ok_or_else(|| {
+        anyhow!(
+            "Failed to resolve mapping value; field {} should be a JSON string to be parsed: {}",
+            field_name,
+            value
+        )
+    })?;
+    from_str(json).map_err(|err| {
+        anyhow!(
+            "Failed to resolve mapping value; couldn't parse field {} as JSON: {}",
+            field_name,
+            err
+        )
+    })
+}
+
+// it traverse the transformed output and convert objects into arrays wherever found. The index
+// fields are the `@index` fields recorded while the output was traversed, see `IndexFields`.
+#[allow(clippy::too_many_arguments)]
+pub fn process_array_convertible_objs(
+    input: &Value,
+    output: &mut Value,
+    xpath: &str,
+    key: &str,
+    visited: &mut LinkedList<String>,
+    array_lens: &mut LinkedList<usize>,
+    index_fields: &[Vec<String>],
+    options: &TransformOptions,
+) -> Result<()> {
+    match input {
+        Value::Object(ref tree) => {
+            let is_array_convertible = is_obj_to_be_converted_to_array(key);
+            // an array convertible object gets its own stacks, so that the spread arrays of a
+            // sibling or nested array convertible object don't leak into its split
+            let (mut scoped_visited, mut scoped_array_lens) =
+                (LinkedList::new(), LinkedList::new());
+            let (visited, array_lens) = if is_array_convertible {
+                (&mut scoped_visited, &mut scoped_array_lens)
+            } else {
+                (visited, array_lens)
+            };
+            if is_array_convertible {
+                visited.push_back(clean_path(&format_key(xpath, key))?);
+                let parent_obj = if xpath.is_empty() {
+                    output.as_object_mut().ok_or_else(|| {
+                        anyhow!(
+                        "Failed to process array convertible object; output expected to be object"
+                    )
+                    })?
+                } else {
+                    output
+                        .pointer_mut(&clean_path(xpath)?)
+                        .ok_or_else(||anyhow!("Failed to process array convertible object; failed for find parent object"))?
+                        .as_object_mut()
+                        .ok_or_else(|| anyhow!("Failed to process array convertible object; output expected to be object"))?
+                };
+
+                let value = parent_obj.remove(key).ok_or_else(|| {
+                    anyhow!(
+                        "Failed to process array convertible object; couldn't find field name {} in {}",
+                        &key,
+                        truncate_context(format!("{:#?}", parent_obj), options)
+                    )
+                })?;
+                parent_obj.insert(clean_key(key)?.to_string(), value);
+            }
+
+            for (sub_key, v) in tree.iter() {
+                process_array_convertible_objs(
+                    v,
+                    output,
+                    &format_key(xpath, key),
+                    sub_key,
+                    visited,
+                    array_lens,
+                    index_fields,
+                    options,
+                )?;
+            }
+
+            // start array splitting
+            if is_array_convertible {
+                let obj_path = format_key(xpath, key);
+                split_obj_to_array(
+                    output,
+                    array_lens.pop_back().ok_or_else(|| anyhow!("Failed to process array convertible object; a array convertible object {} is detected but no spread array field was found", &key))?,
+                    visited,
+                    &clean_path(&obj_path)?,
+                    index_pointers(index_fields, &split_path(&obj_path)?)?,
+                    options,
+                )?
+            }
+        }
+        _ => {
+            if is_to_be_spread_array(key) {
+                let parent_obj = output
+                    .pointer_mut(&clean_path(xpath)?)
+                    .ok_or_else(|| anyhow!("Failed to process array convertible object; unable to find the parent obj path of the array {}", &key))?
+                    .as_object_mut()
+                    .ok_or_else(|| anyhow!("Failed to process array convertible object; the parent obj of the spread array {} is not an object type", &key))?;
+                // nested arrays are flattened, so that each leaf element gets its own object, objects
+                // included; the elements are data, they are not walked for decorated keys
+                let spread_array = match parent_obj.remove(key).ok_or_else(|| {
+                    anyhow!(
+                        "Failed to process array convertible object; couldn't find {} in {} ",
+                        &key,
+                        truncate_context(format!("{:#?}", parent_obj), options)
+                    )
+                })? {
+                    Value::Array(values) => Value::Array(flatten_array(values)),
+                    // a scalar can't be split, a missing optional array resolved to null is let through
+                    value @ (Value::String(_) | Value::Number(_) | Value::Bool(_))
+                        if is_in_array_convertible_obj(xpath)? =>
+                    {
+                        bail!(
+                            "Failed to process array convertible object; the spread field {} should resolve to an array, not a {}: {}",
+                            format_key(xpath, key),
+                            type_name(&value),
+                            truncate_context(value.to_string(), options)
+                        )
+                    }
+                    value => value,
+                };
+                if let Some(values) = spread_array.as_array() {
+                    array_lens.push_back(values.len());
+                }
+                parent_obj.insert(clean_key(key)?.to_string(), spread_array);
+                visited.push_back(clean_path(&format_key(xpath, key))?);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Converts the array convertible objects of a transformed output object into arrays. The conversion
+// works on a copy of the output, which replaces it once every object is converted, so the output is
+// left untouched if the conversion fails partway.
+pub fn convert_array_convertible_objs(
+    output: &mut Value,
+    index_fields: &[Vec<String>],
+    options: &TransformOptions,
+) -> Result<()> {
+    let mut converted = output.clone();
+    process_array_convertible_objs(
+        output,
+        &mut converted,
+        "",
+        "",
+        &mut Default::default(),
+        &mut Default::default(),
+        index_fields,
+        options,
+    )?;
+    *output = converted;
+    Ok(())
+}
+
+// tells whether one of the keys of the path is an array convertible object, example `/[order]/ids`
+fn is_in_array_convertible_obj(xpath: &str) -> Result<bool> {
+    Ok(split_path(xpath)?
+        .into_iter()
+        .any(is_obj_to_be_converted_to_array))
+}
+
+// Moves the spread fields of an array convertible object template to a skeleton of the object that
+// holds only them, along with the objects they are nested in and the `@when` conditions of those
+// objects, so that the spread arrays can be resolved apart from the rest of the template. The fields
+// of nested array convertible objects and of the objects resolved by a directive are left in place.
+pub(crate) fn take_spread_fields(template: &mut Map<String, Value>) -> Map<String, Value> {
+    let mut skeleton = Map::new();
+    let keys: Vec<String> = template.keys().cloned().collect();
+    for key in keys {
+        if is_obj_to_be_converted_to_array(&key) || is_comment(&key) {
+            continue;
+        }
+        match template.get_mut(&key) {
+            Some(Value::Object(obj))
+                if !DIRECTIVE_KEYS
+                    .iter()
+                    .any(|directive| obj.contains_key(*directive)) =>
+            {
+                let sub_skeleton = take_spread_fields(obj);
+                if !sub_skeleton.is_empty() {
+                    skeleton.insert(key, Value::Object(sub_skeleton));
+                }
+            }
+            Some(_) if is_to_be_spread_array(&key) => {
+                let value = template.remove(&key).unwrap_or_default();
+                skeleton.insert(key, value);
+            }
+            _ => {}
+        }
+    }
+    if let Some(when) = template.get(WHEN_KEY).filter(|_| !skeleton.is_empty()) {
+        skeleton.insert(WHEN_KEY.to_string(), when.clone());
+    }
+    skeleton
+}
+
+// takes an object that contain the spread arrays and convert it into array of the same object, each
+// takes one element from the array. How the spread arrays elements are combined depends on the
+// `spread_policy` option. The index pointers are the `@index` fields of the object, relative to it.
+pub fn split_obj_to_array(
+    output: &mut Value,
+    array_len: usize,
+    visited: &mut LinkedList<String>,
+    path_to_array_parent_obj: &str,
+    index_pointers: Vec<String>,
+    options: &TransformOptions,
+) -> Result<()> {
+    // example: ["/order/sub_order/details/trackings"], in the template order
+    let mut paths_to_spread_arrays = vec![];
+    let mut path_to_spread_array = visited.pop_back().ok_or_else(|| {
+        anyhow!("Failed to split object to array; could not get path to the spread array")
+    })?;
+    // path_to_array_parent_obj example: "/order/sub_order/details"
+    while path_to_spread_array != path_to_array_parent_obj {
+        paths_to_spread_arrays.push(path_to_spread_array);
+        path_to_spread_array = visited.pop_back().ok_or_else(|| {
+            anyhow!("Failed to split object to array; failed to get path token from the stack")
+        })?;
+    }
+    paths_to_spread_arrays.reverse();
+
+    let paths_from_parent_obj = paths_to_spread_arrays
+        .iter()
+        .map(|path_to_spread_array| {
+            // example "/tracking"
+            path_to_spread_array
+                .strip_prefix(path_to_array_parent_obj)
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Failed to split object to array; could not get path to the spread array"
+                    )
+                })
+        })
+        .collect::<Result<Vec<String>>>()?;
+    let parent_obj = output
+        .pointer(path_to_array_parent_obj)
+        .ok_or_else(|| {
+            anyhow!("Failed to split object to array; could not get path to the spread array")
+        })?
+        .clone();
+    let array_of_objs = SplitObjects::new(parent_obj, paths_from_parent_obj, array_len, options)?
+        .with_index_pointers(index_pointers)
+        .collect();
+    *output
+        .pointer_mut(path_to_array_parent_obj)
+        .ok_or_else(|| anyhow!("Failed to split object to array; failed to get parent object of the spread array from output"))? =
+        Value::Array(array_of_objs);
+    Ok(())
+}
+
+// Collects the spread fields of the objects an array convertible object was split into back into
+// arrays, the inverse of `split_obj_to_array` with `SpreadPolicy::Zip`. The array is the `array_key`
+// field of the value and the spread fields are JSON pointers relative to its objects, the other
+// fields are taken from the first object since they are the same in every object. The objects
+// missing a spread field, e.g. trimmed with `trim_trailing_nulls`, are skipped.
+pub fn collapse_array_to_obj(
+    value: &Value,
+    array_key: &str,
+    spread_fields: &[&str],
+) -> Result<Value> {
+    let objs = match value.get(array_key) {
+        Some(Value::Array(objs)) => objs,
+        Some(array) => bail!(
+            "Failed to collapse array to object; {} should be an array: {}",
+            array_key,
+            array
+        ),
+        None => bail!(
+            "Failed to collapse array to object; couldn't find {} in {}",
+            array_key,
+            value
+        ),
+    };
+    let mut collapsed = objs.first().cloned().ok_or_else(|| {
+        anyhow!(
+            "Failed to collapse array to object; the array {} is empty",
+            array_key
+        )
+    })?;
+    for field in spread_fields {
+        let values: Vec<Value> = objs
+            .iter()
+            .filter_map(|obj| obj.pointer(field).cloned())
+            .collect();
+        *collapsed.pointer_mut(field).ok_or_else(|| {
+            anyhow!(
+                "Failed to collapse array to object; couldn't find the spread field {} in {}",
+                field,
+                objs[0]
+            )
+        })? = Value::Array(values);
+    }
+    let mut result = value.clone();
+    if let Value::Object(parent_obj) = &mut result {
+        parent_obj.insert(array_key.to_string(), collapsed);
+    }
+    Ok(result)
+}
+
+/// Iterator over the objects an array convertible object is split into, each object is built on
+/// demand from the i-th element of the spread arrays, or from their i-th combination with
+/// [`SpreadPolicy::Product`]. See [`Template::split_iter`](crate::Template::split_iter).
+#[derive(Debug, Clone)]
+pub struct SplitObjects {
+    parent_obj: Value,
+    // path of each spread array from the parent object along with its elements
+    spread_arrays: Vec<(String, Vec<Value>)>,
+    spread_policy: SpreadPolicy,
+    // the fields of the spread arrays past their last element are left out rather than set to null
+    trim_trailing_nulls: bool,
+    // positions of the spread arrays elements the objects are built from with
+    // `SpreadPolicy::SkipNull`, the positions with a null element are left out
+    positions: Vec<usize>,
+    // pointers of the `@index` fields from the parent object, set to the index of each object
+    index_pointers: Vec<String>,
+    len: usize,
+    position: usize,
+}
+
+impl SplitObjects {
+    // takes the spread arrays out of the parent object, their paths are relative to the parent
+    // object, in the template order. With `SpreadPolicy::Zip` the number of objects is `array_len`.
+    pub(crate) fn new(
+        mut parent_obj: Value,
+        paths_to_spread_arrays: Vec<String>,
+        array_len: usize,
+        options: &TransformOptions,
+    ) -> Result<Self> {
+        let mut spread_arrays = vec![];
+        for path in paths_to_spread_arrays {
+            // the parent object is only printed when the spread array is missing
+            if parent_obj.pointer(&path).is_none() {
+                bail!(
+                    "Failed to split object to array; could not find {} in {}",
+                    &path,
+                    truncate_context(to_string_pretty(&parent_obj)?, options)
+                );
+            }
+            let spread_array = parent_obj.pointer_mut(&path).map(Value::take);
+            let elems = match spread_array.unwrap_or_default() {
+                Value::Array(elems) => elems,
+                _ if options.spread_policy == SpreadPolicy::Product => bail!(
+                    "Failed to split object to array; the spread field {} is not an array",
+                    path
+                ),
+                _ => vec![],
+            };
+            spread_arrays.push((path, elems));
+        }
+        let trim_trailing_nulls =
+            options.trim_trailing_nulls && options.spread_policy != SpreadPolicy::Product;
+        if trim_trailing_nulls {
+            for (_, elems) in spread_arrays.iter_mut() {
+                while elems.last() == Some(&Value::Null) {
+                    elems.pop();
+                }
+            }
+        }
+        let positions: Vec<usize> = if options.spread_policy == SpreadPolicy::SkipNull {
+            (0..array_len)
+                .filter(|&i| {
+                    spread_arrays
+                        .iter()
+                        .all(|(_, elems)| !elems.get(i).unwrap_or(&Value::Null).is_null())
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        // a product too large to count exceeds any cap
+        let len = match options.spread_policy {
+            SpreadPolicy::Zip => Some(array_len),
+            SpreadPolicy::SkipNull => Some(positions.len()),
+            SpreadPolicy::Pad => Some(
+                spread_arrays
+                    .iter()
+                    .map(|(_, elems)| elems.len())
+                    .max()
+                    .unwrap_or_default(),
+            ),
+            SpreadPolicy::Product => spread_arrays
+                .iter()
+                .try_fold(1usize, |len, (_, elems)| len.checked_mul(elems.len())),
+        };
+        let len = match (len, options.max_array_elements) {
+            (Some(len), Some(max)) if len <= max => len,
+            (Some(len), None) => len,
+            (len, max) => bail!(
+                "Failed to split object to array; the array would have {} elements, more than the max_array_elements cap of {}",
+                len.map_or("too many".to_string(), |len| len.to_string()),
+                max.unwrap_or(usize::MAX)
+            ),
+        };
+        Ok(SplitObjects {
+            parent_obj,
+            spread_arrays,
+            spread_policy: options.spread_policy,
+            trim_trailing_nulls,
+            positions,
+            index_pointers: vec![],
+            len,
+            position: 0,
+        })
+    }
+
+    // sets the `@index` fields of the objects, pointers relative to the parent object
+    pub(crate) fn with_index_pointers(mut self, index_pointers: Vec<String>) -> Self {
+        self.index_pointers = index_pointers;
+        self
+    }
+
+    // an iterator that yields no objects
+    pub(crate) fn empty() -> Self {
+        SplitObjects {
+            parent_obj: Value::Null,
+            spread_arrays: vec![],
+            spread_policy: SpreadPolicy::Zip,
+            trim_trailing_nulls: false,
+            positions: vec![],
+            index_pointers: vec![],
+            len: 0,
+            position: 0,
+        }
+    }
+
+    // builds the next object from `obj` in place of the parent object, the spread arrays are taken
+    // out of the parent object so their fields in `obj` are replaced with the next elements
+    pub(crate) fn split_next(&mut self, mut obj: Value) -> Option<Value> {
+        if self.position >= self.len {
+            return None;
+        }
+        let array_lens: Vec<usize> = self
+            .spread_arrays
+            .iter()
+            .map(|(_, elems)| elems.len())
+            .collect();
+        let indexes = match self.spread_policy {
+            SpreadPolicy::Zip | SpreadPolicy::Pad => vec![self.position; array_lens.len()],
+            SpreadPolicy::SkipNull => vec![self.positions[self.position]; array_lens.len()],
+            SpreadPolicy::Product => product_index(self.position, &array_lens),
+        };
+        for pointer in &self.index_pointers {
+            if let Some(field) = obj.pointer_mut(pointer) {
+                *field = Value::from(self.position);
+            }
+        }
+        for ((path, elems), i) in self.spread_arrays.iter().zip(indexes) {
+            match elems.get(i) {
+                None if self.trim_trailing_nulls => remove_pointer(&mut obj, path),
+                elem => {
+                    if let Some(field) = obj.pointer_mut(path) {
+                        *field = elem.cloned().unwrap_or(Value::Null);
+                    }
+                }
+            }
+        }
+        self.position += 1;
+        Some(obj)
+    }
+
+    // the parent object the spread arrays were taken out of
+    pub(crate) fn parent_obj(&self) -> &Value {
+        &self.parent_obj
+    }
+
+    // the index of the next object
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Iterator for SplitObjects {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let obj = self.parent_obj.clone();
+        self.split_next(obj)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.position;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for SplitObjects {}
+
+// removes the field the JSON pointer points to from its parent object, if any
+fn remove_pointer(value: &mut Value, pointer: &str) {
+    if let Some((parent, token)) = pointer.rsplit_once('/') {
+        if let Some(Value::Object(parent_obj)) = value.pointer_mut(parent) {
+            parent_obj.remove(&unescape_pointer_key(token));
+        }
+    }
+}
+
+// replaces every empty object and empty array with null, nested ones first, so a value holding only
+// empty values isn't empty anymore, example {"a": {}, "b": []} -> {"a": null, "b": null}
+pub fn empty_to_null(value: &mut Value) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => obj.values_mut().for_each(empty_to_null),
+        Value::Array(values) if !values.is_empty() => values.iter_mut().for_each(empty_to_null),
+        Value::Object(_) | Value::Array(_) => *value = Value::Null,
+        _ => {}
+    }
+}
+
+// flattens nested arrays into a single level array, example [[1, 2], [3, [4]]] -> [1, 2, 3, 4]
+fn flatten_array(values: Vec<Value>) -> Vec<Value> {
+    values.into_iter().fold(vec![], |mut flattened, value| {
+        match value {
+            Value::Array(nested_values) => flattened.extend(flatten_array(nested_values)),
+            value => flattened.push(value),
+        }
+        flattened
+    })
+}
+
+// returns the indexes of the n-th combination of elements of arrays with the passed lengths, the
+// last array index varies the fastest, example: n = 1, [2, 2] -> [0, 1], n = 2, [2, 2] -> [1, 0]
+fn product_index(mut n: usize, array_lens: &[usize]) -> Vec<usize> {
+    let mut indexes = vec![0; array_lens.len()];
+    for (index, &len) in indexes.iter_mut().zip(array_lens).rev() {
+        *index = n % len;
+        n /= len;
+    }
+    indexes
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_fun_call, clippy::needless_borrows_for_generic_args)]
+mod tests {
+    use super::*;
+    use once_cell::sync::Lazy;
+    use serde_json::{json, Value};
+    use std::fs;
+    use std::sync::Mutex;
+
+    const OUTPUT_JSON_FILES_DIR: &str = "./test/output";
+    static INPUT_JSON_FILE: Lazy<Mutex<Value>> = Lazy::new(|| {
+        let input = fs::read_to_string("./test/input.json").expect("Unable to read input file");
+        Mutex::new(serde_json::from_str(&input).expect("Unable to parse input json file to value"))
+    });
+
+    #[test]
+    fn test_resolve_output_field_value_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+
+        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+
+        // regular field
+        input_path_tokens.push_back("ids");
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            Value::from(vec!["34554543", "7643534", "512342"])
+        );
+
+        // nested field
+        input_path_tokens.extend(["product", "details", "name"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::from("Red Shoes"));
+
+        // field in an array
+        input_path_tokens.extend(["order", "shipments", "tracking_number"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::from(vec!["1234567", "98776"]));
+
+        // field in an array of arrays
+        input_path_tokens.extend(["order", "shipments", "items"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            Value::from(vec![
+                json!({
+                  "sku": "SKU-123",
+                  "quantity": 4
+                }),
+                json!(    {
+                  "sku": "SKU-343",
+                  "quantity": 3
+                }),
+                json!({
+                  "sku": "SKU-1453",
+                  "quantity": 1
+                }),
+                json!({
+                  "sku": "SKU-543",
+                  "quantity": 1
+                })
+            ])
+        );
+
+        // field in an array of arrays of objs
+        input_path_tokens.extend(["order", "shipments", "items", "sku"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            Value::from(vec!["SKU-123", "SKU-343", "SKU-1453", "SKU-543"])
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_ok_flattened_input() {
+        let input = json!({
+            "order.po_number": "573832",
+            "order": {"id": "nested", "shipment.tracking_number": "1234567"},
+            "order.id": "flattened"
+        });
+        let options = TransformOptions {
+            flattened_input: true,
+            ..Default::default()
+        };
+
+        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+
+        // flattened key at the root
+        input_path_tokens.extend(["order", "po_number"]);
+        let result = resolve_output_field_value(&mut input_path_tokens, &input, &options)
+            .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::from("573832"));
+
+        // nested lookup takes precedence
+        input_path_tokens.extend(["order", "id"]);
+        let result = resolve_output_field_value(&mut input_path_tokens, &input, &options)
+            .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::from("nested"));
+
+        // flattened key in a nested object
+        input_path_tokens.extend(["order", "shipment", "tracking_number"]);
+        let result = resolve_output_field_value(&mut input_path_tokens, &input, &options)
+            .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::from("1234567"));
+
+        // flattened lookup is disabled by default
+        input_path_tokens.extend(["order", "po_number"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_ok_optional() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let input_with_null = json!({"order": {"discount": null}});
+
+        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+
+        // missing optional field
+        input_path_tokens.extend(["order", "discount?", "code"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::Null);
+
+        // null optional field
+        input_path_tokens.extend(["order", "discount?", "code"]);
+        let result = resolve_output_field_value(
+            &mut input_path_tokens,
+            &input_with_null,
+            &Default::default(),
+        )
+        .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::Null);
+
+        // present optional field
+        input_path_tokens.extend(["product", "details?", "name"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Value::from("Red Shoes"));
+
+        // array elements missing the optional field are skipped
+        input_path_tokens.extend(["order", "shipments", "carrier?", "name"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), json!([]));
+
+        // only the marked field is optional
+        input_path_tokens.extend(["order?", "discount", "code"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_ok_passthrough() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+
+        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+        input_path_tokens.extend(["product", "**"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            json!({
+                "id": "654654",
+                "length": 50,
+                "alternative_size": 33,
+                "details": {"name": "Red Shoes", "manufacture": "company"}
+            })
+        );
+
+        input_path_tokens.extend(["**"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert_eq!(result.unwrap(), input);
+
+        input_path_tokens.extend(["product", "**", "id"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Bad path format; ** should be the last token of the path"
+        );
+    }
+
+    #[test]
+    fn test_parse_path_token() {
+        let token = |field_name, optional, embedded_json| PathToken {
+            field_name,
+            optional,
+            embedded_json,
+            predicate: None,
+        };
+        assert_eq!(
+            parse_path_token("discount"),
+            token("discount", false, false)
+        );
+        assert_eq!(
+            parse_path_token("discount?"),
+            token("discount", true, false)
+        );
+        assert_eq!(parse_path_token("payload~"), token("payload", false, true));
+        assert_eq!(parse_path_token("payload~?"), token("payload", true, true));
+        assert_eq!(parse_path_token("payload?~"), token("payload", true, true));
+        assert_eq!(
+            parse_path_token("[\"discount?\"]"),
+            token("discount?", false, false)
+        );
+        assert_eq!(
+            parse_path_token("items[quantity > #1]?"),
+            PathToken {
+                predicate: Some("quantity > #1"),
+                ..token("items", true, false)
+            }
+        );
+        assert_eq!(parse_path_token("[order]"), token("[order]", false, false));
+        assert_eq!(
+            parse_path_token("items[?active]"),
+            PathToken {
+                predicate: Some("active"),
+                ..token("items", false, false)
+            }
+        );
+        assert_eq!(parse_path_token("tags[0]"), token("tags[0]", false, false));
+        assert_eq!(parse_path_token("tags[0]?"), token("tags[0]", true, false));
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_predicate() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let resolve = |path: &str| {
+            let mut path_tokens: LinkedList<&str> = split_path(path).unwrap().into_iter().collect();
+            resolve_output_field_value(&mut path_tokens, &input, &Default::default())
+                .map(Cow::into_owned)
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve("/order/shipments/items[quantity > #1]/sku"),
+            Ok(json!(["SKU-123", "SKU-343"]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/items[quantity == #1]/sku"),
+            Ok(json!(["SKU-1453", "SKU-543"]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/items[sku != 'SKU-123']/quantity"),
+            Ok(json!([3, 1, 1]))
+        );
+        assert_eq!(
+            resolve("/order/shipments[tracking_number == '98776']/items/sku"),
+            Ok(json!(["SKU-1453", "SKU-543"]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/items[quantity >= #5]/sku"),
+            Ok(json!([]))
+        );
+        // an equality predicate matched by a single element selects it
+        assert_eq!(
+            resolve("/order/shipments/items[sku == 'SKU-343']/quantity"),
+            Ok(json!(3))
+        );
+        assert_eq!(
+            resolve("/order/shipments[tracking_number == '98776']/tracking_number"),
+            Ok(json!("98776"))
+        );
+        assert_eq!(
+            resolve("/order/shipments/items[sku == 'SKU-999']/quantity"),
+            Ok(json!([]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/items[quantity > #3]/sku"),
+            Ok(json!(["SKU-123"]))
+        );
+        assert_eq!(
+            resolve("/product[length < #10]/id"),
+            Err("Failed to resolve mapping value; field product doesn't satisfy the predicate length < #10: {\"alternative_size\":33,\"details\":{\"manufacture\":\"company\",\"name\":\"Red Shoes\"},\"id\":\"654654\",\"length\":50}".to_string())
+        );
+        assert_eq!(
+            resolve("/order/shipments/items[quantity >]/sku"),
+            Err("Bad condition format; condition should be an operand or two operands compared with an operator: quantity >".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_bracketed_key() {
+        let input = json!({
+            "tags[0]": "sale",
+            "items": [{"sku": "a", "active": true}, {"sku": "b", "active": false}]
+        });
+        let resolve = |path: &str| {
+            let mut path_tokens: LinkedList<&str> = split_path(path).unwrap().into_iter().collect();
+            resolve_output_field_value(&mut path_tokens, &input, &Default::default())
+                .map(Cow::into_owned)
+                .map_err(|err| err.to_string())
+        };
+
+        // brackets that hold neither a comparison nor a `?` condition are part of the key
+        assert_eq!(resolve("/tags[0]"), Ok(json!("sale")));
+        assert_eq!(resolve("/items[?active]/sku"), Ok(json!(["a"])));
+        assert_eq!(resolve("/items[sku == 'b']/active"), Ok(json!(false)));
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_embedded_json() {
+        let input = json!({
+            "payload": "{\"id\": 5, \"tags\": [\"a\", \"b\"]}",
+            "events": [{"data": "{\"id\": 1}"}, {"data": "{\"id\": 2}"}, {"data": null}],
+            "bad": "{id",
+            "number": 5
+        });
+        let resolve = |path: &str| {
+            let mut path_tokens: LinkedList<&str> = split_path(path).unwrap().into_iter().collect();
+            resolve_output_field_value(&mut path_tokens, &input, &Default::default())
+                .map(Cow::into_owned)
+        };
+
+        assert_eq!(resolve("/payload~/id").unwrap(), json!(5));
+        assert_eq!(resolve("/payload~/tags").unwrap(), json!(["a", "b"]));
+        assert_eq!(resolve("/events/data~?/id").unwrap(), json!([1, 2]));
+        assert_eq!(resolve("/missing~?/id").unwrap(), Value::Null);
+        assert_eq!(
+            resolve("/bad~/id").err().unwrap().to_string(),
+            "Failed to resolve mapping value; couldn't parse field bad as JSON: key must be a string at line 1 column 2"
+        );
+        assert_eq!(
+            resolve("/number~/id").err().unwrap().to_string(),
+            "Failed to resolve mapping value; field number should be a JSON string to be parsed: 5"
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_present_null_and_absent() {
+        let input = json!({"order": {"discount": null, "id": "34554543", "notes": []}});
+        let resolve = |tokens: &[&'static str]| {
+            let mut path_tokens: LinkedList<&str> = tokens.iter().copied().collect();
+            resolve_output_field_value(&mut path_tokens, &input, &Default::default())
+                .map(Cow::into_owned)
+                .map_err(|err| err.to_string())
+        };
+
+        // present but null fields, and the fields under them, are null
+        assert_eq!(resolve(&["order", "discount"]), Ok(Value::Null));
+        assert_eq!(resolve(&["order", "discount", "code"]), Ok(Value::Null));
+
+        // absent fields fail to resolve
+        assert!(resolve(&["order", "coupon"])
+            .unwrap_err()
+            .starts_with("Failed to resolve mapping value; couldn't find field name coupon"));
+        assert!(resolve(&["order", "coupon", "code"]).is_err());
+        assert_eq!(
+            resolve(&["order", "id", "code"]),
+            Err(
+                "Failed to resolve mapping value; couldn't find field name code in the string \"34554543\""
+                    .to_string()
+            )
+        );
+        assert_eq!(resolve(&["order", "notes", "code"]), Ok(json!([])));
+
+        // unless they are optional
+        assert_eq!(resolve(&["order", "coupon?", "code"]), Ok(Value::Null));
+        assert_eq!(resolve(&["order", "id", "code?"]), Ok(Value::Null));
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_wildcard() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let resolve = |path: &str| {
+            let mut path_tokens: LinkedList<&str> = split_path(path).unwrap().into_iter().collect();
+            resolve_output_field_value(&mut path_tokens, &input, &Default::default())
+                .map(Cow::into_owned)
+                .map_err(|err| err.to_string())
+        };
+        let skus = json!(["SKU-123", "SKU-343", "SKU-1453", "SKU-543"]);
+
+        // every wildcard iterates a level, the SKUs of every item of every shipment
+        assert_eq!(resolve("/order/shipments/*/items/*/sku"), Ok(skus.clone()));
+        // without wildcards the nested arrays are flattened implicitly
+        assert_eq!(resolve("/order/shipments/items/sku"), Ok(skus));
+        // a single wildcard keeps one array per shipment
+        assert_eq!(
+            resolve("/order/shipments/*/items/sku"),
+            Ok(json!([["SKU-123", "SKU-343"], ["SKU-1453", "SKU-543"]]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/*/tracking_number"),
+            Ok(json!(["1234567", "98776"]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/*/items/*/quantity"),
+            Ok(json!([4, 3, 1, 1]))
+        );
+        // the values of an object, in key order
+        assert_eq!(
+            resolve("/product/details/*"),
+            Ok(json!(["company", "Red Shoes"]))
+        );
+        assert!(resolve("/order/shipments/*/items/*/color").is_err());
+        assert_eq!(
+            resolve("/order_id/*"),
+            Err("Failed to resolve mapping value; * should iterate an array or an object, not a string: \"34554543\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_strict_array_access() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let options = TransformOptions {
+            strict_array_access: true,
+            ..Default::default()
+        };
+        let resolve = |path: &str| {
+            let mut path_tokens: LinkedList<&str> = split_path(path).unwrap().into_iter().collect();
+            resolve_output_field_value(&mut path_tokens, &input, &options)
+                .map(Cow::into_owned)
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(
+            resolve("/order/shipments/tracking_number"),
+            Err("Failed to resolve mapping value; couldn't find field name tracking_number in an array with the strict_array_access option, its elements are iterated with the * wildcard, example */tracking_number".to_string())
+        );
+        assert!(resolve("/order/shipments/*/items/sku").is_err());
+        assert_eq!(
+            resolve("/order/shipments/*/tracking_number"),
+            Ok(json!(["1234567", "98776"]))
+        );
+        assert_eq!(
+            resolve("/order/shipments/*/items/*/quantity"),
+            Ok(json!([4, 3, 1, 1]))
+        );
+        // the arrays themselves and the elements selected by an equality predicate are not iterated
+        assert_eq!(
+            resolve("/ids"),
+            Ok(json!(["34554543", "7643534", "512342"]))
+        );
+        assert_eq!(
+            resolve("/order/shipments[tracking_number == '98776']/tracking_number"),
+            Ok(json!("98776"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_err() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+
+        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+
+        // field in an obj
+        input_path_tokens.push_back("idsss");
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            anyhow!(
+                "Failed to resolve mapping value; couldn't find field name idsss in the obj {}",
+                serde_json::to_string_pretty(&input).unwrap()
+            )
+            .to_string()
+        );
+
+        // field in an array of objs
+        input_path_tokens.extend(["order", "shipments", "tracking_nomber"]);
+        let result =
+            resolve_output_field_value(&mut input_path_tokens, &input, &Default::default())
+                .map(Cow::into_owned);
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            anyhow!(
+                "Failed to resolve mapping value; couldn't find field name tracking_nomber in the obj {}",
+                serde_json::to_string_pretty(&json!({
+        "tracking_number" : "1234567",
+        "items": [
+          {
+            "sku": "SKU-123",
+            "quantity": 4
+          },
+          {
+            "sku": "SKU-343",
+            "quantity": 3
+          }
+        ]
+      })).unwrap()).to_string());
+    }
+
+    #[test]
+    fn test_resolve_output_field_value_err_context_limit() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let options = TransformOptions {
+            error_context_limit: Some(12),
+            ..Default::default()
+        };
+
+        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+        input_path_tokens.push_back("idsss");
+        let result = resolve_output_field_value(&mut input_path_tokens, &input, &options);
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Failed to resolve mapping value; couldn't find field name idsss in the obj {\n  \"ids\": [..."
+        );
+
+        assert_eq!(truncate_context("Zürich".to_string(), &options), "Zürich");
+        assert_eq!(
+            truncate_context("Zürich, Zürich".to_string(), &options),
+            "Zürich, Züri..."
+        );
+        assert_eq!(
+            truncate_context("Zürich, Zürich".to_string(), &Default::default()),
+            "Zürich, Zürich"
+        );
+
+        let mut output = json!({"rows": {"@each": "/order", "id": "id"}});
+        let err = traverse_mut(&input, &mut output, "", "", &options)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to resolve @each; /order should resolve to an array: {\"po_number\"..."
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = fs::read_to_string(&format!("{}/default.json", OUTPUT_JSON_FILES_DIR))
+            .expect("Unable to read file");
+        let mut output: Value =
+            serde_json::from_str(&output).expect("Unable to parse input json file to value");
+
+        let result = traverse_mut(
+            &input,
+            output.get_mut(0).unwrap(),
+            "",
+            "",
+            &Default::default(),
+        );
+        let expected_transformed_output = fs::read_to_string(&format!(
+            "{}/transformed/default.json",
+            OUTPUT_JSON_FILES_DIR
+        ))
+        .expect(&format!(
+            "Unable to read file {}/transformed/default.json",
+            OUTPUT_JSON_FILES_DIR
+        ));
+        let expected_transformed_output: Value = serde_json::from_str(&expected_transformed_output)
+            .expect(&format!(
+                "Unable to parse file {}/transformed/default.json",
+                OUTPUT_JSON_FILES_DIR
+            ));
+        assert!(result.is_ok());
+        assert_eq!(output, expected_transformed_output);
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_arrays() {
+        let input = json!({"geo": {"lat": 52.52, "lng": 13.405}, "ids": [{"n": 1}, {"n": 2}]});
+        let mut output = json!({
+            "coordinates": ["/geo/lat", "/geo/lng"],
+            "mixed": ["'point'", "/ids", {"lat": "/geo/lat"}, [], ["/geo/lng"]],
+            "rows": {"@each": "/ids", "pair": ["../geo/lat", "@sibling:n"]}
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(
+            output,
+            json!({
+                "coordinates": [52.52, 13.405],
+                "mixed": ["point", [{"n": 1}, {"n": 2}], {"lat": 52.52}, [], [13.405]],
+                "rows": [{"pair": [52.52, 1]}, {"pair": [52.52, 2]}]
+            })
+        );
+
+        let mut output = json!({"coordinates": ["/geo/lat", "/geo/alt"]});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/coordinates/1"
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_interpolated_keys() {
+        let input = json!({"order": {"id": 1, "year": "2024", "tags": ["a"]}});
+        let mut output =
+            json!({"order_${/order/id}": {"id": "/order/id", "y_${'20}24'}": "/order/year"}});
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(output, json!({"order_1": {"id": 1, "y_20}24": "2024"}}));
+
+        let err = |mut output: Value| {
+            traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap()
+        };
+        let collision =
+            err(json!({"order": {"order_${/order/id}": "/order/id", "order_1": "/order/id"}}));
+        assert_eq!(
+            collision.to_string(),
+            "Bad key format; the interpolated key order_${/order/id} collides with another key order_1 of the object"
+        );
+        assert_eq!(
+            collision.downcast_ref::<FieldError>().unwrap().pointer,
+            "/order/order_${~1order~1id}"
+        );
+        assert_eq!(
+            err(json!({"a_${/order/id}": 1, "a_${'1'}": 2})).to_string(),
+            "Bad key format; the interpolated key a_${/order/id} collides with another key a_1 of the object"
+        );
+        assert_eq!(
+            err(json!({"order_${/order/id": "/order/id"})).to_string(),
+            "Bad key format; ${ is not closed with } in the key order_${/order/id"
+        );
+        assert_eq!(
+            err(json!({"order_${/order/tags}": "/order/id"})).to_string(),
+            "Failed to resolve the key order_${/order/tags}; /order/tags should resolve to a string, a number or a bool: [\"a\"]"
+        );
+        assert!(err(json!({"order_${/order/missing}": "/order/id"}))
+            .downcast_ref::<FieldError>()
+            .is_some());
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_deep() {
+        let input = json!({"id": 1});
+        let mut output = json!({"id": "/id", "name": "'deep'"});
+        let mut expected = json!({"id": 1, "name": "deep"});
+        for _ in 0..500 {
+            output = json!({"nested": output, "//note": "500 levels deep"});
+            expected = json!({ "nested": expected });
+        }
+        let mut missing = json!({"id": "/missing"});
+        for _ in 0..500 {
+            missing = json!({ "nested": missing });
+        }
+
+        // a 1 MiB stack, a walk that recurses per object level overflows it in debug builds
+        std::thread::Builder::new()
+            .stack_size(1024 * 1024)
+            .spawn(move || {
+                let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+                assert!(result.is_ok());
+                assert_eq!(output, expected);
+
+                let err = traverse_mut(&input, &mut missing, "", "", &Default::default())
+                    .err()
+                    .unwrap();
+                assert_eq!(
+                    err.downcast_ref::<FieldError>().unwrap().pointer,
+                    format!("{}/id", "/nested".repeat(500))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_comments() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let mut output = json!({
+            "order": {
+                "//note": "this maps the tracking numbers",
+                "trackings": "/order/shipments/tracking_number",
+                "product": {
+                    "//": ["not", "a", "mapping"],
+                    "id": "/product/id"
+                }
+            }
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "order": {
+                    "trackings": ["1234567", "98776"],
+                    "product": {
+                        "id": "654654"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_conditions() {
+        let input = json!({"order": {"id": 1, "total": 150, "status": "shipped"}});
+        let mut output = json!({
+            "order": {
+                "id": "/order/id",
+                "large": {
+                    "@when": "/order/total > #100",
+                    "total": "/order/total"
+                },
+                "pending": {
+                    "@when": "/order/status == 'pending'",
+                    "status": "/order/status"
+                },
+                "refund": {
+                    "@when": "/order/refund",
+                    "amount": "/order/refund/amount"
+                }
+            }
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(output, json!({"order": {"id": 1, "large": {"total": 150}}}));
+    }
+
+    #[test]
+    fn test_traverse_mut_err_conditions() {
+        let input = json!({"order": {"total": "150"}});
+        let mut output = json!({"order": {"large": {"@when": "/order/total > #100"}}});
+
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to evaluate condition; can't compare \"150\" > 100"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/order/large/@when"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_traverse_mut_ok_time_directives() {
+        let input = json!({"order": {"id": 1}});
+        let mut output = json!({
+            "order": {"id": "/order/id", "created_at": "@now", "created_at_unix": "@now:unix"}
+        });
+        let options = TransformOptions {
+            enable_time: true,
+            now: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(86_400)),
+            ..Default::default()
+        };
+
+        let result = traverse_mut(&input, &mut output, "", "", &options);
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "order": {
+                    "id": 1,
+                    "created_at": "1970-01-02T00:00:00Z",
+                    "created_at_unix": 86_400
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_split_alternatives() {
+        assert_eq!(split_alternatives("/a/b"), vec!["/a/b"]);
+        assert_eq!(split_alternatives(" /a "), vec![" /a "]);
+        assert_eq!(split_alternatives("/a || /b||'c'"), vec!["/a", "/b", "'c'"]);
+        assert_eq!(
+            split_alternatives("/[\"a||b\"] || 'x || y'"),
+            vec!["/[\"a||b\"]", "'x || y'"]
+        );
+        assert_eq!(split_alternatives("/a |b"), vec!["/a |b"]);
+        assert_eq!(
+            split_alternatives("f(/a || /b) || 'c'"),
+            vec!["f(/a || /b)", "'c'"]
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_traverse_mut_ok_functions() {
+        let input = json!({"notes": "ship PO-1234 today", "memo": "no order"});
+        let mut output = json!({
+            "po_number": r"regex(/notes, 'PO-(\d+)')",
+            "fallback": r"regex(/memo, 'PO-(\d+)') || 'unknown'"
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(output, json!({"po_number": "1234", "fallback": "unknown"}));
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_fallbacks() {
+        let input = json!({"a": {"secondary": "second", "tertiary": null}});
+        let mut output = json!({
+            "literal": "/a/primary || /a/missing || 'unknown'",
+            "path": "/a/primary || /a/secondary || 'unknown'",
+            "null": "/a/tertiary || /a/secondary"
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({"literal": "unknown", "path": "second", "null": null})
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_fallbacks() {
+        let input = json!({"a": {}});
+        let mut output = json!({"value": "/a/primary || /a/secondary"});
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_err());
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Failed to resolve mapping value; couldn't find field name secondary in the obj {}"
+        );
+
+        let options = TransformOptions {
+            default_on_missing: Some(json!("N/A")),
+            ..Default::default()
+        };
+        let result = traverse_mut(&input, &mut output, "", "", &options);
+        assert!(result.is_ok());
+        assert_eq!(output, json!({"value": "N/A"}));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let root = json!({"order": {"id": 1}, "id": 0});
+        let shipment = json!({"id": 2, "tracking": "a"});
+        let item = json!({"sku": "x"});
+        let roots = [&root];
+        let mut contexts = Contexts::new(&roots);
+        contexts.push(&shipment);
+        contexts.push(&item);
+        let resolve = |path| resolve_path(&contexts, path, &Default::default());
+
+        assert_eq!(resolve("/order/id").unwrap().unwrap(), json!(1));
+        assert_eq!(resolve("sku").unwrap().unwrap(), json!("x"));
+        assert_eq!(resolve("../tracking").unwrap().unwrap(), json!("a"));
+        assert_eq!(resolve("../../order/id").unwrap().unwrap(), json!(1));
+        assert_eq!(resolve("").unwrap().unwrap(), item);
+        assert!(resolve("tracking").unwrap().is_err());
+        assert_eq!(
+            resolve("../../../id").err().unwrap().to_string(),
+            "Bad path format; .. goes above the input root: ../../../id"
+        );
+        assert_eq!(
+            resolve("/../id").err().unwrap().to_string(),
+            "Bad path format; .. goes above the input root: /../id"
+        );
+        assert_eq!(
+            resolve("sku/../id").err().unwrap().to_string(),
+            "Bad path format; .. should only lead relative paths: sku/../id"
+        );
+        assert_eq!(
+            resolve_path(&[&root], "id", &Default::default())
+                .unwrap()
+                .unwrap(),
+            json!(0)
+        );
+
+        // the input roots are tried in order
+        let roots = [&shipment, &root];
+        let layers = Contexts::new(&roots);
+        let resolve = |path| resolve_path(&layers, path, &Default::default());
+        assert_eq!(resolve("/id").unwrap().unwrap(), json!(2));
+        assert_eq!(resolve("/order/id").unwrap().unwrap(), json!(1));
+        assert!(resolve("/sku").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_traverse_mut_relative_paths() {
+        let input = json!({"id": 0, "order": {"id": 1, "shipments": [{"id": 2}, {"id": 3}]}});
+        let template = json!({
+            "order_id": "order/id",
+            "rows": {"@each": "order/shipments", "id": "id", "order_id": "/order/id"}
+        });
+
+        let mut output = template.clone();
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({"order_id": 1, "rows": [{"id": 2, "order_id": 1}, {"id": 3, "order_id": 1}]})
+        );
+
+        let options = TransformOptions {
+            relative_paths: RelativePathPolicy::Strict,
+            ..Default::default()
+        };
+        let mut output = template;
+        let err = traverse_mut(&input, &mut output, "", "", &options)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad path format; the relative path order/id is outside of an @each body, it should start with /"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/order_id"
+        );
+
+        let mut output = json!({"rows": {"@each": "order/shipments", "id": "id"}});
+        let err = traverse_mut(&input, &mut output, "", "", &options)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad path format; the relative path order/shipments is outside of an @each body, it should start with /"
+        );
+
+        let mut output = json!({
+            "order_id": "/order/id || 'none'",
+            "rows": {"@each": "/order/shipments", "id": "id", "order_id": "../order/id"}
+        });
+        let result = traverse_mut(&input, &mut output, "", "", &options);
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({"order_id": 1, "rows": [{"id": 2, "order_id": 1}, {"id": 3, "order_id": 1}]})
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_each() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let mut output = json!({
+            "order": {
+                "po_number": "/order/po_number",
+                "rows": {
+                    "@each": "/order/shipments",
+                    "tracking_number": "tracking_number",
+                    "items": {
+                        "@each": "items",
+                        "//note": "combines shipment and item level fields",
+                        "sku": "sku",
+                        "tracking_number": "../tracking_number",
+                        "quantity": "@sibling:quantity",
+                        "po_number": "../../order/po_number",
+                        "large": {"@when": "quantity > #3", "quantity": "quantity"}
+                    }
+                }
+            }
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "order": {
+                    "po_number": "573832",
+                    "rows": [
+                        {
+                            "tracking_number": "1234567",
+                            "items": [
+                                {"sku": "SKU-123", "tracking_number": "1234567", "quantity": 4, "po_number": "573832", "large": {"quantity": 4}},
+                                {"sku": "SKU-343", "tracking_number": "1234567", "quantity": 3, "po_number": "573832"}
+                            ]
+                        },
+                        {
+                            "tracking_number": "98776",
+                            "items": [
+                                {"sku": "SKU-1453", "tracking_number": "98776", "quantity": 1, "po_number": "573832"},
+                                {"sku": "SKU-543", "tracking_number": "98776", "quantity": 1, "po_number": "573832"}
+                            ]
+                        }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_each() {
+        let input = json!({"order": {"id": 1, "shipments": [{"id": 2}]}});
+
+        let mut output = json!({"rows": {"@each": "/order/id", "id": "id"}});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to resolve @each; /order/id should resolve to an array: 1"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/rows/@each"
+        );
+
+        let mut output = json!({"rows": {"@each": "/order/shipments", "id": "../../id"}});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad path format; .. goes above the input root: ../../id"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/rows/id"
+        );
+
+        let mut output = json!({"id": "@sibling:id"});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @sibling format; it can only be used in an @each body: @sibling:id"
+        );
+
+        let mut output = json!({"rows": {"@each": "/order/shipments", "id": "@sibling:../id"}});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @sibling format; it should be followed by a field of the current element: @sibling:../id"
+        );
+
+        // the input roots of a layered transform are not `@each` elements
+        let layer = json!({"id": 2});
+        let mut output = json!({"id": "@sibling:id"});
+        let err = traverse_mut_in(
+            &Contexts::new(&[&layer, &input]),
+            &mut output,
+            "",
+            "",
+            &Default::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @sibling format; it can only be used in an @each body: @sibling:id"
+        );
+
+        // nor is an element pushed outside of an `@each` iteration
+        let element = json!({"id": 3});
+        let roots = [&input];
+        let mut contexts = Contexts::new(&roots);
+        contexts.push(&element);
+        let mut output = json!({"id": "@sibling:id"});
+        let err = traverse_mut_in(&contexts, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @sibling format; it can only be used in an @each body: @sibling:id"
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_group_by() {
+        let input = json!({"orders": [
+            {"region": "us", "amount": 10},
+            {"region": "eu", "amount": 5},
+            {"region": "us", "amount": 20}
+        ]});
+        let mut output = json!({
+            "amounts": {"@groupBy": "/orders", "key": "region", "value": "amount"},
+            "orders": {"@groupBy": "/orders", "key": "region"},
+            "by_amount": {"@groupBy": "/orders", "key": "amount", "value": "region"}
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "amounts": {"eu": [5], "us": [10, 20]},
+                "orders": {
+                    "eu": [{"region": "eu", "amount": 5}],
+                    "us": [{"region": "us", "amount": 10}, {"region": "us", "amount": 20}]
+                },
+                "by_amount": {"10": ["us"], "20": ["us"], "5": ["eu"]}
+            })
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_merge_by() {
+        let input = json!({"order": {"shipments": [
+            {"items": [{"sku": "SKU-123", "quantity": 4, "price": 1.5}, {"sku": "SKU-343", "quantity": 3, "price": 2}]},
+            {"items": [{"sku": "SKU-123", "quantity": 1, "price": 0.5}]}
+        ]}});
+        let mut output = json!({
+            "items": {
+                "@mergeBy": "/order/shipments/items",
+                "key": "sku",
+                "sum": ["quantity", "price"]
+            },
+            "skus": {"@mergeBy": "/order/shipments/items", "key": "sku"},
+            "missing": {"@mergeBy": "/order/returns?", "key": "sku", "sum": ["quantity"]}
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "items": [
+                    {"sku": "SKU-123", "quantity": 5, "price": 2.0},
+                    {"sku": "SKU-343", "quantity": 3, "price": 2}
+                ],
+                "skus": [
+                    {"sku": "SKU-123", "quantity": 4, "price": 1.5},
+                    {"sku": "SKU-343", "quantity": 3, "price": 2}
+                ],
+                "missing": []
+            })
+        );
+
+        let input = json!({"items": [
+            {"sku": 10, "quantity": 1},
+            {"sku": "10", "quantity": 2},
+            {"sku": 10, "quantity": 3}
+        ]});
+        let mut output =
+            json!({"items": {"@mergeBy": "/items", "key": "sku", "sum": ["quantity"]}});
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({"items": [{"sku": 10, "quantity": 4}, {"sku": "10", "quantity": 2}]})
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_merge_by() {
+        let input = json!({"items": [
+            {"sku": "a", "quantity": 1},
+            {"sku": "a", "quantity": "2"},
+            {"sku": "b"},
+            "c"
+        ]});
+        let err = |output: Value| {
+            let mut output = json!({ "merged": output });
+            let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap();
+            (
+                err.to_string(),
+                err.downcast_ref::<FieldError>().unwrap().pointer.clone(),
+            )
+        };
+
+        assert_eq!(
+            err(json!({"@mergeBy": "/items", "key": "sku", "sum": ["quantity"]})),
+            (
+                "Failed to resolve @mergeBy; the sum field quantity of the element {\"quantity\":\"2\",\"sku\":\"a\"} should be a number: \"2\"".to_string(),
+                "/merged/sum".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@mergeBy": "/items", "key": "sku", "sum": ["price"]})),
+            (
+                "Failed to resolve @mergeBy; couldn't find the sum field price in the element {\"quantity\":1,\"sku\":\"a\"}".to_string(),
+                "/merged/sum".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@mergeBy": "/items", "key": "sku"})),
+            (
+                "Failed to resolve @mergeBy; the element \"c\" should be an object".to_string(),
+                "/merged/@mergeBy".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@mergeBy": "/items", "key": "quantity"})),
+            (
+                "Failed to resolve @mergeBy; couldn't find the key quantity in the element {\"sku\":\"b\"}".to_string(),
+                "/merged/key".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@mergeBy": "/items", "key": "sku", "sum": "quantity"})),
+            (
+                "Bad @mergeBy format; sum should be an array of field names: \"quantity\""
+                    .to_string(),
+                "/merged/sum".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@mergeBy": "/items", "sum": ["quantity"]})),
+            (
+                "Bad @mergeBy format; missing the key field to merge by".to_string(),
+                "/merged".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_add_numbers() {
+        assert_eq!(add_numbers(&json!(1), &json!(2)), json!(3));
+        assert_eq!(add_numbers(&json!(-1), &json!(2)), json!(1));
+        assert_eq!(add_numbers(&json!(1.5), &json!(2)), json!(3.5));
+        assert_eq!(
+            add_numbers(&json!(u64::MAX), &json!(1)),
+            json!(u64::MAX as f64 + 1.0)
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_indexed() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let mut output = json!({
+            "trackings": {
+                "@indexed": "/order/shipments",
+                "prefix": "shipment_",
+                "field": "tracking_number"
+            },
+            "skus": {"@indexed": "/order/shipments/items/sku"},
+            "empty": {"@indexed": "/order/shipments/items[quantity > #10]", "prefix": "item_"},
+            "missing": {"@indexed": "/order/returns?", "prefix": "return_"}
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "trackings": {"shipment_0": "1234567", "shipment_1": "98776"},
+                "skus": {"0": "SKU-123", "1": "SKU-343", "2": "SKU-1453", "3": "SKU-543"},
+                "empty": {},
+                "missing": {}
+            })
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_filter() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let mut output = json!({
+            "skus": {
+                "@filter": "/order/shipments/items",
+                "where": "/quantity > #1",
+                "select": "sku"
+            },
+            "items": {"@filter": "/order/shipments/items", "where": "sku == 'SKU-1453'"},
+            "all": {"@filter": "/ids"},
+            "empty": {"@filter": "/order/shipments/items", "where": "quantity > #10", "select": "sku"},
+            "missing": {"@filter": "/order/returns?", "select": "sku"}
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        result.unwrap();
+        assert_eq!(
+            output,
+            json!({
+                "skus": ["SKU-123", "SKU-343"],
+                "items": [{"sku": "SKU-1453", "quantity": 1}],
+                "all": ["34554543", "7643534", "512342"],
+                "empty": [],
+                "missing": []
+            })
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_filter() {
+        let input = json!({"name": "Red Shoes", "items": [{"sku": "SKU-123", "quantity": 4}, {"quantity": 3}]});
+        let err = |output: Value| {
+            let mut output = json!({ "skus": output });
+            let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap();
+            (
+                err.to_string(),
+                err.downcast_ref::<FieldError>().unwrap().pointer.clone(),
+            )
+        };
+
+        assert_eq!(
+            err(json!({"@filter": "/items", "where": "quantity > #1", "select": "sku"})),
+            (
+                "Failed to resolve @filter; couldn't find the select sku in the element {\"quantity\":3}".to_string(),
+                "/skus/select".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@filter": "/items", "where": true})),
+            (
+                "Bad @filter format; where should be a condition: true".to_string(),
+                "/skus/where".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@filter": "/items", "where": "quantity >"})).1,
+            "/skus/where"
+        );
+        assert_eq!(
+            err(json!({"@filter": "/items", "field": "sku"})),
+            (
+                "Bad @filter format; unsupported field field, the supported fields are select and where".to_string(),
+                "/skus/field".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@filter": "/name"})),
+            (
+                "Failed to resolve @filter; /name should resolve to an array: \"Red Shoes\""
+                    .to_string(),
+                "/skus/@filter".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_default_object() {
+        let input = json!({"product": {"name": "Red Shoes", "price": 10}});
+        let mut output = json!({
+            "details": {
+                "@defaultObject": {"color": "unknown", "size": null},
+                "color": "/product/details/color",
+                "sizes": {"eu": "/product/details/size/eu", "us": "/product/details/size/us"}
+            },
+            "summary": {
+                "@defaultObject": {"name": "unknown"},
+                "name": "/product/name",
+                "price": "/product/price"
+            }
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        result.unwrap();
+        assert_eq!(
+            output,
+            json!({
+                "details": {"color": "unknown", "size": null},
+                "summary": {"name": "Red Shoes", "price": 10}
+            })
+        );
+
+        // some mappings resolve, the missing ones fail as usual or take the default_on_missing value
+        let partial = json!({
+            "product": {
+                "@defaultObject": {},
+                "name": "/product/name",
+                "color": "/product/details/color"
+            }
+        });
+        let err = traverse_mut(&input, &mut partial.clone(), "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/product/color"
+        );
+        let options = TransformOptions {
+            default_on_missing: Some(Value::Null),
+            ..Default::default()
+        };
+        let mut output = partial;
+        traverse_mut(&input, &mut output, "", "", &options).unwrap();
+        assert_eq!(
+            output,
+            json!({"product": {"name": "Red Shoes", "color": null}})
+        );
+
+        // the hard coded values and the directives don't count as found mappings
+        let mut output = json!({"details": {
+            "@defaultObject": {"color": "unknown"},
+            "color": "/product/details/color || /product/color",
+            "kind": "'shoe'",
+            "depth": "@depth"
+        }});
+        traverse_mut(&input, &mut output, "", "", &Default::default()).unwrap();
+        assert_eq!(output, json!({"details": {"color": "unknown"}}));
+
+        // the misses of a nested body count for the enclosing one
+        let nested = json!({"details": {
+            "@defaultObject": {"none": true},
+            "color": "/product/details/color",
+            "size": {"@defaultObject": {}, "eu": "/product/details/size/eu"}
+        }});
+        let mut output = nested.clone();
+        traverse_mut(&input, &mut output, "", "", &Default::default()).unwrap();
+        assert_eq!(output, json!({"details": {"none": true}}));
+        let input_with_size = json!({"product": {"details": {"size": {"eu": 40}}}});
+        let err = traverse_mut(
+            &input_with_size,
+            &mut nested.clone(),
+            "",
+            "",
+            &Default::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/details/color"
+        );
+        // a literal value equal to what a miss used to resolve to is kept
+        let input_with_marker = json!({"product": {"name": "\u{0}@missing\u{0}"}});
+        let mut output = json!({"name": {"@defaultObject": {}, "value": "/product/name"}});
+        traverse_mut(&input_with_marker, &mut output, "", "", &Default::default()).unwrap();
+        assert_eq!(output, json!({"name": {"value": "\u{0}@missing\u{0}"}}));
+
+        let mut output =
+            json!({"details": {"@defaultObject": "unknown", "color": "/product/color"}});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Bad @defaultObject format; it should be an object: \"unknown\""
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/details/@defaultObject"
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_zip() {
+        let input = json!({
+            "skus": ["SKU-123", "SKU-343", "SKU-1453"],
+            "quantities": [4, null, 1],
+            "prices": [12.5]
+        });
+        let transform = |output: Value, spread_policy: SpreadPolicy| {
+            let mut output = json!({ "items": output });
+            let options = TransformOptions {
+                spread_policy,
+                ..Default::default()
+            };
+            traverse_mut(&input, &mut output, "", "", &options).map(|_| output["items"].take())
+        };
+
+        assert_eq!(
+            transform(
+                json!({"@zip": {"sku": "/skus", "qty": "/quantities", "//": "comment"}}),
+                SpreadPolicy::Zip
+            )
+            .unwrap(),
+            json!([
+                {"sku": "SKU-123", "qty": 4},
+                {"sku": "SKU-343", "qty": null},
+                {"sku": "SKU-1453", "qty": 1}
+            ])
+        );
+        assert_eq!(
+            transform(
+                json!({"@zip": {"sku": "/skus", "qty": "/quantities"}}),
+                SpreadPolicy::SkipNull
+            )
+            .unwrap(),
+            json!([{"sku": "SKU-123", "qty": 4}, {"sku": "SKU-1453", "qty": 1}])
+        );
+        assert_eq!(
+            transform(
+                json!({"@zip": {"sku": "/skus", "price": "/prices"}}),
+                SpreadPolicy::Pad
+            )
+            .unwrap(),
+            json!([
+                {"sku": "SKU-123", "price": 12.5},
+                {"sku": "SKU-343", "price": null},
+                {"sku": "SKU-1453", "price": null}
+            ])
+        );
+        assert_eq!(
+            transform(
+                json!({"@zip": {"price": "/prices", "sku": "/skus"}}),
+                SpreadPolicy::Product
+            )
+            .unwrap(),
+            json!([
+                {"sku": "SKU-123", "price": 12.5},
+                {"sku": "SKU-343", "price": 12.5},
+                {"sku": "SKU-1453", "price": 12.5}
+            ])
+        );
+        assert_eq!(
+            transform(json!({"@zip": {"sku": "/returns?"}}), SpreadPolicy::Zip).unwrap(),
+            json!([])
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_zip() {
+        let input = json!({"skus": ["SKU-123", "SKU-343"], "prices": [12.5], "name": "Red Shoes"});
+        let err = |output: Value| {
+            let mut output = json!({ "items": output });
+            let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap();
+            (
+                err.to_string(),
+                err.downcast_ref::<FieldError>().unwrap().pointer.clone(),
+            )
+        };
+
+        assert_eq!(
+            err(json!({"@zip": {"sku": "/skus", "price": "/prices"}})),
+            (
+                "Failed to resolve @zip; the arrays should have the same length: price has 1, sku has 2".to_string(),
+                "/items/@zip".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@zip": {"sku": "/name"}})),
+            (
+                "Failed to resolve @zip; /name should resolve to an array: \"Red Shoes\""
+                    .to_string(),
+                "/items/@zip".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@zip": "/skus"})),
+            (
+                "Bad @zip format; it should be an object mapping field names to array paths: \"/skus\"".to_string(),
+                "/items/@zip".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@zip": {"sku": "/skus"}, "name": "/name"})),
+            (
+                "Bad @zip format; unsupported field name, the object should only have the @zip field".to_string(),
+                "/items/name".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_typeswitch() {
+        let template = json!({
+            "price": {
+                "@typeswitch": "/price",
+                "number": {"amount": "/price", "currency": "'USD'"},
+                "object": {"amount": "/price/amount", "currency": "/price/currency"},
+                "null": "'free'"
+            },
+            "lines": {
+                "@typeswitch": "/lines?",
+                "array": {"@each": "/lines", "sku": "sku"},
+                "null": []
+            }
+        });
+        let transform = |input: Value| {
+            let mut output = template.clone();
+            traverse_mut(&input, &mut output, "", "", &Default::default()).map(|_| output)
+        };
+
+        assert_eq!(
+            transform(json!({"price": 12.5, "lines": [{"sku": "SKU-123"}]})).unwrap(),
+            json!({
+                "price": {"amount": 12.5, "currency": "USD"},
+                "lines": [{"sku": "SKU-123"}]
+            })
+        );
+        assert_eq!(
+            transform(json!({"price": {"amount": 10, "currency": "EUR"}})).unwrap(),
+            json!({"price": {"amount": 10, "currency": "EUR"}, "lines": []})
+        );
+        assert_eq!(
+            transform(json!({"price": null})).unwrap(),
+            json!({"price": "free", "lines": []})
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_typeswitch() {
+        let input = json!({"price": "12.50", "amount": 1});
+        let err = |output: Value| {
+            let mut output = json!({ "price": output });
+            let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap();
+            (
+                err.to_string(),
+                err.downcast_ref::<FieldError>().unwrap().pointer.clone(),
+            )
+        };
+
+        assert_eq!(
+            err(json!({"@typeswitch": "/price", "number": "/price"})),
+            (
+                "Failed to resolve @typeswitch; /price resolves to a string, which has no branch: \"12.50\"".to_string(),
+                "/price/@typeswitch".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@typeswitch": "/cost", "number": "/cost"})),
+            (
+                "Failed to resolve mapping value; couldn't find field name cost in the obj {\n  \"amount\": 1,\n  \"price\": \"12.50\"\n}".to_string(),
+                "/price/@typeswitch".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@typeswitch": 1, "number": "/price"})),
+            (
+                "Bad @typeswitch format; it should be a mapping path: 1".to_string(),
+                "/price/@typeswitch".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@typeswitch": "/price", "integer": "/price"})),
+            (
+                "Bad @typeswitch format; unsupported field integer, the supported fields are null and boolean and number and string and array and object".to_string(),
+                "/price/integer".to_string()
+            )
+        );
+        // the selected branch errors carry their own pointer
+        assert_eq!(
+            err(json!({"@typeswitch": "/price", "string": {"amount": "/cost"}})).1,
+            "/price/amount"
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_indexed() {
+        let input = json!({"shipments": [{"tracking_number": "1234567"}, {"carrier": "ups"}]});
+        let err = |output: Value| {
+            let mut output = json!({ "trackings": output });
+            let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap();
+            (
+                err.to_string(),
+                err.downcast_ref::<FieldError>().unwrap().pointer.clone(),
+            )
+        };
+
+        assert_eq!(
+            err(json!({"@indexed": "/shipments", "field": "tracking_number"})),
+            (
+                "Failed to resolve @indexed; couldn't find the field tracking_number in the element {\"carrier\":\"ups\"}".to_string(),
+                "/trackings/field".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@indexed": "/shipments", "prefix": 1})),
+            (
+                "Bad @indexed format; prefix should be a string: 1".to_string(),
+                "/trackings/prefix".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@indexed": "/shipments", "key": "carrier"})),
+            (
+                "Bad @indexed format; unsupported field key, the supported fields are field and prefix".to_string(),
+                "/trackings/key".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_err_group_by() {
+        let input = json!({"total": 15, "orders": [{"region": "us", "amount": 10}, {"amount": 5}]});
+        let err = |output: Value| {
+            let mut output = json!({ "groups": output });
+            let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+                .err()
+                .unwrap();
+            (
+                err.to_string(),
+                err.downcast_ref::<FieldError>().unwrap().pointer.clone(),
+            )
+        };
+
+        assert_eq!(
+            err(json!({"@groupBy": "/orders", "key": "region", "value": "amount"})),
+            (
+                "Failed to resolve @groupBy; couldn't find the key region in the element {\"amount\":5}".to_string(),
+                "/groups/key".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@groupBy": "/orders", "key": "amount", "value": "region"})),
+            (
+                "Failed to resolve @groupBy; couldn't find the value region in the element {\"amount\":5}".to_string(),
+                "/groups/value".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@groupBy": "/orders", "value": "amount"})),
+            (
+                "Bad @groupBy format; missing the key field to group by".to_string(),
+                "/groups".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@groupBy": "/orders", "key": "region", "values": "amount"})),
+            (
+                "Bad @groupBy format; unsupported field values, the supported fields are key and value".to_string(),
+                "/groups/values".to_string()
+            )
+        );
+        assert_eq!(
+            err(json!({"@groupBy": "/total", "key": "region"})),
+            (
+                "Failed to resolve @groupBy; /total should resolve to an array: 15".to_string(),
+                "/groups/@groupBy".to_string()
+            )
+        );
+
+        let input = json!({"orders": [{"region": 10}, {"region": "10"}]});
+        let mut output = json!({"groups": {"@groupBy": "/orders", "key": "region"}});
+        let err = traverse_mut(&input, &mut output, "", "", &Default::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Failed to resolve @groupBy; the keys 10 and \"10\" name the same group"
+        );
+        assert_eq!(
+            err.downcast_ref::<FieldError>().unwrap().pointer,
+            "/groups/key"
+        );
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_quoted_keys() {
+        let input = json!({
+            "order": {
+                "a/b": {"value": 1},
+                "c.d": "dotted"
+            }
+        });
+        let mut output = json!({
+            "slashed": "/order/[\"a/b\"]/value",
+            "dotted": "/order/[\"c.d\"]"
+        });
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(output, json!({"slashed": 1, "dotted": "dotted"}));
+    }
+
+    #[test]
+    fn test_unquote_literal() {
+        assert_eq!(unquote_literal("'edi'"), Some("edi".to_string()));
+        assert_eq!(unquote_literal("''"), Some("".to_string()));
+        assert_eq!(unquote_literal("'O''Brien'"), Some("O'Brien".to_string()));
+        assert_eq!(unquote_literal("''''"), Some("'".to_string()));
+        assert_eq!(unquote_literal(r"'O\'Brien'"), Some("O'Brien".to_string()));
+        assert_eq!(
+            unquote_literal(r"'C:\\temp\\'"),
+            Some(r"C:\temp\".to_string())
+        );
+        assert_eq!(
+            unquote_literal(r"'PO-(\d+)'"),
+            Some(r"PO-(\d+)".to_string())
+        );
+        assert_eq!(unquote_literal(r"'a\\\''"), Some(r"a\'".to_string()));
+        assert_eq!(unquote_literal(r"'abc\'"), None);
+        assert_eq!(unquote_literal("'"), None);
+        assert_eq!(unquote_literal("/order/id"), None);
+    }
+
+    #[test]
+    fn test_traverse_mut_ok_quoted_literals() {
+        let input = json!({});
+        let mut output = json!({"customer": {
+            "name": "'O''Brien'",
+            "note": "'it''s ''quoted'''",
+            "nickname": r"'O\'B'",
+            "path": r"'C:\\temp\\' || /path",
+            "alias": r"/alias || 'a \'||\' b'"
+        }});
+
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({"customer": {
+                "name": "O'Brien",
+                "note": "it's 'quoted'",
+                "nickname": "O'B",
+                "path": r"C:\temp\",
+                "alias": "a '||' b"
+            }})
+        );
+    }
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(type_name(&json!(null)), "null");
+        assert_eq!(type_name(&json!(true)), "boolean");
+        assert_eq!(type_name(&json!(1.5)), "number");
+        assert_eq!(type_name(&json!("a")), "string");
+        assert_eq!(type_name(&json!([])), "array");
+        assert_eq!(type_name(&json!({})), "object");
+    }
+
+    #[test]
+    fn test_describe_types_ok() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let output = json!({
+            "[order]": {
+                "//note": "/not/a/mapping",
+                "...item_ids": "/ids",
+                "account_id": "/retailer/id",
+                "position": "@index",
+                "quantity": "/order/shipments/items/quantity",
+                "product": {
+                    "details": "/product/details",
+                    "length": "/product/length",
+                    "source": "'edi'"
                 }
-                visited.push_back(format_key(&clean_path(xpath)?, clean_key(key)?));
             }
-        }
+        });
+
+        let mut types = vec![];
+        let result = describe_types(&input, &output, "", "", &Default::default(), &mut types);
+        assert!(result.is_ok());
+        assert_eq!(
+            types,
+            vec![
+                ("/[order]/...item_ids".to_string(), "array".to_string()),
+                ("/[order]/account_id".to_string(), "string".to_string()),
+                ("/[order]/position".to_string(), "number".to_string()),
+                ("/[order]/product/details".to_string(), "object".to_string()),
+                ("/[order]/product/length".to_string(), "number".to_string()),
+                ("/[order]/product/source".to_string(), "string".to_string()),
+                ("/[order]/quantity".to_string(), "array".to_string()),
+            ]
+        );
     }
-    Ok(())
-}
 
-// takes an object that contain the spread arrays and convert it into array of the same object, each
-// takes one element from the array.
-pub fn split_obj_to_array(
-    output: &mut Value,
-    array_len: usize,
-    visited: &mut LinkedList<String>,
-    path_to_array_parent_obj: &str,
-    parent_obj_name: &str,
-) -> Result<()> {
-    // example: "/order/sub_order/details/trackings"
-    let mut path_to_spread_array = visited.pop_back().ok_or_else(|| {
-        anyhow!("Failed to split object to array; could not get path to the spread array")
-    })?;
-    let mut array_of_objs = vec![
-        output
-            .pointer(path_to_array_parent_obj)
-            .ok_or_else(|| anyhow!(
-                "Failed to split object to array; could not get path to the spread array"
-            ))?
-            .clone();
-        array_len
-    ];
-    // path_to_array_parent_obj example: "/order/sub_order/details"
-    while path_to_spread_array != path_to_array_parent_obj {
-        // example "/tracking"
-        let (_, array_path_from_parent_obj) = path_to_spread_array
-            .split_once(parent_obj_name)
-            .ok_or_else(|| {
-                anyhow!("Failed to split object to array; could not get path to the spread array")
-            })?;
-        for (i, obj) in array_of_objs.iter_mut().enumerate() {
-            let path = format_key(&path_to_spread_array, &i.to_string());
-            let elem = output.pointer(&path).unwrap_or(&Value::Null).clone();
-            let pretty_print_obj = to_string_pretty(&obj)?;
-            *obj.pointer_mut(array_path_from_parent_obj).ok_or_else(|| {
-                anyhow!(
-                    "Failed to split object to array; could not find {} in {}",
-                    &array_path_from_parent_obj,
-                    &pretty_print_obj
-                )
-            })? = elem;
-        }
+    #[test]
+    fn test_traverse_mut_ok_default_on_missing() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+        let mut output = json!({
+            "order": {
+                "id": "/order_id",
+                "discount": "/order/discount/code",
+                "tracking": "/order/shipments/tracking_nomber"
+            }
+        });
+        let options = TransformOptions {
+            default_on_missing: Some(Value::Null),
+            ..Default::default()
+        };
 
-        path_to_spread_array = visited.pop_back().ok_or_else(|| {
-            anyhow!("Failed to split object to array; failed to get path token from the stack")
-        })?;
+        let result = traverse_mut(&input, &mut output, "", "", &options);
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            json!({
+                "order": {
+                    "id": "34554543",
+                    "discount": null,
+                    "tracking": null
+                }
+            })
+        );
     }
-    *output
-        .pointer_mut(path_to_array_parent_obj)
-        .ok_or_else(|| anyhow!("Failed to split object to array; failed to get parent object of the spread array from output"))? =
-        to_value(array_of_objs.clone())?;
-    Ok(())
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use once_cell::sync::Lazy;
-    use serde_json::{json, Value};
-    use std::fs;
-    use std::sync::Mutex;
+    #[test]
+    fn test_flatten_array() {
+        assert!(flatten_array(vec![]).is_empty());
+        assert_eq!(
+            flatten_array(vec![json!(1), json!({"a": [2]})]),
+            vec![json!(1), json!({"a": [2]})]
+        );
+        assert_eq!(
+            flatten_array(vec![json!([1, 2]), json!([3, [4, []]])]),
+            vec![json!(1), json!(2), json!(3), json!(4)]
+        );
+    }
 
-    const OUTPUT_JSON_FILES_DIR: &str = "./test/output";
-    static INPUT_JSON_FILE: Lazy<Mutex<Value>> = Lazy::new(|| {
-        let input = fs::read_to_string("./test/input.json").expect("Unable to read input file");
-        Mutex::new(serde_json::from_str(&input).expect("Unable to parse input json file to value"))
-    });
+    #[test]
+    fn test_empty_to_null() {
+        let mut value = json!({"a": {}, "b": [], "c": [{}, 1], "d": {"e": []}, "f": "", "g": 0});
+        empty_to_null(&mut value);
+        assert_eq!(
+            value,
+            json!({"a": null, "b": null, "c": [null, 1], "d": {"e": null}, "f": "", "g": 0})
+        );
+
+        let mut value = json!([]);
+        empty_to_null(&mut value);
+        assert_eq!(value, Value::Null);
+    }
 
     #[test]
-    fn test_clean_key() {
-        assert_eq!(clean_key("").unwrap(), "");
-        assert_eq!(clean_key("key").unwrap(), "key");
-        assert_eq!(clean_key("...key").unwrap(), "key");
+    fn test_product_index() {
+        assert_eq!(product_index(0, &[]), Vec::<usize>::new());
+        assert_eq!(product_index(1, &[2]), vec![1]);
+        assert_eq!(
+            (0..6)
+                .map(|n| product_index(n, &[2, 3]))
+                .collect::<Vec<_>>(),
+            vec![
+                vec![0, 0],
+                vec![0, 1],
+                vec![0, 2],
+                vec![1, 0],
+                vec![1, 1],
+                vec![1, 2]
+            ]
+        );
     }
 
     #[test]
-    fn test_clean_path() {
-        assert_eq!(clean_path("").unwrap(), "");
-        assert_eq!(clean_path("/obj").unwrap(), "/obj");
-        assert_eq!(clean_path("/obj/obj").unwrap(), "/obj/obj");
-        assert_eq!(clean_path("obj/obj").unwrap(), "/obj/obj");
-        assert_eq!(clean_path("/[obj]/...array").unwrap(), "/obj/array");
-        assert_eq!(clean_path("/[obj]/obj/...array").unwrap(), "/obj/obj/array");
+    fn test_split_objects() {
+        let parent_obj = json!({"id": 1, "a": [1, 2], "b": {"c": ["x", "y", "z"]}});
+        let paths = vec!["/a".to_string(), "/b/c".to_string()];
+
+        let mut objs =
+            SplitObjects::new(parent_obj.clone(), paths.clone(), 3, &Default::default()).unwrap();
+        assert_eq!(objs.len(), 3);
+        assert_eq!(objs.next(), Some(json!({"id": 1, "a": 1, "b": {"c": "x"}})));
+        assert_eq!(objs.len(), 2);
+        assert_eq!(
+            objs.collect::<Vec<Value>>(),
+            vec![
+                json!({"id": 1, "a": 2, "b": {"c": "y"}}),
+                json!({"id": 1, "a": null, "b": {"c": "z"}})
+            ]
+        );
+
+        let options = TransformOptions {
+            spread_policy: SpreadPolicy::Product,
+            ..Default::default()
+        };
+        let objs = SplitObjects::new(parent_obj, paths, 3, &options).unwrap();
+        assert_eq!(objs.len(), 6);
+        assert_eq!(objs.last(), Some(json!({"id": 1, "a": 2, "b": {"c": "z"}})));
+
+        assert_eq!(
+            SplitObjects::new(json!({"a": 1}), vec!["/a".to_string()], 1, &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to split object to array; the spread field /a is not an array"
+        );
+        assert_eq!(SplitObjects::empty().next(), None);
+
+        let options = TransformOptions {
+            max_array_elements: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            SplitObjects::new(json!({"a": [1, 2, 3]}), vec!["/a".to_string()], 3, &options)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to split object to array; the array would have 3 elements, more than the max_array_elements cap of 2"
+        );
+        assert!(
+            SplitObjects::new(json!({"a": [1, 2]}), vec!["/a".to_string()], 2, &options).is_ok()
+        );
     }
 
     #[test]
-    fn test_is_obj_to_be_converted_to_array() {
-        assert_eq!(is_obj_to_be_converted_to_array("[obj]"), true);
-        assert_eq!(is_obj_to_be_converted_to_array("obj"), false);
-        assert_eq!(is_obj_to_be_converted_to_array("[obj"), false);
-        assert_eq!(is_obj_to_be_converted_to_array("obj]"), false);
+    fn test_take_spread_fields() {
+        let mut template = json!({
+            "id": "/id",
+            "...sku": "/skus",
+            "details": {"@when": "/details", "...qty": "/qtys", "note": "/note"},
+            "meta": {"source": "'edi'"},
+            "[items]": {"...item_id": "/item_ids"},
+            "lines": {"@each": "/lines", "...line": "/line"}
+        });
+
+        let skeleton = take_spread_fields(template.as_object_mut().unwrap());
+        assert_eq!(
+            Value::Object(skeleton),
+            json!({
+                "...sku": "/skus",
+                "details": {"@when": "/details", "...qty": "/qtys"}
+            })
+        );
+        assert_eq!(
+            template,
+            json!({
+                "id": "/id",
+                "details": {"@when": "/details", "note": "/note"},
+                "meta": {"source": "'edi'"},
+                "[items]": {"...item_id": "/item_ids"},
+                "lines": {"@each": "/lines", "...line": "/line"}
+            })
+        );
     }
 
     #[test]
-    fn test_is_to_be_spread_array() {
-        assert_eq!(is_to_be_spread_array("...array"), true);
-        assert_eq!(is_to_be_spread_array("array"), false);
+    fn test_collapse_array_to_obj() {
+        let value = json!({"id": 1, "items": [
+            {"sku": "a", "qty": 1, "source": "edi"},
+            {"sku": "b", "qty": 2, "source": "edi"},
+            {"qty": 3, "source": "edi"}
+        ]});
+
+        assert_eq!(
+            collapse_array_to_obj(&value, "items", &["/sku", "/qty"]).unwrap(),
+            json!({"id": 1, "items": {"sku": ["a", "b"], "qty": [1, 2, 3], "source": "edi"}})
+        );
+        let err = |value: &Value, fields: &[&str]| {
+            collapse_array_to_obj(value, "items", fields)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            err(&value, &["/color"]),
+            "Failed to collapse array to object; couldn't find the spread field /color in {\"qty\":1,\"sku\":\"a\",\"source\":\"edi\"}"
+        );
+        assert_eq!(
+            err(&json!({"items": []}), &["/sku"]),
+            "Failed to collapse array to object; the array items is empty"
+        );
+        assert_eq!(
+            err(&json!({"items": {"sku": "a"}}), &["/sku"]),
+            "Failed to collapse array to object; items should be an array: {\"sku\":\"a\"}"
+        );
+        assert_eq!(
+            err(&json!({"id": 1}), &["/sku"]),
+            "Failed to collapse array to object; couldn't find items in {\"id\":1}"
+        );
     }
 
     #[test]
-    fn test_format_key() {
-        assert_eq!(format_key("", ""), "");
-        assert_eq!(format_key("", "key"), "/key");
-        assert_eq!(format_key("/xpath/xpath", ""), "/xpath/xpath");
-        assert_eq!(format_key("/xpath", "key"), "/xpath/key");
+    fn test_split_then_collapse_is_identity() {
+        // a linear congruential generator, so that the generated cases are reproducible
+        let mut seed = 42u64;
+        let mut next = |bound: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % bound
+        };
+        for _ in 0..200 {
+            let len = 1 + next(6) as usize;
+            let mut parent_obj = json!({"id": next(1000), "details": {"source": "edi"}});
+            let mut paths = vec![];
+            for i in 0..1 + next(4) {
+                let values: Value = (0..len).map(|_| json!(next(100))).collect();
+                let (parent, path) = if next(2) == 0 {
+                    ("", format!("/spread_{}", i))
+                } else {
+                    ("/details", format!("/details/spread_{}", i))
+                };
+                parent_obj.pointer_mut(parent).unwrap()[format!("spread_{}", i)] = values;
+                paths.push(path);
+            }
+
+            let objs: Value =
+                SplitObjects::new(parent_obj.clone(), paths.clone(), len, &Default::default())
+                    .unwrap()
+                    .collect();
+            let fields: Vec<&str> = paths.iter().map(String::as_str).collect();
+            assert_eq!(
+                collapse_array_to_obj(&json!({"order": objs}), "order", &fields).unwrap(),
+                json!({"order": parent_obj})
+            );
+        }
     }
 
     #[test]
-    fn test_resolve_output_field_value_ok() {
-        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+    fn test_split_objects_skip_null() {
+        let options = TransformOptions {
+            spread_policy: SpreadPolicy::SkipNull,
+            ..Default::default()
+        };
+        let objs = SplitObjects::new(
+            json!({"id": 1, "a": [1, null, 3, 4], "b": {"c": ["x", "y", null]}}),
+            vec!["/a".to_string(), "/b/c".to_string()],
+            4,
+            &options,
+        )
+        .unwrap();
 
-        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+        // a null in any spread array skips the position, so does a missing element
+        assert_eq!(objs.len(), 1);
+        assert_eq!(
+            objs.collect::<Vec<Value>>(),
+            vec![json!({"id": 1, "a": 1, "b": {"c": "x"}})]
+        );
 
-        // regular field
-        input_path_tokens.push_back("ids");
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_ok());
+        let input = json!({"order_id": "1", "ids": ["34554543", null, "512342", null]});
+        let output =
+            json!([{"[items]": {"order_id": "/order_id", "...id": "/ids", "line": "@index"}}]);
         assert_eq!(
-            result.unwrap(),
-            Value::from(vec!["34554543", "7643534", "512342"])
+            crate::transform_with_options(&input, &output, &options).unwrap(),
+            json!([{"items": [
+                {"order_id": "1", "id": "34554543", "line": 0},
+                {"order_id": "1", "id": "512342", "line": 1}
+            ]}])
         );
+    }
 
-        // nested field
-        input_path_tokens.extend(["product", "details", "name"]);
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), Value::from("Red Shoes"));
+    #[test]
+    fn test_split_objects_pad() {
+        let parent_obj = json!({"id": 1, "a": [1, 2, 3], "b": {"c": ["x", null]}});
+        let paths = vec!["/a".to_string(), "/b/c".to_string()];
+        let options = TransformOptions {
+            spread_policy: SpreadPolicy::Pad,
+            ..Default::default()
+        };
 
-        // field in an array
-        input_path_tokens.extend(["order", "shipments", "tracking_number"]);
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), Value::from(vec!["1234567", "98776"]));
+        // the array takes the length of the longest spread array, whatever the one passed
+        assert_eq!(
+            SplitObjects::new(parent_obj.clone(), paths.clone(), 2, &options)
+                .unwrap()
+                .collect::<Vec<Value>>(),
+            vec![
+                json!({"id": 1, "a": 1, "b": {"c": "x"}}),
+                json!({"id": 1, "a": 2, "b": {"c": null}}),
+                json!({"id": 1, "a": 3, "b": {"c": null}})
+            ]
+        );
 
-        // field in an array of arrays
-        input_path_tokens.extend(["order", "shipments", "items"]);
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_ok());
+        let options = TransformOptions {
+            trim_trailing_nulls: true,
+            ..options
+        };
         assert_eq!(
-            result.unwrap(),
-            Value::from(vec![
-                json!({
-                  "sku": "SKU-123",
-                  "quantity": 4
-                }),
-                json!(    {
-                  "sku": "SKU-343",
-                  "quantity": 3
-                }),
-                json!({
-                  "sku": "SKU-1453",
-                  "quantity": 1
-                }),
-                json!({
-                  "sku": "SKU-543",
-                  "quantity": 1
-                })
-            ])
+            SplitObjects::new(parent_obj.clone(), paths.clone(), 2, &options)
+                .unwrap()
+                .collect::<Vec<Value>>(),
+            vec![
+                json!({"id": 1, "a": 1, "b": {"c": "x"}}),
+                json!({"id": 1, "a": 2, "b": {}}),
+                json!({"id": 1, "a": 3, "b": {}})
+            ]
+        );
+        let objs = SplitObjects::new(
+            json!({"a": [1, null, null], "b": ["x", "y", null]}),
+            vec!["/a".to_string(), "/b".to_string()],
+            3,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            objs.collect::<Vec<Value>>(),
+            vec![json!({"a": 1, "b": "x"}), json!({"b": "y"})]
         );
 
-        // field in an array of arrays of objs
-        input_path_tokens.extend(["order", "shipments", "items", "sku"]);
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_ok());
+        // the trimmed fields are left out with the zip policy too, the array length is kept
+        let options = TransformOptions {
+            trim_trailing_nulls: true,
+            ..Default::default()
+        };
         assert_eq!(
-            result.unwrap(),
-            Value::from(vec!["SKU-123", "SKU-343", "SKU-1453", "SKU-543"])
+            SplitObjects::new(parent_obj, paths, 2, &options)
+                .unwrap()
+                .collect::<Vec<Value>>(),
+            vec![
+                json!({"id": 1, "a": 1, "b": {"c": "x"}}),
+                json!({"id": 1, "a": 2, "b": {}})
+            ]
         );
     }
 
     #[test]
-    fn test_resolve_output_field_value_err() {
-        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+    fn test_process_array_convertible_objs_scoped() {
+        let mut output = json!({
+            "[a]": {"...x": [1, 2], "...y": ["p", "q"]},
+            "[b]": {"...z": [1, 2, 3]},
+            "[c]": {"...u": [1, 2], "[d]": {"...v": [1, 2, 3], "...w": [4, 5, 6]}}
+        });
+        process_array_convertible_objs(
+            &output.clone(),
+            &mut output,
+            "",
+            "",
+            &mut LinkedList::new(),
+            &mut LinkedList::new(),
+            &[],
+            &Default::default(),
+        )
+        .unwrap();
 
-        let mut input_path_tokens: LinkedList<&str> = LinkedList::new();
+        assert_eq!(
+            output,
+            json!({
+                "a": [{"x": 1, "y": "p"}, {"x": 2, "y": "q"}],
+                "b": [{"z": 1}, {"z": 2}, {"z": 3}],
+                "c": [
+                    {"u": 1, "d": [{"v": 1, "w": 4}, {"v": 2, "w": 5}, {"v": 3, "w": 6}]},
+                    {"u": 2, "d": [{"v": 1, "w": 4}, {"v": 2, "w": 5}, {"v": 3, "w": 6}]}
+                ]
+            })
+        );
 
-        // field in an obj
-        input_path_tokens.push_back("idsss");
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_err());
+        // the spread arrays of a sibling don't make up for a missing spread array
+        let mut output = json!({"[a]": {"...x": [1, 2], "...y": [3, 4]}, "[b]": {"z": 1}});
         assert_eq!(
-            result.err().unwrap().to_string(),
-            anyhow!(
-                "Failed to resolve mapping value; couldn't find field name idsss in the obj {}",
-                serde_json::to_string_pretty(&input).unwrap()
+            process_array_convertible_objs(
+                &output.clone(),
+                &mut output,
+                "",
+                "",
+                &mut LinkedList::new(),
+                &mut LinkedList::new(),
+                &[],
+                &Default::default(),
             )
-            .to_string()
+            .err()
+            .unwrap()
+            .to_string(),
+            "Failed to process array convertible object; a array convertible object [b] is detected but no spread array field was found"
         );
+    }
+
+    #[test]
+    fn test_process_array_convertible_objs_scalar_spread() {
+        let process = |mut output: Value| {
+            process_array_convertible_objs(
+                &output.clone(),
+                &mut output,
+                "",
+                "",
+                &mut LinkedList::new(),
+                &mut LinkedList::new(),
+                &[],
+                &Default::default(),
+            )
+            .map(|_| output)
+            .map_err(|err| err.to_string())
+        };
 
-        // field in an array of objs
-        input_path_tokens.extend(["order", "shipments", "tracking_nomber"]);
-        let result = resolve_output_field_value(&mut input_path_tokens, &input);
-        assert!(result.is_err());
         assert_eq!(
-            result.err().unwrap().to_string(),
-            anyhow!(
-                "Failed to resolve mapping value; couldn't find field name tracking_nomber in the obj {}",
-                serde_json::to_string_pretty(&json!({
-        "tracking_number" : "1234567",
-        "items": [
-          {
-            "sku": "SKU-123",
-            "quantity": 4
-          },
-          {
-            "sku": "SKU-343",
-            "quantity": 3
-          }
-        ]
-      })).unwrap()).to_string());
+            process(json!({"[order]": {"...ids": [1, 2], "...po": "573832"}})),
+            Err("Failed to process array convertible object; the spread field /[order]/...po should resolve to an array, not a string: \"573832\"".to_string())
+        );
+        assert_eq!(
+            process(json!({"[order]": {"...ids": [1], "details": {"...active": true}}})),
+            Err("Failed to process array convertible object; the spread field /[order]/details/...active should resolve to an array, not a boolean: true".to_string())
+        );
+        // a missing optional array is let through, and so are the spreads outside of `[obj]`
+        assert_eq!(
+            process(json!({"[order]": {"...ids": [1, 2], "...tags": null}})),
+            Ok(json!({"order": [{"ids": 1, "tags": null}, {"ids": 2, "tags": null}]}))
+        );
+        assert_eq!(
+            process(json!({"order": {"...id": 1}})),
+            Ok(json!({"order": {"id": 1}}))
+        );
     }
 
     #[test]
-    fn test_traverse_mut_ok() {
-        let input = INPUT_JSON_FILE.lock().unwrap().clone();
-        let output = fs::read_to_string(&format!("{}/default.json", OUTPUT_JSON_FILES_DIR))
-            .expect("Unable to read file");
-        let mut output: Value =
-            serde_json::from_str(&output).expect("Unable to parse input json file to value");
+    fn test_process_array_convertible_objs_unusual_keys() {
+        let process = |mut output: Value| {
+            process_array_convertible_objs(
+                &output.clone(),
+                &mut output,
+                "",
+                "",
+                &mut LinkedList::new(),
+                &mut LinkedList::new(),
+                &[],
+                &Default::default(),
+            )
+            .map(|_| output)
+            .map_err(|err| err.to_string())
+        };
 
-        let result = traverse_mut(&input, &mut output.get_mut(0).unwrap(), "", "");
-        let expected_transformed_output = fs::read_to_string(&format!(
-            "{}/transformed/default.json",
-            OUTPUT_JSON_FILES_DIR
-        ))
-        .expect(&format!(
-            "Unable to read file {}/transformed/default.json",
-            OUTPUT_JSON_FILES_DIR
-        ));
-        let expected_transformed_output: Value = serde_json::from_str(&expected_transformed_output)
-            .expect(&format!(
-                "Unable to parse file {}/transformed/default.json",
-                OUTPUT_JSON_FILES_DIR
-            ));
-        assert!(result.is_ok());
-        assert_eq!(output, expected_transformed_output);
+        // the unclosed bracket doesn't swallow the spread key, and combined decorations are cleaned
+        assert_eq!(
+            process(json!({"[order]": {"price[usd": {"...amounts": [1, 2]}}})),
+            Ok(json!({"order": [{"price[usd": {"amounts": 1}}, {"price[usd": {"amounts": 2}}]}))
+        );
+        assert_eq!(
+            process(json!({"[order]": {"[...ids]": [1, 2]}})),
+            Ok(json!({"order": [{"ids": 1}, {"ids": 2}]}))
+        );
+    }
+
+    #[test]
+    fn test_convert_array_convertible_objs() {
+        let mut output = json!({"[a]": {"...x": [1, 2]}, "b": {"[c]": {"...y": [3]}}});
+        convert_array_convertible_objs(&mut output, &[], &Default::default()).unwrap();
+        assert_eq!(
+            output,
+            json!({"a": [{"x": 1}, {"x": 2}], "b": {"c": [{"y": 3}]}})
+        );
+
+        // `[a]` is converted before `[b]` fails, the output is left as it was
+        let original = json!({"[a]": {"...x": [1, 2]}, "[b]": {"...y": [3], "...z": "4"}});
+        let mut output = original.clone();
+        assert!(
+            convert_array_convertible_objs(&mut output, &[], &Default::default())
+                .unwrap_err()
+                .to_string()
+                .starts_with(
+                    "Failed to process array convertible object; the spread field /[b]/...z"
+                )
+        );
+        assert_eq!(output, original);
+    }
+
+    #[test]
+    fn test_process_array_convertible_objs_object_spread() {
+        let mut output = json!({
+            "[order]": {
+                "...items": [{"sku": "SKU-123", "quantity": 4}, {"sku": "SKU-343", "quantity": 3}],
+                "...ids": ["34554543", "7643534"],
+                "[lines]": {"...line": [[{"sku": "a"}], [{"sku": "b", "...x": 1}]]}
+            }
+        });
+        process_array_convertible_objs(
+            &output.clone(),
+            &mut output,
+            "",
+            "",
+            &mut LinkedList::new(),
+            &mut LinkedList::new(),
+            &[],
+            &Default::default(),
+        )
+        .unwrap();
+
+        // each object of the spread array goes whole into its own object, the keys of the spread
+        // objects are data and are left as is
+        let lines = json!([{"line": {"sku": "a"}}, {"line": {"sku": "b", "...x": 1}}]);
+        assert_eq!(
+            output,
+            json!({
+                "order": [
+                    {"items": {"sku": "SKU-123", "quantity": 4}, "ids": "34554543", "lines": lines},
+                    {"items": {"sku": "SKU-343", "quantity": 3}, "ids": "7643534", "lines": lines}
+                ]
+            })
+        );
     }
 
     #[test]
     fn test_traverse_mut_err() {
         let input = INPUT_JSON_FILE.lock().unwrap().clone();
 
-        let mut output = json!([[]]);
-        let result = traverse_mut(&input, &mut output, "", "");
+        let mut output = json!([[1]]);
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
 
         assert!(result.is_err());
         assert_eq!(
             result.err().unwrap().to_string(),
-            "Traversing output object failed; output object field should be string: [[]]"
+            "Traversing output object failed; output object field should be string: 1"
+        )
+    }
+
+    #[test]
+    fn test_traverse_mut_err_field_pointer() {
+        let input = INPUT_JSON_FILE.lock().unwrap().clone();
+
+        let mut output = json!({"order": {"a/b": {"id": "/product/idd"}}});
+        let result = traverse_mut(&input, &mut output, "", "", &Default::default());
+
+        assert!(result.is_err());
+        assert_eq!(
+            result
+                .err()
+                .unwrap()
+                .downcast_ref::<FieldError>()
+                .unwrap()
+                .pointer,
+            "/order/a~1b/id"
         )
     }
 }
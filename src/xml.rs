@@ -0,0 +1,206 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+};
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+/// Prefix of the object keys that are written as attributes of the object element instead of
+/// child elements, e.g. `{"order": {"@id": 1}}` is written as `<order id="1"/>`.
+pub const XML_ATTRIBUTE_PREFIX: &str = "@";
+// name of the document element, the output objects are its children
+const XML_ROOT_ELEMENT: &str = "root";
+// name of the elements of values that have no key, e.g. the elements of a root array
+const XML_ITEM_ELEMENT: &str = "item";
+
+// Serializes the transformed output to an XML document. The fields of every output array element
+// are written as children of the `root` element, object keys become elements, array elements
+// become repeated elements of the array key and scalars become the element text.
+pub fn to_xml(value: &Value) -> Result<String> {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str(&format!("<{}>", XML_ROOT_ELEMENT));
+    let elements = match value {
+        Value::Array(elements) => elements.iter().collect(),
+        value => vec![value],
+    };
+    for element in elements {
+        match element {
+            Value::Object(obj) => {
+                if let Some(key) = obj.keys().find(|k| k.starts_with(XML_ATTRIBUTE_PREFIX)) {
+                    bail!(
+                        "Failed to convert to XML; the output object {} can't be an attribute",
+                        key
+                    );
+                }
+                write_children(obj, &mut xml)?;
+            }
+            element => write_element(XML_ITEM_ELEMENT, element, &mut xml)?,
+        }
+    }
+    xml.push_str(&format!("</{}>", XML_ROOT_ELEMENT));
+    Ok(xml)
+}
+
+// writes the value as element(s) named after its key, arrays are written as one element per array
+// element and nested arrays are flattened
+fn write_element(name: &str, value: &Value, xml: &mut String) -> Result<()> {
+    if let Value::Array(elements) = value {
+        for element in elements {
+            write_element(name, element, xml)?;
+        }
+        return Ok(());
+    }
+    if !is_valid_name(name) {
+        bail!(
+            "Failed to convert to XML; {} is not a valid element name",
+            name
+        );
+    }
+    xml.push('<');
+    xml.push_str(name);
+    match value {
+        Value::Null => xml.push_str("/>"),
+        Value::Object(obj) => {
+            for (key, v) in obj {
+                if let Some(attribute) = key.strip_prefix(XML_ATTRIBUTE_PREFIX) {
+                    write_attribute(attribute, v, xml)?;
+                }
+            }
+            if obj.keys().all(|k| k.starts_with(XML_ATTRIBUTE_PREFIX)) {
+                xml.push_str("/>");
+            } else {
+                xml.push('>');
+                write_children(obj, xml)?;
+                xml.push_str(&format!("</{}>", name));
+            }
+        }
+        scalar => {
+            xml.push('>');
+            xml.push_str(&escape(&scalar_text(scalar)));
+            xml.push_str(&format!("</{}>", name));
+        }
+    }
+    Ok(())
+}
+
+// writes the fields of the object that are not attributes as child elements
+fn write_children(obj: &Map<String, Value>, xml: &mut String) -> Result<()> {
+    for (key, v) in obj
+        .iter()
+        .filter(|(key, _)| !key.starts_with(XML_ATTRIBUTE_PREFIX))
+    {
+        write_element(key, v, xml)?;
+    }
+    Ok(())
+}
+
+// writes a scalar as attribute of the open element tag, null attributes are left out
+fn write_attribute(name: &str, value: &Value, xml: &mut String) -> Result<()> {
+    if !is_valid_name(name) {
+        bail!(
+            "Failed to convert to XML; {} is not a valid attribute name",
+            name
+        );
+    }
+    match value {
+        Value::Null => {}
+        Value::Array(_) | Value::Object(_) => bail!(
+            "Failed to convert to XML; the attribute {} should be a string, a number or a bool: {}",
+            name,
+            value
+        ),
+        scalar => xml.push_str(&format!(" {}=\"{}\"", name, escape(&scalar_text(scalar)))),
+    }
+    Ok(())
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+// an XML name starts with a letter or `_`, followed by letters, digits, `-`, `_` or `.`
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_xml() {
+        let value = json!([{
+            "order": {
+                "@id": 1,
+                "@note": null,
+                "po_number": "573832",
+                "shipped": true,
+                "discount": null,
+                "items": [{"@sku": "SKU-123"}, {"@sku": "SKU-343", "quantity": 3}],
+                "tags": [["a", "b"], "c"],
+                "empty": []
+            }
+        }]);
+
+        assert_eq!(
+            to_xml(&value).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?><root><order id="1"><discount/><items sku="SKU-123"/><items sku="SKU-343"><quantity>3</quantity></items><po_number>573832</po_number><shipped>true</shipped><tags>a</tags><tags>b</tags><tags>c</tags></order></root>"#
+        );
+    }
+
+    #[test]
+    fn test_to_xml_root_array() {
+        assert_eq!(
+            to_xml(&json!([1, "a & b"])).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?><root><item>1</item><item>a &amp; b</item></root>"#
+        );
+    }
+
+    #[test]
+    fn test_to_xml_err() {
+        assert_eq!(
+            to_xml(&json!([{"order": {"po number": "573832"}}]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to convert to XML; po number is not a valid element name"
+        );
+        assert_eq!(
+            to_xml(&json!([{"order": {"@id": {"value": 1}}}]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to convert to XML; the attribute id should be a string, a number or a bool: {\"value\":1}"
+        );
+        assert_eq!(
+            to_xml(&json!([{"@order": 1}])).err().unwrap().to_string(),
+            "Failed to convert to XML; the output object @order can't be an attribute"
+        );
+    }
+}
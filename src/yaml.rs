@@ -0,0 +1,60 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+// Parses a single YAML document into a JSON value, `name` tells the input from the output in the
+// errors. Multi-document streams are rejected since the transform takes one input and one output.
+pub fn parse_yaml(content: &str, name: &str) -> Result<Value> {
+    let mut documents = serde_yaml::Deserializer::from_str(content);
+    let value = match documents.next() {
+        Some(document) => Value::deserialize(document)
+            .with_context(|| format!("Failed to parse {} YAML", name))?,
+        None => bail!("Failed to parse {} YAML; it has no document", name),
+    };
+    let extra_documents = documents.count();
+    if extra_documents > 0 {
+        bail!(
+            "Failed to parse {} YAML; multi-document YAML is not supported, found {} documents",
+            name,
+            extra_documents + 1
+        );
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_yaml() {
+        assert_eq!(
+            parse_yaml("order:\n  id: 1\n  skus: [a, b]\n", "input").unwrap(),
+            json!({"order": {"id": 1, "skus": ["a", "b"]}})
+        );
+        assert_eq!(
+            parse_yaml("---\norder: 1\n", "input").unwrap(),
+            json!({"order": 1})
+        );
+        assert_eq!(parse_yaml("", "input").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_parse_yaml_err() {
+        assert_eq!(
+            parse_yaml("order: 1\n---\norder: 2\n", "input")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to parse input YAML; multi-document YAML is not supported, found 2 documents"
+        );
+        assert_eq!(
+            parse_yaml("order: [1\n", "output")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Failed to parse output YAML"
+        );
+    }
+}